@@ -0,0 +1,26 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use smolder_proto::smb::smb2::{
+    ChangeNotifyResponse, CloseResponse, CreateResponse, IoctlResponse, NegotiateResponse,
+    QueryDirectoryResponse, QueryInfoResponse, ReadResponse, SessionSetupResponse,
+    TreeConnectResponse, WriteResponse,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = NegotiateResponse::decode(data);
+    if let Ok(response) = QueryDirectoryResponse::decode(data) {
+        let _ = response.directory_entries();
+    }
+    let _ = SessionSetupResponse::decode(data);
+    let _ = TreeConnectResponse::decode(data);
+    let _ = CreateResponse::decode(data);
+    let _ = CloseResponse::decode(data);
+    let _ = ReadResponse::decode(data);
+    let _ = WriteResponse::decode(data);
+    let _ = QueryInfoResponse::decode(data);
+    let _ = ChangeNotifyResponse::decode(data);
+    if let Ok(response) = IoctlResponse::decode(data) {
+        let _ = response.dfs_referral_response();
+    }
+});
@@ -11,6 +11,7 @@ use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 
 use smolder_proto::smb::smb2::NegotiateResponse;
+use zeroize::Zeroize;
 
 #[cfg(all(unix, feature = "kerberos-gssapi"))]
 use super::kerberos_gssapi::GssapiKerberosBackend;
@@ -78,6 +79,12 @@ impl fmt::Debug for KerberosCredentials {
     }
 }
 
+impl Drop for KerberosCredentials {
+    fn drop(&mut self) {
+        self.password.zeroize();
+    }
+}
+
 impl KerberosCredentials {
     /// Creates password-backed Kerberos credentials for an SMB account.
     #[cfg(feature = "kerberos-sspi")]
@@ -404,6 +411,12 @@ struct KerberosAuthEngine<B: KerberosBackend> {
     _backend: PhantomData<B>,
 }
 
+impl<B: KerberosBackend> Drop for KerberosAuthEngine<B> {
+    fn drop(&mut self) {
+        self.session_key.zeroize();
+    }
+}
+
 impl<B: KerberosBackend> KerberosAuthEngine<B> {
     fn new(credentials: KerberosCredentials, target: KerberosTarget) -> Self {
         Self {
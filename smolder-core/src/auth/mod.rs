@@ -31,7 +31,9 @@ pub use kerberos::{
     doc(cfg(any(feature = "kerberos", feature = "kerberos-gssapi")))
 )]
 pub use kerberos_spn::KerberosTarget;
-pub use ntlm::{NtlmAuthenticator, NtlmCredentials};
+#[cfg(test)]
+pub(crate) use ntlm::test_challenge_token;
+pub use ntlm::{NtlmAuthenticator, NtlmCredentials, NtlmServerInfo};
 pub use ntlm_rpc::{NtlmRpcPacketIntegrity, NtlmSessionSecurity};
 pub(crate) use ntlm_rpc_bind::NtlmRpcBindHandshake;
 
@@ -84,4 +86,9 @@ pub trait AuthProvider {
     fn session_key(&self) -> Option<&[u8]> {
         None
     }
+
+    /// Returns the server identity from an NTLM challenge, if one was received.
+    fn ntlm_server_info(&self) -> Option<&NtlmServerInfo> {
+        None
+    }
 }
@@ -1,15 +1,18 @@
 //! NTLMv2 message generation for SMB session setup.
 
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::fmt;
+use std::time::SystemTime;
 
 use bitflags::bitflags;
 use hmac::{Hmac, Mac};
 use md4::{Digest as _, Md4};
 use md5::Md5;
 use rand::random;
+use zeroize::Zeroize;
 
-use smolder_proto::smb::smb2::utf16le;
+use smolder_proto::smb::filetime::system_time_to_filetime;
 use smolder_proto::smb::smb2::NegotiateResponse;
+use smolder_proto::smb::smb2::{utf16le, utf16le_string};
 
 use super::spnego::{
     encode_mech_type_list, encode_neg_token_init, encode_neg_token_resp, encode_neg_token_resp_ntlm,
@@ -22,8 +25,7 @@ const NTLMSSP_SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
 const NTLM_MESSAGE_NEGOTIATE: u32 = 1;
 const NTLM_MESSAGE_CHALLENGE: u32 = 2;
 const NTLM_MESSAGE_AUTHENTICATE: u32 = 3;
-const WINDOWS_TICK: u64 = 10_000_000;
-const SEC_TO_UNIX_EPOCH: u64 = 11_644_473_600;
+const NTLMSSP_REVISION_W2K3: u8 = 0x0f;
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     struct NegotiateFlags: u32 {
@@ -32,6 +34,7 @@ bitflags! {
         const SIGN = 0x0000_0010;
         const SEAL = 0x0000_0020;
         const NTLM = 0x0000_0200;
+        const ANONYMOUS = 0x0000_0800;
         const ALWAYS_SIGN = 0x0000_8000;
         const TARGET_TYPE_DOMAIN = 0x0001_0000;
         const TARGET_TYPE_SERVER = 0x0002_0000;
@@ -49,18 +52,15 @@ struct AvId(u16);
 
 impl AvId {
     const EOL: Self = Self(0x0000);
-    #[cfg(test)]
     const NB_COMPUTER_NAME: Self = Self(0x0001);
-    #[cfg(test)]
     const NB_DOMAIN_NAME: Self = Self(0x0002);
     const DNS_COMPUTER_NAME: Self = Self(0x0003);
-    #[cfg(test)]
     const DNS_DOMAIN_NAME: Self = Self(0x0004);
+    const DNS_TREE_NAME: Self = Self(0x0005);
     const TIMESTAMP: Self = Self(0x0007);
     #[cfg(test)]
     const SINGLE_HOST: Self = Self(0x0008);
     const TARGET_NAME: Self = Self(0x0009);
-    #[cfg(test)]
     const CHANNEL_BINDINGS: Self = Self(0x000a);
 }
 
@@ -86,6 +86,7 @@ struct ChallengeMessage {
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct NegotiateMessage {
     flags: NegotiateFlags,
+    version: Option<[u8; 8]>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -109,13 +110,93 @@ enum NtlmState {
     Complete,
 }
 
+/// Server identity advertised in an NTLM challenge message.
+///
+/// SMB2 session setup carries no native OS or primary domain strings; servers
+/// report their names in the challenge target info and, when negotiated, their
+/// Windows version in the challenge `VERSION` field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NtlmServerInfo {
+    /// NetBIOS computer name of the server.
+    pub netbios_computer_name: Option<String>,
+    /// NetBIOS domain or workgroup name of the server.
+    pub netbios_domain_name: Option<String>,
+    /// Fully qualified DNS name of the server.
+    pub dns_computer_name: Option<String>,
+    /// DNS domain name of the server.
+    pub dns_domain_name: Option<String>,
+    /// DNS forest name of the server.
+    pub dns_tree_name: Option<String>,
+    /// Windows major version, minor version, and build number.
+    pub os_version: Option<(u8, u8, u16)>,
+}
+
+impl NtlmServerInfo {
+    fn from_challenge(challenge_message: &[u8], challenge: &ChallengeMessage) -> Self {
+        let name = |av_id| {
+            challenge
+                .target_info
+                .iter()
+                .find(|pair| pair.av_id == av_id)
+                .and_then(|pair| utf16le_string(&pair.value).ok())
+        };
+        let os_version = challenge_message
+            .get(48..56)
+            .filter(|_| challenge.flags.contains(NegotiateFlags::VERSION))
+            .map(|version| {
+                (
+                    version[0],
+                    version[1],
+                    u16::from_le_bytes([version[2], version[3]]),
+                )
+            });
+        Self {
+            netbios_computer_name: name(AvId::NB_COMPUTER_NAME),
+            netbios_domain_name: name(AvId::NB_DOMAIN_NAME),
+            dns_computer_name: name(AvId::DNS_COMPUTER_NAME),
+            dns_domain_name: name(AvId::DNS_DOMAIN_NAME),
+            dns_tree_name: name(AvId::DNS_TREE_NAME),
+            os_version,
+        }
+    }
+}
+
 /// Username, password, and optional domain/workstation information for NTLM.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// The password, or the NT hash standing in for it, is overwritten with zeros
+/// when the credentials are dropped.
+#[derive(Clone, PartialEq, Eq)]
 pub struct NtlmCredentials {
     username: String,
     password: String,
+    nt_hash: Option<[u8; 16]>,
     domain: String,
     workstation: String,
+    os_version: Option<[u8; 8]>,
+    target_name: Option<String>,
+    channel_bindings: Option<[u8; 16]>,
+}
+
+impl fmt::Debug for NtlmCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NtlmCredentials")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("nt_hash", &self.nt_hash.map(|_| "<redacted>"))
+            .field("domain", &self.domain)
+            .field("workstation", &self.workstation)
+            .field("os_version", &self.os_version)
+            .field("target_name", &self.target_name)
+            .field("channel_bindings", &self.channel_bindings)
+            .finish()
+    }
+}
+
+impl Drop for NtlmCredentials {
+    fn drop(&mut self) {
+        self.password.zeroize();
+        self.nt_hash.zeroize();
+    }
 }
 
 impl NtlmCredentials {
@@ -124,11 +205,75 @@ impl NtlmCredentials {
         Self {
             username: username.into(),
             password: password.into(),
+            nt_hash: None,
             domain: String::new(),
             workstation: String::new(),
+            os_version: None,
+            target_name: None,
+            channel_bindings: None,
         }
     }
 
+    /// Creates credentials that authenticate with an NT hash instead of a password.
+    ///
+    /// The hash is the MD4 of the UTF-16LE password, as stored by Windows
+    /// account databases, and is used directly as the NTLMv2 key input.
+    #[must_use]
+    pub fn from_nt_hash(username: impl Into<String>, nt_hash: [u8; 16]) -> Self {
+        let mut credentials = Self::new(username, "");
+        credentials.nt_hash = Some(nt_hash);
+        credentials
+    }
+
+    /// Creates empty credentials for an anonymous (null session) logon.
+    #[must_use]
+    pub fn anonymous() -> Self {
+        Self::new("", "")
+    }
+
+    /// Returns true when these credentials describe an anonymous logon.
+    #[must_use]
+    pub fn is_anonymous(&self) -> bool {
+        self.username.is_empty() && self.password.is_empty() && self.nt_hash.is_none()
+    }
+
+    /// Loads credentials from the `SMOLDER_SMB_USERNAME`, `SMOLDER_SMB_PASSWORD`,
+    /// `SMOLDER_SMB_DOMAIN`, and `SMOLDER_SMB_WORKSTATION` environment variables.
+    ///
+    /// Returns `None` when the username or password is unset. The domain and
+    /// workstation are optional.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let value = |key| lookup(key).filter(|value: &String| !value.is_empty());
+        let mut credentials = Self::new(
+            value("SMOLDER_SMB_USERNAME")?,
+            value("SMOLDER_SMB_PASSWORD")?,
+        );
+        if let Some(domain) = value("SMOLDER_SMB_DOMAIN") {
+            credentials = credentials.with_domain(domain);
+        }
+        if let Some(workstation) = value("SMOLDER_SMB_WORKSTATION") {
+            credentials = credentials.with_workstation(workstation);
+        }
+        Some(credentials)
+    }
+
+    /// Returns the account name.
+    #[must_use]
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Returns the NTLM domain component, which may be empty.
+    #[must_use]
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
     /// Sets the NTLM domain component.
     #[must_use]
     pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
@@ -142,6 +287,49 @@ impl NtlmCredentials {
         self.workstation = workstation.into();
         self
     }
+
+    /// Advertises a Windows version in the negotiate and authenticate messages.
+    ///
+    /// SMB2 has no native OS or LAN Manager strings; the NTLM `VERSION` field
+    /// is what servers log as the client OS. It is omitted unless set, and
+    /// `build` is the Windows build number, for example `19041`.
+    #[must_use]
+    pub fn with_os_version(mut self, major: u8, minor: u8, build: u16) -> Self {
+        let [build_low, build_high] = build.to_le_bytes();
+        self.os_version = Some([
+            major,
+            minor,
+            build_low,
+            build_high,
+            0,
+            0,
+            0,
+            NTLMSSP_REVISION_W2K3,
+        ]);
+        self
+    }
+
+    /// Sets the service principal name sent in the `MsvAvTargetName` AV pair.
+    ///
+    /// By default the name is `cifs/` followed by the DNS host name from the
+    /// server challenge. Servers enforcing Extended Protection compare it with
+    /// their own SPNs, so set it when connecting through an alias or address.
+    #[must_use]
+    pub fn with_target_name(mut self, target_name: impl Into<String>) -> Self {
+        self.target_name = Some(target_name.into());
+        self
+    }
+
+    /// Sets the channel-binding hash sent in the `MsvAvChannelBindings` AV pair.
+    ///
+    /// The hash is the MD5 of the `gss_channel_bindings_struct` describing the
+    /// outer secure channel, as defined by RFC 5929 for TLS. SMB over TCP has
+    /// no outer channel, so this is only needed when the session is tunnelled.
+    #[must_use]
+    pub fn with_channel_bindings(mut self, channel_bindings: [u8; 16]) -> Self {
+        self.channel_bindings = Some(channel_bindings);
+        self
+    }
 }
 
 /// NTLMv2 authentication provider for SMB `SESSION_SETUP`.
@@ -153,6 +341,14 @@ pub struct NtlmAuthenticator {
     state: NtlmState,
     session_key: Option<[u8; 16]>,
     exported_session_key_override: Option<[u8; 16]>,
+    server_info: Option<NtlmServerInfo>,
+}
+
+impl Drop for NtlmAuthenticator {
+    fn drop(&mut self) {
+        self.session_key.zeroize();
+        self.exported_session_key_override.zeroize();
+    }
 }
 
 impl NtlmAuthenticator {
@@ -165,6 +361,7 @@ impl NtlmAuthenticator {
             state: NtlmState::Initial,
             session_key: None,
             exported_session_key_override: None,
+            server_info: None,
         }
     }
 
@@ -182,6 +379,12 @@ impl NtlmAuthenticator {
         self
     }
 
+    /// Returns the server identity from the challenge message, once one was received.
+    #[must_use]
+    pub fn server_info(&self) -> Option<&NtlmServerInfo> {
+        self.server_info.as_ref()
+    }
+
     /// Overrides the exported session key used when NTLM key exchange is negotiated.
     #[cfg(test)]
     #[must_use]
@@ -191,7 +394,13 @@ impl NtlmAuthenticator {
     }
 
     fn negotiate_flags(&self) -> NegotiateFlags {
-        NegotiateFlags::UNICODE
+        let version = if self.credentials.os_version.is_some() {
+            NegotiateFlags::VERSION
+        } else {
+            NegotiateFlags::empty()
+        };
+        version
+            | NegotiateFlags::UNICODE
             | NegotiateFlags::REQUEST_TARGET
             | NegotiateFlags::SIGN
             | NegotiateFlags::SEAL
@@ -219,6 +428,7 @@ impl AuthProvider for NtlmAuthenticator {
 
         let negotiate = NegotiateMessage {
             flags: self.negotiate_flags(),
+            version: self.credentials.os_version,
         };
         let negotiate_message = negotiate.encode();
         if ntlm_debug_enabled() {
@@ -252,15 +462,20 @@ impl AuthProvider for NtlmAuthenticator {
                     return Err(AuthError::InvalidToken("authentication was rejected"));
                 }
 
-                let session_key = self.session_key.ok_or(AuthError::InvalidState(
-                    "session key missing during completion",
-                ))?;
-                let mech_list_mic = mech_list_mic(session_key, *flags);
+                let mech_list_mic = match self.session_key {
+                    Some(session_key) => Some(mech_list_mic(session_key, *flags)),
+                    None if flags.contains(NegotiateFlags::ANONYMOUS) => None,
+                    None => {
+                        return Err(AuthError::InvalidState(
+                            "session key missing during completion",
+                        ))
+                    }
+                };
                 self.state = NtlmState::Complete;
                 return Ok(encode_neg_token_resp(
                     Some(NEG_STATE_ACCEPT_COMPLETE),
                     None,
-                    Some(&mech_list_mic),
+                    mech_list_mic.as_ref().map(|mic| mic.as_slice()),
                 ));
             }
             NtlmState::Complete => {
@@ -271,6 +486,10 @@ impl AuthProvider for NtlmAuthenticator {
         let negotiate_flags = NegotiateMessage::decode(&negotiate_message)?.flags;
         let challenge_message = extract_mech_token(incoming)?;
         let challenge = ChallengeMessage::decode(&challenge_message)?;
+        self.server_info = Some(NtlmServerInfo::from_challenge(
+            &challenge_message,
+            &challenge,
+        ));
         if ntlm_debug_enabled() {
             eprintln!(
                 "ntlm type2 flags=0x{:08x} challenge={} len={} av_pairs={} token={}",
@@ -306,7 +525,7 @@ impl AuthProvider for NtlmAuthenticator {
         }
         let token = encode_neg_token_resp_ntlm(&authenticate_message);
 
-        self.session_key = Some(session_key);
+        self.session_key = session_key;
         self.state = NtlmState::WaitingForCompletion {
             flags: authenticate.flags,
         };
@@ -335,6 +554,10 @@ impl AuthProvider for NtlmAuthenticator {
     fn session_key(&self) -> Option<&[u8]> {
         self.session_key.as_ref().map(|value| value.as_slice())
     }
+
+    fn ntlm_server_info(&self) -> Option<&NtlmServerInfo> {
+        self.server_info.as_ref()
+    }
 }
 
 impl NegotiateMessage {
@@ -352,7 +575,7 @@ impl NegotiateMessage {
         out.extend_from_slice(&0u16.to_le_bytes());
         out.extend_from_slice(&0u32.to_le_bytes());
         if include_version {
-            out.extend_from_slice(&default_version_bytes());
+            out.extend_from_slice(&self.version.unwrap_or_else(default_version_bytes));
         }
         out
     }
@@ -370,13 +593,18 @@ impl NegotiateMessage {
 
         let flags = NegotiateFlags::from_bits(read_u32(message, 12)?)
             .ok_or(AuthError::InvalidToken("unsupported NTLM negotiate flags"))?;
-        if flags.contains(NegotiateFlags::VERSION) && message.len() < 40 {
-            return Err(AuthError::InvalidToken(
+        let version = if flags.contains(NegotiateFlags::VERSION) {
+            let version = message.get(32..40).ok_or(AuthError::InvalidToken(
                 "negotiate message missing version payload",
-            ));
-        }
+            ))?;
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(version);
+            Some(bytes)
+        } else {
+            None
+        };
 
-        Ok(Self { flags })
+        Ok(Self { flags, version })
     }
 }
 
@@ -411,9 +639,10 @@ impl ChallengeMessage {
     fn encode_for_test(&self) -> Vec<u8> {
         let target_info = encode_target_info(&self.target_info);
         let target_info_len = u16::try_from(target_info.len()).expect("target info too large");
-        let target_info_offset = 48u32;
+        let include_version = self.flags.contains(NegotiateFlags::VERSION);
+        let target_info_offset = if include_version { 56u32 } else { 48u32 };
 
-        let mut out = Vec::with_capacity(48 + target_info.len());
+        let mut out = Vec::with_capacity(target_info_offset as usize + target_info.len());
         out.extend_from_slice(NTLMSSP_SIGNATURE);
         out.extend_from_slice(&NTLM_MESSAGE_CHALLENGE.to_le_bytes());
         out.extend_from_slice(&0u16.to_le_bytes());
@@ -425,6 +654,10 @@ impl ChallengeMessage {
         out.extend_from_slice(&target_info_len.to_le_bytes());
         out.extend_from_slice(&target_info_len.to_le_bytes());
         out.extend_from_slice(&target_info_offset.to_le_bytes());
+        if include_version {
+            // Windows Server 2022, build 20348.
+            out.extend_from_slice(&[10, 0, 0x7c, 0x4f, 0, 0, 0, NTLMSSP_REVISION_W2K3]);
+        }
         out.extend_from_slice(&target_info);
         out
     }
@@ -542,6 +775,35 @@ impl AuthenticateMessage {
     }
 }
 
+/// Builds the SPNEGO-wrapped NTLM challenge a loopback test server sends back.
+#[cfg(test)]
+pub(crate) fn test_challenge_token(server_challenge: [u8; 8]) -> Vec<u8> {
+    let challenge = ChallengeMessage {
+        flags: NegotiateFlags::UNICODE
+            | NegotiateFlags::REQUEST_TARGET
+            | NegotiateFlags::NTLM
+            | NegotiateFlags::SIGN
+            | NegotiateFlags::ALWAYS_SIGN
+            | NegotiateFlags::EXTENDED_SESSIONSECURITY
+            | NegotiateFlags::TARGET_INFO
+            | NegotiateFlags::_128
+            | NegotiateFlags::KEY_EXCH,
+        server_challenge,
+        target_info: vec![
+            AvPair {
+                av_id: AvId::NB_DOMAIN_NAME,
+                value: utf16le("MOCK"),
+            },
+            AvPair {
+                av_id: AvId::NB_COMPUTER_NAME,
+                value: utf16le("MOCKSERVER"),
+            },
+        ],
+    };
+    encode_neg_token_resp_ntlm(&challenge.encode_for_test())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn build_authenticate_message(
     credentials: &NtlmCredentials,
     challenge: &ChallengeMessage,
@@ -551,9 +813,33 @@ fn build_authenticate_message(
     client_challenge: [u8; 8],
     fallback_timestamp: u64,
     exported_session_key_override: Option<[u8; 16]>,
-) -> Result<(AuthenticateMessage, [u8; 16]), AuthError> {
+) -> Result<(AuthenticateMessage, Option<[u8; 16]>), AuthError> {
     let negotiated_flags = authenticate_flags(negotiate_flags, challenge.flags);
-    let target_info = ntlmv2_target_info(&challenge.target_info, fallback_timestamp);
+    let version = credentials
+        .os_version
+        .filter(|_| negotiated_flags.contains(NegotiateFlags::VERSION));
+    if credentials.is_anonymous() {
+        // MS-NLMP 3.1.5.1.2: an anonymous AUTHENTICATE carries a single zero
+        // byte as the LM response, no NT response, and no session key.
+        let authenticate = AuthenticateMessage {
+            lm_challenge_response: vec![0],
+            nt_challenge_response: Vec::new(),
+            domain_name: Vec::new(),
+            user_name: Vec::new(),
+            workstation: utf16le(&credentials.workstation),
+            encrypted_random_session_key: Vec::new(),
+            flags: (negotiated_flags | NegotiateFlags::ANONYMOUS) - NegotiateFlags::KEY_EXCH,
+            version,
+            mic: None,
+        };
+        return Ok((authenticate, None));
+    }
+    let target_info = ntlmv2_target_info(
+        &challenge.target_info,
+        fallback_timestamp,
+        credentials.target_name.as_deref(),
+        credentials.channel_bindings,
+    );
     let timestamp = target_info_timestamp(&target_info).unwrap_or(fallback_timestamp);
 
     let response_key_nt = ntowfv2(credentials);
@@ -585,11 +871,11 @@ fn build_authenticate_message(
         workstation: utf16le(&credentials.workstation),
         encrypted_random_session_key,
         flags: negotiated_flags,
-        version: None,
+        version,
         mic: None,
     };
 
-    Ok((authenticate, session_key))
+    Ok((authenticate, Some(session_key)))
 }
 
 fn authenticate_flags(
@@ -609,12 +895,11 @@ fn authenticate_flags(
             flags.remove(capability);
         }
     }
-    flags.remove(NegotiateFlags::VERSION);
     flags
 }
 
 fn default_version_bytes() -> [u8; 8] {
-    [6, 1, 0, 0, 0, 0, 0, 0x0f]
+    [6, 1, 0, 0, 0, 0, 0, NTLMSSP_REVISION_W2K3]
 }
 
 fn encrypt_random_session_key(
@@ -689,7 +974,9 @@ fn seal_key(exported_session_key: [u8; 16], flags: NegotiateFlags) -> [u8; 16] {
 }
 
 fn ntowfv2(credentials: &NtlmCredentials) -> [u8; 16] {
-    let nt_hash = nt_hash(&credentials.password);
+    let nt_hash = credentials
+        .nt_hash
+        .unwrap_or_else(|| nt_hash(&credentials.password));
     let identity = utf16le(&(credentials.username.to_uppercase() + &credentials.domain));
     hmac_md5(&nt_hash, &identity)
 }
@@ -757,7 +1044,12 @@ fn encode_target_info(target_info: &[AvPair]) -> Vec<u8> {
     out
 }
 
-fn ntlmv2_target_info(target_info: &[AvPair], fallback_timestamp: u64) -> Vec<AvPair> {
+fn ntlmv2_target_info(
+    target_info: &[AvPair],
+    fallback_timestamp: u64,
+    target_name: Option<&str>,
+    channel_bindings: Option<[u8; 16]>,
+) -> Vec<AvPair> {
     let mut output = target_info.to_vec();
     if target_info_timestamp(&output).is_none() {
         upsert_av_pair(
@@ -766,7 +1058,9 @@ fn ntlmv2_target_info(target_info: &[AvPair], fallback_timestamp: u64) -> Vec<Av
             fallback_timestamp.to_le_bytes().to_vec(),
         );
     }
-    if let Some(dns_host) = output
+    if let Some(target_name) = target_name {
+        upsert_av_pair(&mut output, AvId::TARGET_NAME, utf16le(target_name));
+    } else if let Some(dns_host) = output
         .iter()
         .find(|pair| pair.av_id == AvId::DNS_COMPUTER_NAME)
         .map(|pair| pair.value.clone())
@@ -775,6 +1069,13 @@ fn ntlmv2_target_info(target_info: &[AvPair], fallback_timestamp: u64) -> Vec<Av
         target_name.extend_from_slice(&dns_host);
         upsert_av_pair(&mut output, AvId::TARGET_NAME, target_name);
     }
+    if let Some(channel_bindings) = channel_bindings {
+        upsert_av_pair(
+            &mut output,
+            AvId::CHANNEL_BINDINGS,
+            channel_bindings.to_vec(),
+        );
+    }
     output
 }
 
@@ -846,12 +1147,13 @@ fn read_security_buffer(message: &[u8], offset: usize) -> Result<Option<&[u8]>,
     if len == 0 {
         return Ok(None);
     }
-    if data_offset + len > message.len() {
-        return Err(AuthError::InvalidToken(
+    message
+        .get(data_offset..)
+        .and_then(|rest| rest.get(..len))
+        .map(Some)
+        .ok_or(AuthError::InvalidToken(
             "security buffer points past message",
-        ));
-    }
-    Ok(Some(&message[data_offset..data_offset + len]))
+        ))
 }
 
 fn read_u16(message: &[u8], offset: usize) -> Result<u16, AuthError> {
@@ -937,11 +1239,7 @@ fn rc4k(key: &[u8], data: &[u8]) -> Vec<u8> {
 }
 
 fn current_windows_timestamp() -> u64 {
-    let duration = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or(Duration::from_secs(0));
-    (duration.as_secs() + SEC_TO_UNIX_EPOCH) * WINDOWS_TICK
-        + u64::from(duration.subsec_nanos()) / 100
+    system_time_to_filetime(SystemTime::now())
 }
 
 fn ntlm_debug_enabled() -> bool {
@@ -982,12 +1280,42 @@ mod tests {
         NEG_STATE_ACCEPT_COMPLETE,
     };
     use super::{
-        current_windows_timestamp, hex_bytes, nt_hash, parse_target_info, target_info_timestamp,
-        AuthProvider, AuthenticateMessage, AvId, AvPair, ChallengeMessage, NegotiateFlags,
-        NegotiateMessage, NtlmAuthenticator, NtlmCredentials,
+        current_windows_timestamp, hex_bytes, lmv2_response, nt_hash, ntowfv2, parse_target_info,
+        target_info_timestamp, AuthProvider, AuthenticateMessage, AvId, AvPair, ChallengeMessage,
+        NegotiateFlags, NegotiateMessage, NtlmAuthenticator, NtlmCredentials, NtlmServerInfo,
     };
     use crate::auth::SpnegoMechanism;
 
+    #[test]
+    fn credentials_load_from_environment_lookup() {
+        let vars = [
+            ("SMOLDER_SMB_USERNAME", "alice"),
+            ("SMOLDER_SMB_PASSWORD", "secret"),
+            ("SMOLDER_SMB_DOMAIN", "LAB"),
+            ("SMOLDER_SMB_WORKSTATION", ""),
+        ];
+        let lookup = |key: &str| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| (*value).to_owned())
+        };
+
+        assert_eq!(
+            NtlmCredentials::from_lookup(lookup),
+            Some(NtlmCredentials::new("alice", "secret").with_domain("LAB"))
+        );
+        let without_password = |key: &str| lookup(key).filter(|_| key != "SMOLDER_SMB_PASSWORD");
+        assert_eq!(NtlmCredentials::from_lookup(without_password), None);
+    }
+
+    #[test]
+    fn credentials_debug_output_redacts_the_password() {
+        let credentials = NtlmCredentials::new("alice", "hunter2").with_domain("LAB");
+        let debug = format!("{credentials:?}");
+        assert!(debug.contains("alice"));
+        assert!(!debug.contains("hunter2"));
+    }
+
     #[test]
     fn nt_hash_matches_known_password_vector() {
         assert_eq!(
@@ -999,6 +1327,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn nt_hash_matches_empty_password_vector() {
+        assert_eq!(hex_bytes(&nt_hash("")), "31d6cfe0d16ae931b73c59d7e0c089c0");
+    }
+
+    #[test]
+    fn ntlmv2_keys_match_ms_nlmp_vectors() {
+        let credentials = NtlmCredentials::new("User", "Password").with_domain("Domain");
+        let response_key = ntowfv2(&credentials);
+        assert_eq!(hex_bytes(&response_key), "0c868a403bfd7a93a3001ef22ef02e3f");
+
+        let server_challenge = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+        assert_eq!(
+            hex_bytes(&lmv2_response(&response_key, server_challenge, [0xaa; 8])),
+            "86c35097ac9cec102554764a57cccc19aaaaaaaaaaaaaaaa"
+        );
+    }
+
+    #[test]
+    fn nt_hash_credentials_derive_the_password_response_key() {
+        let from_hash =
+            NtlmCredentials::from_nt_hash("User", nt_hash("Password")).with_domain("Domain");
+
+        assert!(!from_hash.is_anonymous());
+        assert_eq!(
+            hex_bytes(&ntowfv2(&from_hash)),
+            "0c868a403bfd7a93a3001ef22ef02e3f"
+        );
+        assert!(format!("{from_hash:?}").contains("nt_hash: Some(\"<redacted>\")"));
+    }
+
     #[test]
     fn challenge_message_roundtrips_target_info() {
         let challenge = ChallengeMessage {
@@ -1192,6 +1551,169 @@ mod tests {
         assert!(auth.session_key().is_some());
     }
 
+    #[test]
+    fn authenticator_advertises_configured_os_version() {
+        let credentials = NtlmCredentials::new("alice", "password").with_os_version(10, 0, 19041);
+        let mut auth = NtlmAuthenticator::new(credentials)
+            .with_client_challenge([0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x00, 0x11])
+            .with_timestamp(5_000);
+        let negotiate = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb302,
+            negotiate_contexts: Vec::new(),
+            server_guid: [0; 16],
+            capabilities: GlobalCapabilities::empty(),
+            max_transact_size: 0,
+            max_read_size: 0,
+            max_write_size: 0,
+            system_time: 0,
+            server_start_time: 0,
+            security_buffer: Vec::new(),
+        };
+        let expected_version = [10, 0, 0x61, 0x4a, 0, 0, 0, 0x0f];
+
+        let initial = auth
+            .initial_token(&negotiate)
+            .expect("initial token should build");
+        let negotiate = extract_mech_token(&initial).expect("should extract NTLM token");
+        assert_eq!(negotiate.len(), 40);
+        let negotiate = NegotiateMessage::decode(&negotiate).expect("type1 should decode");
+        assert!(negotiate.flags.contains(NegotiateFlags::VERSION));
+        assert_eq!(negotiate.version, Some(expected_version));
+
+        let challenge = ChallengeMessage {
+            flags: auth.negotiate_flags(),
+            server_challenge: [8, 7, 6, 5, 4, 3, 2, 1],
+            target_info: vec![AvPair {
+                av_id: AvId::TIMESTAMP,
+                value: 9_999u64.to_le_bytes().to_vec(),
+            }],
+        };
+        let response = auth
+            .next_token(&encode_neg_token_resp_ntlm(&challenge.encode_for_test()))
+            .expect("challenge response should build");
+        let authenticate = extract_mech_token(&response).expect("should extract NTLM token");
+        let authenticate = AuthenticateMessage::decode(&authenticate).expect("type3 should decode");
+
+        assert!(authenticate.flags.contains(NegotiateFlags::VERSION));
+        assert_eq!(authenticate.version, Some(expected_version));
+        assert_eq!(
+            authenticate.user_name,
+            smolder_proto::smb::smb2::utf16le("alice")
+        );
+    }
+
+    #[test]
+    fn authenticator_records_server_identity_from_challenge() {
+        let mut auth = NtlmAuthenticator::new(NtlmCredentials::new("alice", "password"));
+        let negotiate = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb302,
+            negotiate_contexts: Vec::new(),
+            server_guid: [0; 16],
+            capabilities: GlobalCapabilities::empty(),
+            max_transact_size: 0,
+            max_read_size: 0,
+            max_write_size: 0,
+            system_time: 0,
+            server_start_time: 0,
+            security_buffer: Vec::new(),
+        };
+        auth.initial_token(&negotiate)
+            .expect("initial token should build");
+        assert_eq!(auth.server_info(), None);
+
+        let challenge = ChallengeMessage {
+            flags: auth.negotiate_flags() | NegotiateFlags::VERSION,
+            server_challenge: [8, 7, 6, 5, 4, 3, 2, 1],
+            target_info: vec![
+                AvPair {
+                    av_id: AvId::NB_DOMAIN_NAME,
+                    value: smolder_proto::smb::smb2::utf16le("CORP"),
+                },
+                AvPair {
+                    av_id: AvId::NB_COMPUTER_NAME,
+                    value: smolder_proto::smb::smb2::utf16le("FS01"),
+                },
+                AvPair {
+                    av_id: AvId::DNS_DOMAIN_NAME,
+                    value: smolder_proto::smb::smb2::utf16le("corp.example"),
+                },
+                AvPair {
+                    av_id: AvId::DNS_COMPUTER_NAME,
+                    value: smolder_proto::smb::smb2::utf16le("fs01.corp.example"),
+                },
+            ],
+        };
+        auth.next_token(&encode_neg_token_resp_ntlm(&challenge.encode_for_test()))
+            .expect("challenge response should build");
+
+        assert_eq!(
+            auth.server_info(),
+            Some(&NtlmServerInfo {
+                netbios_computer_name: Some("FS01".to_owned()),
+                netbios_domain_name: Some("CORP".to_owned()),
+                dns_computer_name: Some("fs01.corp.example".to_owned()),
+                dns_domain_name: Some("corp.example".to_owned()),
+                dns_tree_name: None,
+                os_version: Some((10, 0, 20348)),
+            })
+        );
+    }
+
+    #[test]
+    fn credentials_send_configured_target_name_and_channel_bindings() {
+        let credentials = NtlmCredentials::new("alice", "password")
+            .with_target_name("cifs/fileserver.corp.example")
+            .with_channel_bindings([0x5a; 16]);
+        let mut auth = NtlmAuthenticator::new(credentials).with_timestamp(5_000);
+        let negotiate = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb302,
+            negotiate_contexts: Vec::new(),
+            server_guid: [0; 16],
+            capabilities: GlobalCapabilities::empty(),
+            max_transact_size: 0,
+            max_read_size: 0,
+            max_write_size: 0,
+            system_time: 0,
+            server_start_time: 0,
+            security_buffer: Vec::new(),
+        };
+        auth.initial_token(&negotiate)
+            .expect("initial token should build");
+
+        let challenge = ChallengeMessage {
+            flags: auth.negotiate_flags(),
+            server_challenge: [8, 7, 6, 5, 4, 3, 2, 1],
+            target_info: vec![AvPair {
+                av_id: AvId::DNS_COMPUTER_NAME,
+                value: smolder_proto::smb::smb2::utf16le("10.0.0.5"),
+            }],
+        };
+        let response = auth
+            .next_token(&encode_neg_token_resp_ntlm(&challenge.encode_for_test()))
+            .expect("challenge response should build");
+        let authenticate = extract_mech_token(&response).expect("should extract NTLM token");
+        let authenticate = AuthenticateMessage::decode(&authenticate).expect("type3 should decode");
+        let target_info =
+            parse_target_info(&authenticate.nt_challenge_response[44..]).expect("target info");
+        let value = |av_id| {
+            target_info
+                .iter()
+                .find(|pair| pair.av_id == av_id)
+                .map(|pair| pair.value.clone())
+        };
+
+        assert_eq!(
+            value(AvId::TARGET_NAME),
+            Some(smolder_proto::smb::smb2::utf16le(
+                "cifs/fileserver.corp.example"
+            ))
+        );
+        assert_eq!(value(AvId::CHANNEL_BINDINGS), Some(vec![0x5a; 16]));
+    }
+
     #[test]
     fn authenticator_matches_impacket_on_windows_key_exchange_challenge() {
         let negotiate = NegotiateResponse {
@@ -1308,6 +1830,60 @@ mod tests {
         assert_eq!(parsed.mech_list_mic.as_ref().map(Vec::len), Some(16));
     }
 
+    #[test]
+    fn anonymous_authenticator_sends_null_session_type3() {
+        let mut auth = NtlmAuthenticator::new(NtlmCredentials::anonymous());
+        let negotiate = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb302,
+            negotiate_contexts: Vec::new(),
+            server_guid: [0; 16],
+            capabilities: GlobalCapabilities::empty(),
+            max_transact_size: 0,
+            max_read_size: 0,
+            max_write_size: 0,
+            system_time: 0,
+            server_start_time: 0,
+            security_buffer: Vec::new(),
+        };
+        auth.initial_token(&negotiate)
+            .expect("initial token should build");
+
+        let challenge = ChallengeMessage {
+            flags: NegotiateFlags::UNICODE
+                | NegotiateFlags::NTLM
+                | NegotiateFlags::SIGN
+                | NegotiateFlags::EXTENDED_SESSIONSECURITY
+                | NegotiateFlags::TARGET_INFO
+                | NegotiateFlags::_128
+                | NegotiateFlags::KEY_EXCH,
+            server_challenge: [8, 7, 6, 5, 4, 3, 2, 1],
+            target_info: Vec::new(),
+        };
+        let response = auth
+            .next_token(&encode_neg_token_resp_ntlm(&challenge.encode_for_test()))
+            .expect("challenge response should build");
+        let authenticate = extract_mech_token(&response).expect("should extract NTLM token");
+        let authenticate = AuthenticateMessage::decode(&authenticate).expect("type3 should decode");
+
+        assert_eq!(authenticate.lm_challenge_response, vec![0]);
+        assert!(authenticate.nt_challenge_response.is_empty());
+        assert!(authenticate.user_name.is_empty());
+        assert!(authenticate.domain_name.is_empty());
+        assert!(authenticate.encrypted_random_session_key.is_empty());
+        assert!(authenticate.flags.contains(NegotiateFlags::ANONYMOUS));
+        assert!(!authenticate.flags.contains(NegotiateFlags::KEY_EXCH));
+        assert_eq!(auth.session_key(), None);
+
+        let final_spnego = vec![0xa1, 0x07, 0x30, 0x05, 0xa0, 0x03, 0x0a, 0x01, 0x00];
+        let final_token = auth
+            .next_token(&final_spnego)
+            .expect("anonymous completion should not need a MIC");
+        let parsed = parse_neg_token_resp(&final_token).expect("completion token should parse");
+        assert_eq!(parsed.neg_state, Some(NEG_STATE_ACCEPT_COMPLETE));
+        assert_eq!(parsed.mech_list_mic, None);
+    }
+
     #[test]
     fn timestamp_helper_uses_windows_epoch() {
         assert!(current_windows_timestamp() > 100_000_000);
@@ -7,21 +7,24 @@
 //! not the intended starting point for new integrations.
 
 use smolder_proto::smb::smb2::{
-    ChangeNotifyRequest, ChangeNotifyResponse, CloseRequest, CloseResponse, Command,
-    CreateRequest, CreateResponse, EchoRequest, EchoResponse, FileId, FlushRequest,
-    FlushResponse, Header, HeaderFlags, IoctlRequest, IoctlResponse, LockRequest, LockResponse,
-    LogoffRequest, LogoffResponse, MessageId, NegotiateRequest, NegotiateResponse,
+    AsyncId, CancelRequest, ChangeNotifyRequest, ChangeNotifyResponse, CloseRequest,
+    CloseResponse, Command,
+    CreateRequest, CreateResponse, CreditCharge, CtlCode, Dialect, EchoRequest, EchoResponse, FileId,
+    FlushRequest, FlushResponse, GlobalCapabilities, Header, HeaderFlags, IoctlRequest,
+    IoctlResponse, LockRequest, LockResponse, LogoffRequest, LogoffResponse, MessageId,
+    NegotiateRequest, NegotiateResponse, OplockBreak,
     NetworkInterfaceInfoResponse, QueryDirectoryRequest, QueryDirectoryResponse, QueryInfoRequest,
     QueryInfoResponse, ReadRequest, ReadResponse, ResumeKeyResponse, SessionId,
-    SessionSetupRequest, SessionSetupResponse, SessionSetupSecurityMode, SetInfoRequest,
-    SetInfoResponse,
+    SessionFlags, SessionSetupRequest, SessionSetupResponse, SessionSetupSecurityMode,
+    SetInfoRequest, SetInfoResponse,
     TreeConnectRequest, TreeConnectResponse, TreeDisconnectRequest, TreeDisconnectResponse,
     TreeId, WriteRequest, WriteResponse,
 };
 use smolder_proto::smb::status::NtStatus;
-use tracing::{Instrument, trace, trace_span};
+use tracing::{Instrument, debug, trace, trace_span};
+use zeroize::Zeroizing;
 
-use crate::auth::AuthProvider;
+use crate::auth::{AuthProvider, NtlmServerInfo};
 use crate::error::CoreError;
 use crate::transport::SmbTransport;
 
@@ -29,6 +32,7 @@ mod helpers;
 mod state;
 
 use self::helpers::*;
+pub(crate) use self::helpers::CREDIT_PAYLOAD_SIZE;
 use self::state::RequestContext;
 pub use self::state::{
     Authenticated, CompoundRequest, CompoundResponse, Connected, DurableHandle,
@@ -42,9 +46,20 @@ pub struct Connection<T, State> {
     transport: T,
     next_message_id: u64,
     available_credits: u32,
+    outstanding: Option<OutstandingRequest>,
+    process_id: u32,
+    oplock_breaks: Vec<OplockBreak>,
     state: State,
 }
 
+/// A request that was sent but whose final response has not been read yet.
+#[derive(Debug, Clone, Copy)]
+struct OutstandingRequest {
+    command: Command,
+    message_id: MessageId,
+    async_id: Option<AsyncId>,
+}
+
 #[derive(Debug)]
 struct TransactionFrames {
     header: Header,
@@ -72,6 +87,9 @@ impl<T> Connection<T, Connected> {
             transport,
             next_message_id: 0,
             available_credits: 1,
+            outstanding: None,
+            process_id: 0,
+            oplock_breaks: Vec::new(),
             state: Connected,
         }
     }
@@ -84,6 +102,64 @@ impl<T, State> Connection<T, State> {
         &self.state
     }
 
+    /// Returns the number of SMB credits currently available for new requests.
+    #[must_use]
+    pub fn available_credits(&self) -> u32 {
+        self.available_credits
+    }
+
+    /// Returns the message id of a request that was sent but never completed.
+    ///
+    /// This is set when a request future was dropped while waiting for its
+    /// response, for example by a timeout wrapper, or when reading the
+    /// response timed out. Pass the id to `cancel` before issuing further
+    /// requests; `tree_disconnect` and `logoff` cancel it automatically.
+    #[must_use]
+    pub fn outstanding_request(&self) -> Option<MessageId> {
+        self.outstanding.map(|request| request.message_id)
+    }
+
+    /// Returns the process id stamped into every outgoing sync header.
+    #[must_use]
+    pub fn process_id(&self) -> u32 {
+        self.process_id
+    }
+
+    /// Pins the process id stamped into every outgoing sync header.
+    ///
+    /// The default is `0`, as MS-SMB2 recommends. Servers ignore the field, so
+    /// this only matters for reproducing captured traffic byte for byte;
+    /// Windows clients historically sent `0xFEFF`.
+    pub fn set_process_id(&mut self, process_id: u32) {
+        self.process_id = process_id;
+    }
+
+    /// Returns and clears the oplock break notifications received so far.
+    ///
+    /// Servers push `OPLOCK_BREAK` notifications unsolicited, so they are
+    /// collected while reading the responses to other requests. A break sent
+    /// to an otherwise idle connection is only seen once the next request is
+    /// issued. Lease break notifications are skipped. Acknowledge each oplock
+    /// break with `acknowledge_oplock_break` on the tree connection.
+    pub fn take_oplock_breaks(&mut self) -> Vec<OplockBreak> {
+        std::mem::take(&mut self.oplock_breaks)
+    }
+
+    fn collect_oplock_break(&mut self, body: &[u8]) {
+        // Lease breaks share the command but carry a 44-byte body; they are not
+        // errors for the request being waited on, so skip any body that is not
+        // an oplock break.
+        match OplockBreak::decode(body) {
+            Ok(notification) => self.oplock_breaks.push(notification),
+            Err(error) => debug!(%error, "skipping unrecognised OPLOCK_BREAK notification"),
+        }
+    }
+
+    /// Returns the inner transport mutably, for example to adjust its timeouts.
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
     /// Consumes the connection and returns the inner transport.
     #[must_use]
     pub fn into_transport(self) -> T {
@@ -109,6 +185,11 @@ where
             )
             .await?;
         let response = NegotiateResponse::decode(transaction.body())?;
+        if !request.dialects.contains(&response.dialect_revision) {
+            return Err(CoreError::InvalidResponse(
+                "negotiate response selected a dialect that was not offered",
+            ));
+        }
         let preauth_integrity = negotiate_preauth_integrity_state(
             request,
             &response,
@@ -127,6 +208,9 @@ where
             transport: self.transport,
             next_message_id: self.next_message_id,
             available_credits: self.available_credits,
+            outstanding: self.outstanding,
+            process_id: self.process_id,
+            oplock_breaks: self.oplock_breaks,
             state: Negotiated {
                 response,
                 client_signing_mode: request.security_mode,
@@ -199,9 +283,7 @@ where
 
             session_id = header.session_id;
             if success {
-                let raw_response_session_key = auth_provider.session_key().map(ToOwned::to_owned);
-                let response_session_key =
-                    derive_smb_session_key(raw_response_session_key.as_deref());
+                let response_session_key = derive_smb_session_key(auth_provider.session_key());
                 let signing_required = session_signing_required(
                     client_signing_mode,
                     self.state.response.security_mode,
@@ -209,7 +291,7 @@ where
                 );
                 let response_signing = derive_signing_state(
                     self.state.response.dialect_revision,
-                    response_session_key.as_deref(),
+                    response_session_key.as_deref().map(Vec::as_slice),
                     preauth_integrity.as_ref(),
                 )?;
                 verify_final_session_setup_response(
@@ -220,22 +302,25 @@ where
                     response_signing.as_deref(),
                 )?;
                 auth_provider.finish(&response.security_buffer)?;
-                let raw_session_key = auth_provider.session_key().map(ToOwned::to_owned);
-                let session_key = derive_smb_session_key(raw_session_key.as_deref());
+                let raw_session_key = auth_provider.session_key();
+                let session_key = derive_smb_session_key(raw_session_key);
                 let signing = derive_signing_state(
                     self.state.response.dialect_revision,
-                    session_key.as_deref(),
+                    session_key.as_deref().map(Vec::as_slice),
                     preauth_integrity.as_ref(),
                 )?;
                 let encryption = derive_encryption_state(
                     &self.state.response,
-                    raw_session_key.as_deref(),
+                    raw_session_key,
                     preauth_integrity.as_ref(),
                 )?;
                 let Connection {
                     transport,
                     next_message_id,
                     available_credits,
+                    outstanding,
+                    process_id,
+                    oplock_breaks,
                     state,
                 } = self;
                 let Negotiated {
@@ -250,6 +335,9 @@ where
                     transport,
                     next_message_id,
                     available_credits,
+                    outstanding,
+                    process_id,
+                    oplock_breaks,
                     state: Authenticated {
                         negotiated,
                         client_signing_mode,
@@ -262,6 +350,7 @@ where
                         encryption_required,
                         encryption,
                         compression,
+                        ntlm_server_info: auth_provider.ntlm_server_info().cloned(),
                     },
                 });
             }
@@ -321,6 +410,9 @@ where
             transport,
             next_message_id,
             available_credits,
+            outstanding,
+            process_id,
+            oplock_breaks,
             state,
         } = self;
         let Negotiated {
@@ -335,6 +427,9 @@ where
             transport,
             next_message_id,
             available_credits,
+            outstanding,
+            process_id,
+            oplock_breaks,
             state: Authenticated {
                 negotiated,
                 client_signing_mode,
@@ -347,6 +442,7 @@ where
                 encryption_required,
                 encryption,
                 compression,
+                ntlm_server_info: None,
             },
         })
     }
@@ -363,9 +459,123 @@ where
     }
 
     /// Returns the exported session key for the authenticated session, if available.
+    ///
+    /// The key protects every signed and encrypted message on the session, so
+    /// treat it like a credential. Keys that come from outside the built-in
+    /// mechanisms are supplied through [`AuthProvider::session_key`].
     #[must_use]
     pub fn session_key(&self) -> Option<&[u8]> {
-        self.state.session_key.as_deref()
+        self.state.session_key.as_deref().map(Vec::as_slice)
+    }
+
+    /// Derives the SMB 3.x application key for the authenticated session.
+    ///
+    /// Returns `None` for SMB 2.x dialects or when the auth mechanism exported
+    /// no session key.
+    pub fn application_key(&self) -> Result<Option<Zeroizing<Vec<u8>>>, CoreError> {
+        session_application_key(
+            &self.state.negotiated,
+            self.state.session_key.as_deref().map(Vec::as_slice),
+            self.state.preauth_integrity.as_ref(),
+        )
+    }
+
+    /// Returns the server identity from the NTLM challenge, if the session used NTLM.
+    #[must_use]
+    pub fn ntlm_server_info(&self) -> Option<&NtlmServerInfo> {
+        self.state.ntlm_server_info.as_ref()
+    }
+
+    /// Returns whether the server logged the session on as a guest.
+    ///
+    /// This is set when the server maps the caller to its guest account,
+    /// either silently or after a guest fallback.
+    #[must_use]
+    pub fn is_guest(&self) -> bool {
+        self.state
+            .session
+            .session_flags
+            .contains(SessionFlags::IS_GUEST)
+    }
+
+    /// Returns whether the server treated the session as an anonymous null session.
+    #[must_use]
+    pub fn is_null_session(&self) -> bool {
+        self.state
+            .session
+            .session_flags
+            .contains(SessionFlags::IS_NULL)
+    }
+
+    /// Returns the dialect selected by the server during negotiate.
+    #[must_use]
+    pub fn dialect(&self) -> Dialect {
+        self.state.negotiated.dialect_revision
+    }
+
+    /// Returns the capabilities the server advertised during negotiate.
+    #[must_use]
+    pub fn server_capabilities(&self) -> GlobalCapabilities {
+        self.state.negotiated.capabilities
+    }
+
+    /// Returns the largest `READ` length the server accepts.
+    #[must_use]
+    pub fn max_read_size(&self) -> u32 {
+        self.state.negotiated.max_read_size
+    }
+
+    /// Returns the largest `WRITE` length the server accepts.
+    #[must_use]
+    pub fn max_write_size(&self) -> u32 {
+        self.state.negotiated.max_write_size
+    }
+
+    /// Returns the largest `IOCTL`, `QUERY_INFO`, or `QUERY_DIRECTORY` buffer the server accepts.
+    #[must_use]
+    pub fn max_transact_size(&self) -> u32 {
+        self.state.negotiated.max_transact_size
+    }
+
+    /// Returns whether requests on this session must be signed.
+    #[must_use]
+    pub fn signing_required(&self) -> bool {
+        self.state.signing_required
+    }
+
+    /// Returns whether requests may exceed 64 KiB by charging multiple credits.
+    ///
+    /// This requires an SMB 2.1 or later dialect and the server's `LARGE_MTU` capability.
+    #[must_use]
+    pub fn supports_multi_credit(&self) -> bool {
+        multi_credit_supported(&self.state.negotiated)
+    }
+
+    /// Returns whether requests on this session are sent encrypted.
+    #[must_use]
+    pub fn encryption_required(&self) -> bool {
+        self.state.encryption_required
+    }
+
+    /// Encrypts every further request on this session, even when the server
+    /// did not ask for it.
+    ///
+    /// Fails when the session has no SMB 3.x encryption keys, so a caller that
+    /// insists on encryption never falls back to plaintext. Trees connected
+    /// afterwards inherit the requirement. Transport-level security accepted
+    /// during negotiate, as with SMB over QUIC, already satisfies it.
+    pub fn require_encryption(&mut self) -> Result<(), CoreError> {
+        if transport_level_security_accepted(&self.state.negotiated)? {
+            return Ok(());
+        }
+        if self.state.encryption.is_none() {
+            return Err(CoreError::Unsupported(
+                "session did not negotiate SMB 3.x encryption",
+            ));
+        }
+        self.state.session.session_flags |= SessionFlags::ENCRYPT_DATA;
+        self.state.encryption_required = true;
+        Ok(())
     }
 
     /// Executes a raw compound request within the authenticated session.
@@ -392,6 +602,16 @@ where
         Ok(response)
     }
 
+    /// Sends a `CANCEL` for an outstanding request and drains its final response.
+    ///
+    /// Servers normally complete a cancelled request with `STATUS_CANCELLED`,
+    /// but the request may already have finished; the final status is
+    /// returned either way. Afterwards the connection accepts new requests.
+    pub async fn cancel(&mut self, message_id: MessageId) -> Result<NtStatus, CoreError> {
+        let context = self.state.request_context();
+        self.cancel_outstanding(message_id, context).await
+    }
+
     /// Performs `LOGOFF` and transitions back into the negotiated state.
     pub async fn logoff(mut self) -> Result<Connection<T, Negotiated>, CoreError> {
         let context = self.state.request_context();
@@ -408,6 +628,9 @@ where
             transport,
             next_message_id,
             available_credits,
+            outstanding,
+            process_id,
+            oplock_breaks,
             state,
         } = self;
         let Authenticated {
@@ -422,6 +645,9 @@ where
             transport,
             next_message_id,
             available_credits,
+            outstanding,
+            process_id,
+            oplock_breaks,
             state: Negotiated {
                 response,
                 client_signing_mode,
@@ -456,6 +682,9 @@ where
             transport,
             next_message_id,
             available_credits,
+            outstanding,
+            process_id,
+            oplock_breaks,
             state,
         } = self;
         let Authenticated {
@@ -470,6 +699,7 @@ where
             encryption_required: _,
             encryption,
             compression,
+            ntlm_server_info,
         } = state;
         let encryption_required =
             tree_encryption_required(&negotiated, session.session_flags, response.share_flags)?;
@@ -478,6 +708,9 @@ where
             transport,
             next_message_id,
             available_credits,
+            outstanding,
+            process_id,
+            oplock_breaks,
             state: TreeConnected {
                 negotiated,
                 client_signing_mode,
@@ -492,6 +725,7 @@ where
                 encryption_required,
                 encryption,
                 compression,
+                ntlm_server_info,
             },
         })
     }
@@ -526,6 +760,9 @@ where
             transport,
             next_message_id,
             available_credits,
+            outstanding,
+            process_id,
+            oplock_breaks,
             state,
         } = self;
         let TreeConnected {
@@ -539,6 +776,7 @@ where
             signing,
             encryption,
             compression,
+            ntlm_server_info,
             ..
         } = state;
         let encryption_required = session_encryption_required(&negotiated, session.session_flags)?;
@@ -547,6 +785,9 @@ where
             transport,
             next_message_id,
             available_credits,
+            outstanding,
+            process_id,
+            oplock_breaks,
             state: Authenticated {
                 negotiated,
                 client_signing_mode,
@@ -559,6 +800,7 @@ where
                 encryption_required,
                 encryption,
                 compression,
+                ntlm_server_info,
             },
         })
     }
@@ -646,6 +888,16 @@ where
         Ok(response)
     }
 
+    /// Sends a `CANCEL` for an outstanding request and drains its final response.
+    ///
+    /// Servers normally complete a cancelled request with `STATUS_CANCELLED`,
+    /// but the request may already have finished; the final status is
+    /// returned either way. Afterwards the connection accepts new requests.
+    pub async fn cancel(&mut self, message_id: MessageId) -> Result<NtStatus, CoreError> {
+        let context = self.state.request_context();
+        self.cancel_outstanding(message_id, context).await
+    }
+
     /// Performs a `LOCK` request on the active tree.
     pub async fn lock(&mut self, request: &LockRequest) -> Result<LockResponse, CoreError> {
         let context = self.state.request_context();
@@ -661,7 +913,28 @@ where
         Ok(response)
     }
 
+    /// Acknowledges an oplock break, accepting the level in `acknowledgment`.
+    pub async fn acknowledge_oplock_break(
+        &mut self,
+        acknowledgment: &OplockBreak,
+    ) -> Result<OplockBreak, CoreError> {
+        let context = self.state.request_context();
+        let (_, response) = self
+            .transact(
+                Command::OplockBreak,
+                acknowledgment.encode(),
+                context,
+                &[NtStatus::SUCCESS.to_u32()],
+                OplockBreak::decode,
+            )
+            .await?;
+        Ok(response)
+    }
+
     /// Performs a `CHANGE_NOTIFY` request on the active tree.
+    ///
+    /// `STATUS_NOTIFY_ENUM_DIR` is accepted and yields an empty output buffer,
+    /// telling the caller that too many changes occurred to be listed.
     pub async fn change_notify(
         &mut self,
         request: &ChangeNotifyRequest,
@@ -672,7 +945,10 @@ where
                 Command::ChangeNotify,
                 request.encode(),
                 context,
-                &[NtStatus::SUCCESS.to_u32()],
+                &[
+                    NtStatus::SUCCESS.to_u32(),
+                    NtStatus::NOTIFY_ENUM_DIR.to_u32(),
+                ],
                 ChangeNotifyResponse::decode,
             )
             .await?;
@@ -680,8 +956,14 @@ where
     }
 
     /// Performs a `READ` request on the active tree.
+    ///
+    /// Reads longer than 64 KiB are charged multiple credits when the server
+    /// supports multi-credit requests.
     pub async fn read(&mut self, request: &ReadRequest) -> Result<ReadResponse, CoreError> {
-        let context = self.state.request_context();
+        let context = self
+            .state
+            .request_context()
+            .with_credit_charge(credit_charge_for(&self.state.negotiated, request.length));
         let (_, response) = self
             .transact(
                 Command::Read,
@@ -694,9 +976,46 @@ where
         Ok(response)
     }
 
+    /// Performs a `READ` on a message-mode named pipe.
+    ///
+    /// The returned flag is `true` when the server answered with
+    /// `STATUS_BUFFER_OVERFLOW` and more of the current message is still queued.
+    pub async fn read_pipe_message(
+        &mut self,
+        request: &ReadRequest,
+    ) -> Result<(Vec<u8>, bool), CoreError> {
+        let context = self
+            .state
+            .request_context()
+            .with_credit_charge(credit_charge_for(&self.state.negotiated, request.length));
+        let (header, response) = self
+            .transact(
+                Command::Read,
+                request.encode(),
+                context,
+                &[
+                    NtStatus::SUCCESS.to_u32(),
+                    NtStatus::BUFFER_OVERFLOW.to_u32(),
+                ],
+                ReadResponse::decode,
+            )
+            .await?;
+        Ok((
+            response.data,
+            header.status == NtStatus::BUFFER_OVERFLOW.to_u32(),
+        ))
+    }
+
     /// Performs a `WRITE` request on the active tree.
+    ///
+    /// Writes longer than 64 KiB are charged multiple credits when the server
+    /// supports multi-credit requests.
     pub async fn write(&mut self, request: &WriteRequest) -> Result<WriteResponse, CoreError> {
-        let context = self.state.request_context();
+        let length = u32::try_from(request.data.len()).unwrap_or(u32::MAX);
+        let context = self
+            .state
+            .request_context()
+            .with_credit_charge(credit_charge_for(&self.state.negotiated, length));
         let (_, response) = self
             .transact(
                 Command::Write,
@@ -740,6 +1059,9 @@ where
     }
 
     /// Performs an `IOCTL` request on the active tree.
+    ///
+    /// The response is rejected when it names a different control code or
+    /// returns more output than `max_output_response` allows.
     pub async fn ioctl(&mut self, request: &IoctlRequest) -> Result<IoctlResponse, CoreError> {
         let context = self.state.request_context();
         let (_, response) = self
@@ -751,9 +1073,31 @@ where
                 IoctlResponse::decode,
             )
             .await?;
+        validate_ioctl_response(request, &response)?;
         Ok(response)
     }
 
+    /// Issues an arbitrary `FSCTL` and returns its output buffer.
+    ///
+    /// Pass [`FileId::NONE`] for control codes that are not tied to an open handle.
+    pub async fn fsctl(
+        &mut self,
+        ctl_code: CtlCode,
+        file_id: FileId,
+        input: Vec<u8>,
+        max_output_response: u32,
+    ) -> Result<Vec<u8>, CoreError> {
+        let response = self
+            .ioctl(&IoctlRequest::fsctl(
+                ctl_code,
+                file_id,
+                max_output_response,
+                input,
+            ))
+            .await?;
+        Ok(response.output)
+    }
+
     /// Queries the server's network-interface inventory through `FSCTL_QUERY_NETWORK_INTERFACE_INFO`.
     pub async fn query_network_interfaces(
         &mut self,
@@ -778,7 +1122,42 @@ where
         ResumeKeyResponse::decode(&response.output).map_err(CoreError::from)
     }
 
+    /// Writes one message to a named pipe and reads the reply through `FSCTL_PIPE_TRANSCEIVE`.
+    ///
+    /// The returned flag is `true` when the server answered with
+    /// `STATUS_BUFFER_OVERFLOW`, meaning the reply did not fit in
+    /// `max_output_response` and the rest of the message must be read separately.
+    pub async fn pipe_transceive(
+        &mut self,
+        file_id: FileId,
+        input: Vec<u8>,
+        max_output_response: u32,
+    ) -> Result<(Vec<u8>, bool), CoreError> {
+        let request = IoctlRequest::pipe_transceive(file_id, max_output_response, input);
+        let context = self.state.request_context();
+        let (header, response) = self
+            .transact(
+                Command::Ioctl,
+                request.encode(),
+                context,
+                &[
+                    NtStatus::SUCCESS.to_u32(),
+                    NtStatus::BUFFER_OVERFLOW.to_u32(),
+                ],
+                IoctlResponse::decode,
+            )
+            .await?;
+        validate_ioctl_response(&request, &response)?;
+        Ok((
+            response.output,
+            header.status == NtStatus::BUFFER_OVERFLOW.to_u32(),
+        ))
+    }
+
     /// Performs a `QUERY_DIRECTORY` request on the active tree.
+    ///
+    /// `STATUS_NO_MORE_FILES` and `STATUS_NO_SUCH_FILE`, which servers return
+    /// when a search pattern matches nothing, both yield an empty response.
     pub async fn query_directory(
         &mut self,
         request: &QueryDirectoryRequest,
@@ -789,10 +1168,14 @@ where
                 Command::QueryDirectory,
                 request.encode(),
                 context,
-                &[NtStatus::SUCCESS.to_u32(), NtStatus::NO_MORE_FILES.to_u32()],
+                &[
+                    NtStatus::SUCCESS.to_u32(),
+                    NtStatus::NO_MORE_FILES.to_u32(),
+                    NtStatus::NO_SUCH_FILE.to_u32(),
+                ],
             )
             .await?;
-        if header.status == NtStatus::NO_MORE_FILES.to_u32() {
+        if header.status != NtStatus::SUCCESS.to_u32() {
             return Ok(QueryDirectoryResponse::empty());
         }
         QueryDirectoryResponse::decode(&body).map_err(CoreError::from)
@@ -847,9 +1230,96 @@ where
     }
 
     /// Returns the exported session key for the authenticated session, if available.
+    ///
+    /// The key protects every signed and encrypted message on the session, so
+    /// treat it like a credential. Keys that come from outside the built-in
+    /// mechanisms are supplied through [`AuthProvider::session_key`].
     #[must_use]
     pub fn session_key(&self) -> Option<&[u8]> {
-        self.state.session_key.as_deref()
+        self.state.session_key.as_deref().map(Vec::as_slice)
+    }
+
+    /// Derives the SMB 3.x application key for the authenticated session.
+    ///
+    /// Returns `None` for SMB 2.x dialects or when the auth mechanism exported
+    /// no session key.
+    pub fn application_key(&self) -> Result<Option<Zeroizing<Vec<u8>>>, CoreError> {
+        session_application_key(
+            &self.state.negotiated,
+            self.state.session_key.as_deref().map(Vec::as_slice),
+            self.state.preauth_integrity.as_ref(),
+        )
+    }
+
+    /// Returns the server identity from the NTLM challenge, if the session used NTLM.
+    #[must_use]
+    pub fn ntlm_server_info(&self) -> Option<&NtlmServerInfo> {
+        self.state.ntlm_server_info.as_ref()
+    }
+
+    /// Returns whether the server logged the session on as a guest.
+    ///
+    /// This is set when the server maps the caller to its guest account,
+    /// either silently or after a guest fallback.
+    #[must_use]
+    pub fn is_guest(&self) -> bool {
+        self.state
+            .session
+            .session_flags
+            .contains(SessionFlags::IS_GUEST)
+    }
+
+    /// Returns whether the server treated the session as an anonymous null session.
+    #[must_use]
+    pub fn is_null_session(&self) -> bool {
+        self.state
+            .session
+            .session_flags
+            .contains(SessionFlags::IS_NULL)
+    }
+
+    /// Returns the dialect selected by the server during negotiate.
+    #[must_use]
+    pub fn dialect(&self) -> Dialect {
+        self.state.negotiated.dialect_revision
+    }
+
+    /// Returns the capabilities the server advertised during negotiate.
+    #[must_use]
+    pub fn server_capabilities(&self) -> GlobalCapabilities {
+        self.state.negotiated.capabilities
+    }
+
+    /// Returns the largest `READ` length the server accepts.
+    #[must_use]
+    pub fn max_read_size(&self) -> u32 {
+        self.state.negotiated.max_read_size
+    }
+
+    /// Returns the largest `WRITE` length the server accepts.
+    #[must_use]
+    pub fn max_write_size(&self) -> u32 {
+        self.state.negotiated.max_write_size
+    }
+
+    /// Returns the largest `IOCTL`, `QUERY_INFO`, or `QUERY_DIRECTORY` buffer the server accepts.
+    #[must_use]
+    pub fn max_transact_size(&self) -> u32 {
+        self.state.negotiated.max_transact_size
+    }
+
+    /// Returns whether requests on this session must be signed.
+    #[must_use]
+    pub fn signing_required(&self) -> bool {
+        self.state.signing_required
+    }
+
+    /// Returns whether requests may exceed 64 KiB by charging multiple credits.
+    ///
+    /// This requires an SMB 2.1 or later dialect and the server's `LARGE_MTU` capability.
+    #[must_use]
+    pub fn supports_multi_credit(&self) -> bool {
+        multi_credit_supported(&self.state.negotiated)
     }
 }
 
@@ -857,11 +1327,21 @@ impl<T, State> Connection<T, State>
 where
     T: SmbTransport + Send,
 {
+    /// Closes the underlying transport without any further SMB exchange.
+    ///
+    /// Call this after `logoff` to tear the connection down explicitly and
+    /// observe transport errors that dropping the connection would discard.
+    pub async fn shutdown(mut self) -> Result<(), CoreError> {
+        self.transport.shutdown_transport().await?;
+        Ok(())
+    }
+
     async fn transact_compound_raw(
         &mut self,
         requests: &[CompoundRequest],
         context: RequestContext,
     ) -> Result<Vec<CompoundResponse>, CoreError> {
+        self.ensure_no_outstanding_request()?;
         let request_packets = self.build_compound_request_packets(requests, &context)?;
         let payload = request_packets
             .iter()
@@ -968,12 +1448,6 @@ where
             .transact_framed(command, body, context, accepted_statuses)
             .await?;
         let response = decode(transaction.body())?;
-        trace!(
-            ?command,
-            message_id = transaction.header.message_id.0,
-            status = transaction.header.status,
-            "received smb response"
-        );
 
         Ok((transaction.header, response))
     }
@@ -998,6 +1472,15 @@ where
         context: RequestContext,
         accepted_statuses: &[u32],
     ) -> Result<TransactionFrames, CoreError> {
+        if let Some(abandoned) = self
+            .outstanding
+            .filter(|_| matches!(command, Command::TreeDisconnect | Command::Logoff))
+        {
+            // Teardown must not be blocked by an abandoned request, so cancel it first.
+            self.cancel_outstanding(abandoned.message_id, context.clone())
+                .await?;
+        }
+        self.ensure_no_outstanding_request()?;
         if context.should_encrypt() && context.encryption.is_none() {
             return Err(CoreError::InvalidInput(
                 "session requires encryption but no encryption key is available",
@@ -1008,12 +1491,15 @@ where
                 "session requires signing but no signing key is available",
             ));
         }
-        let message_id = self.preview_message_ids(1)?[0];
+        let credit_charge = usize::from(context.credit_charge);
+        let message_id = self.preview_message_ids(credit_charge)?[0];
 
         let mut header = Header::new(command, message_id);
+        header.process_id = self.process_id;
         header.session_id = context.session_id;
         header.tree_id = context.tree_id;
-        header.credit_request_response = self.credit_request_hint_after_reserve(1);
+        header.credit_charge = CreditCharge(context.credit_charge);
+        header.credit_request_response = self.credit_request_hint_after_reserve(credit_charge);
         if context.signing.is_some() && !context.should_encrypt() {
             header.flags |= HeaderFlags::SIGNED;
         }
@@ -1028,14 +1514,46 @@ where
             signing.sign_packet(&mut packet)?;
         }
         let message = encode_transport_payload(&packet, &context)?;
-        self.commit_message_ids(1)?;
+        self.commit_message_ids(credit_charge)?;
 
-        trace!(?command, message_id = message_id.0, "sending smb request");
+        debug!(
+            ?command,
+            message_id = message_id.0,
+            tree_id = header.tree_id.0,
+            "sending smb request"
+        );
+        #[cfg(feature = "packet-trace")]
+        trace!(
+            ?command,
+            message_id = message_id.0,
+            packet = %hex_dump(&packet),
+            "smb request bytes"
+        );
         self.transport
             .send_message(&message)
             .instrument(trace_span!("smb_send", ?command, message_id = message_id.0))
             .await?;
+        self.outstanding = Some(OutstandingRequest {
+            command,
+            message_id,
+            async_id: None,
+        });
+
+        let result = self
+            .recv_final_response(command, message_id, packet, &context, accepted_statuses)
+            .await;
+        self.release_outstanding_on_error(&result);
+        result
+    }
 
+    async fn recv_final_response(
+        &mut self,
+        command: Command,
+        message_id: MessageId,
+        packet: Vec<u8>,
+        context: &RequestContext,
+        accepted_statuses: &[u32],
+    ) -> Result<TransactionFrames, CoreError> {
         let mut pending_async_id = None;
         loop {
             let response_message = self
@@ -1044,7 +1562,7 @@ where
                 .instrument(trace_span!("smb_recv", ?command, message_id = message_id.0))
                 .await?;
             let (response_payload, encrypted_response) =
-                decode_transport_payload(&response_message, &context)?;
+                decode_transport_payload(&response_message, context)?;
             if response_payload.len() < Header::LEN {
                 return Err(CoreError::InvalidResponse(
                     "response shorter than SMB2 header",
@@ -1052,6 +1570,10 @@ where
             }
 
             let response_header = Header::decode(&response_payload[..Header::LEN])?;
+            if is_oplock_break_notification(&response_header) {
+                self.collect_oplock_break(&response_payload[Header::LEN..]);
+                continue;
+            }
             if response_header.command != command {
                 return Err(CoreError::UnexpectedCommand {
                     expected: command,
@@ -1068,6 +1590,11 @@ where
                 let async_id = validate_pending_response(&response_header)?;
                 self.apply_credit_grant(u32::from(response_header.credit_request_response))?;
                 pending_async_id = Some(async_id);
+                self.outstanding = Some(OutstandingRequest {
+                    command,
+                    message_id,
+                    async_id: Some(async_id),
+                });
                 trace!(
                     ?command,
                     message_id = response_header.message_id.0,
@@ -1077,12 +1604,28 @@ where
                 continue;
             }
 
+            self.outstanding = None;
             if let Some(async_id) = pending_async_id {
                 validate_async_final_response(&response_header, async_id)?;
             }
 
+            debug!(
+                ?command,
+                message_id = response_header.message_id.0,
+                tree_id = response_header.tree_id.0,
+                status = %NtStatus(response_header.status),
+                "received smb response"
+            );
+            #[cfg(feature = "packet-trace")]
+            trace!(
+                ?command,
+                message_id = response_header.message_id.0,
+                packet = %hex_dump(&response_payload),
+                "smb response bytes"
+            );
+
             if command != Command::SessionSetup && !encrypted_response {
-                verify_response_signature(&response_header, &response_payload, &context)?;
+                verify_response_signature(&response_header, &response_payload, context)?;
             }
             self.apply_credit_grant(u32::from(response_header.credit_request_response))?;
             if !accepted_statuses.contains(&response_header.status) {
@@ -1100,61 +1643,211 @@ where
         }
     }
 
-    fn preview_message_ids(&self, count: usize) -> Result<Vec<MessageId>, CoreError> {
-        let count = self.validate_request_count(count)?;
-        let start = self.next_message_id;
-
-        Ok((0..count)
-            .map(|offset| MessageId(start + u64::from(offset)))
-            .collect())
+    /// Forgets the outstanding request after a failure that ended its exchange.
+    ///
+    /// A timed-out read leaves the request in flight on the server, so it stays
+    /// outstanding for `cancel` to drain. Any other failure means the response
+    /// was consumed or the transport is gone, and keeping the request would
+    /// only make every later call, including `logoff`, fail for the wrong reason.
+    fn release_outstanding_on_error<R>(&mut self, result: &Result<R, CoreError>) {
+        let still_in_flight = match result {
+            Ok(_) => return,
+            Err(CoreError::Timeout(_)) => true,
+            Err(CoreError::Io(error)) => error.kind() == std::io::ErrorKind::TimedOut,
+            Err(_) => false,
+        };
+        if !still_in_flight {
+            self.outstanding = None;
+        }
     }
 
-    fn commit_message_ids(&mut self, count: usize) -> Result<(), CoreError> {
-        let count = self.validate_request_count(count)?;
-        self.next_message_id =
-            self.next_message_id
-                .checked_add(u64::from(count))
-                .ok_or(CoreError::InvalidInput(
-                    "message id space exhausted for SMB request dispatch",
-                ))?;
-        self.available_credits -= count;
-        Ok(())
+    async fn cancel_outstanding(
+        &mut self,
+        message_id: MessageId,
+        context: RequestContext,
+    ) -> Result<NtStatus, CoreError> {
+        let outstanding = self
+            .outstanding
+            .filter(|request| request.message_id == message_id)
+            .ok_or(CoreError::InvalidInput(
+                "no outstanding request uses the given message id",
+            ))?;
+        let result = self.send_cancel(outstanding, context).await;
+        self.release_outstanding_on_error(&result);
+        result
     }
 
-    fn validate_request_count(&self, count: usize) -> Result<u32, CoreError> {
-        if count == 0 {
-            return Err(CoreError::InvalidInput(
-                "compound request chain must contain at least one element",
-            ));
+    async fn send_cancel(
+        &mut self,
+        outstanding: OutstandingRequest,
+        context: RequestContext,
+    ) -> Result<NtStatus, CoreError> {
+        let message_id = outstanding.message_id;
+
+        let mut header = Header::new(Command::Cancel, message_id);
+        header.process_id = self.process_id;
+        header.session_id = context.session_id;
+        header.tree_id = context.tree_id;
+        header.credit_charge = CreditCharge(0);
+        header.credit_request_response = 0;
+        if let Some(async_id) = outstanding.async_id {
+            header.flags |= HeaderFlags::ASYNC_COMMAND;
+            header.async_id = Some(async_id);
         }
-        let count = u32::try_from(count)
-            .map_err(|_| CoreError::InvalidInput("too many SMB requests in one chain"))?;
-        if self.available_credits < count {
-            return Err(CoreError::Unsupported(
-                "insufficient SMB credits available for the requested chain",
-            ));
+        if context.signing.is_some() && !context.should_encrypt() {
+            header.flags |= HeaderFlags::SIGNED;
         }
 
-        Ok(count)
-    }
-
-    fn apply_credit_grant(&mut self, granted: u32) -> Result<(), CoreError> {
-        self.available_credits =
-            self.available_credits
-                .checked_add(granted)
-                .ok_or(CoreError::InvalidResponse(
-                    "server granted too many SMB credits",
-                ))?;
-        Ok(())
-    }
+        let mut packet = header.encode();
+        packet.extend_from_slice(&CancelRequest.encode());
+        if let Some(signing) = context
+            .signing
+            .as_deref()
+            .filter(|_| !context.should_encrypt())
+        {
+            signing.sign_packet(&mut packet)?;
+        }
+        let message = encode_transport_payload(&packet, &context)?;
 
-    fn credit_request_hint_after_reserve(&self, count: usize) -> u16 {
-        const TARGET_CREDIT_FLOOR: u32 = 32;
+        debug!(
+            command = ?outstanding.command,
+            message_id = message_id.0,
+            async_id = outstanding.async_id.map(|async_id| async_id.0),
+            "cancelling outstanding smb request"
+        );
+        self.transport
+            .send_message(&message)
+            .instrument(trace_span!("smb_cancel", message_id = message_id.0))
+            .await?;
 
-        let count = u32::try_from(count).unwrap_or(u32::MAX);
-        let available_after_reserve = self.available_credits.saturating_sub(count);
-        count
-            .max(TARGET_CREDIT_FLOOR.saturating_sub(available_after_reserve))
+        // The server still completes the cancelled request, normally with
+        // STATUS_CANCELLED, and that response must be drained before the
+        // connection can carry new requests.
+        loop {
+            let response_message = self
+                .transport
+                .recv_message()
+                .instrument(trace_span!(
+                    "smb_recv",
+                    command = ?outstanding.command,
+                    message_id = message_id.0
+                ))
+                .await?;
+            let (response_payload, encrypted_response) =
+                decode_transport_payload(&response_message, &context)?;
+            if response_payload.len() < Header::LEN {
+                return Err(CoreError::InvalidResponse(
+                    "response shorter than SMB2 header",
+                ));
+            }
+
+            let response_header = Header::decode(&response_payload[..Header::LEN])?;
+            if is_oplock_break_notification(&response_header) {
+                self.collect_oplock_break(&response_payload[Header::LEN..]);
+                continue;
+            }
+            if response_header.command != outstanding.command {
+                return Err(CoreError::UnexpectedCommand {
+                    expected: outstanding.command,
+                    actual: response_header.command,
+                });
+            }
+            if response_header.message_id != message_id {
+                return Err(CoreError::InvalidResponse(
+                    "response message id did not match the request",
+                ));
+            }
+
+            if response_header.status == NtStatus::PENDING.to_u32() {
+                let async_id = validate_pending_response(&response_header)?;
+                self.apply_credit_grant(u32::from(response_header.credit_request_response))?;
+                self.outstanding = Some(OutstandingRequest {
+                    async_id: Some(async_id),
+                    ..outstanding
+                });
+                continue;
+            }
+
+            self.outstanding = None;
+            if !encrypted_response {
+                verify_response_signature(&response_header, &response_payload, &context)?;
+            }
+            self.apply_credit_grant(u32::from(response_header.credit_request_response))?;
+            let status = NtStatus(response_header.status);
+            debug!(
+                command = ?outstanding.command,
+                message_id = message_id.0,
+                %status,
+                "cancelled smb request completed"
+            );
+            return Ok(status);
+        }
+    }
+
+    fn ensure_no_outstanding_request(&self) -> Result<(), CoreError> {
+        if self.outstanding.is_some() {
+            return Err(CoreError::InvalidInput(
+                "a previous request is still outstanding; cancel it before sending another",
+            ));
+        }
+        Ok(())
+    }
+
+    fn preview_message_ids(&self, count: usize) -> Result<Vec<MessageId>, CoreError> {
+        let count = self.validate_request_count(count)?;
+        let start = self.next_message_id;
+
+        Ok((0..count)
+            .map(|offset| MessageId(start + u64::from(offset)))
+            .collect())
+    }
+
+    fn commit_message_ids(&mut self, count: usize) -> Result<(), CoreError> {
+        let count = self.validate_request_count(count)?;
+        self.next_message_id =
+            self.next_message_id
+                .checked_add(u64::from(count))
+                .ok_or(CoreError::InvalidInput(
+                    "message id space exhausted for SMB request dispatch",
+                ))?;
+        self.available_credits -= count;
+        Ok(())
+    }
+
+    fn validate_request_count(&self, count: usize) -> Result<u32, CoreError> {
+        if count == 0 {
+            return Err(CoreError::InvalidInput(
+                "compound request chain must contain at least one element",
+            ));
+        }
+        let count = u32::try_from(count)
+            .map_err(|_| CoreError::InvalidInput("too many SMB requests in one chain"))?;
+        if self.available_credits < count {
+            return Err(CoreError::Unsupported(
+                "insufficient SMB credits available for the requested chain",
+            ));
+        }
+
+        Ok(count)
+    }
+
+    fn apply_credit_grant(&mut self, granted: u32) -> Result<(), CoreError> {
+        self.available_credits =
+            self.available_credits
+                .checked_add(granted)
+                .ok_or(CoreError::InvalidResponse(
+                    "server granted too many SMB credits",
+                ))?;
+        Ok(())
+    }
+
+    fn credit_request_hint_after_reserve(&self, count: usize) -> u16 {
+        const TARGET_CREDIT_FLOOR: u32 = 32;
+
+        let count = u32::try_from(count).unwrap_or(u32::MAX);
+        let available_after_reserve = self.available_credits.saturating_sub(count);
+        count
+            .max(TARGET_CREDIT_FLOOR.saturating_sub(available_after_reserve))
             .min(u32::from(u16::MAX)) as u16
     }
 
@@ -1189,6 +1882,7 @@ where
         let mut packets = Vec::with_capacity(requests.len());
         for (index, (request, message_id)) in requests.iter().zip(message_ids.iter()).enumerate() {
             let mut header = Header::new(request.command, *message_id);
+            header.process_id = self.process_id;
             header.session_id = context.session_id;
             header.tree_id = context.tree_id;
             header.credit_request_response = if index + 1 == requests.len() {
@@ -1257,15 +1951,16 @@ mod tests {
     use smolder_proto::smb::smb2::{
         AsyncId, ChangeNotifyFlags, ChangeNotifyRequest, ChangeNotifyResponse, CipherId,
         CloseRequest, CloseResponse, Command, CompletionFilter, CompressionCapabilities,
-        CreateRequest, CreateResponse, Dialect, EchoResponse, EncryptionCapabilities,
+        CreateRequest, CreateResponse, CreditCharge, Dialect, EchoResponse, EncryptionCapabilities,
         FileAttributes, FileId, FlushRequest, FlushResponse, GlobalCapabilities, Header,
         HeaderFlags, IoctlRequest, IoctlResponse, LockElement, LockFlags, LockRequest,
         LockResponse, LogoffRequest, LogoffResponse, MessageId, NegotiateContext, NegotiateRequest,
-        NegotiateResponse, OplockLevel, PreauthIntegrityCapabilities, PreauthIntegrityHashId,
-        ReadRequest, ReadResponse, ReadResponseFlags, SessionFlags, SessionId, SessionSetupRequest,
-        SessionSetupResponse, SessionSetupSecurityMode, ShareFlags, ShareType, SigningMode,
-        TransportCapabilities, TransportCapabilityFlags, TreeCapabilities, TreeConnectRequest,
-        TreeConnectResponse, TreeDisconnectRequest, TreeId, WriteRequest, WriteResponse,
+        NegotiateResponse, OplockBreak, OplockLevel, PreauthIntegrityCapabilities,
+        PreauthIntegrityHashId, ReadRequest, ReadResponse, ReadResponseFlags, SessionFlags,
+        SessionId, SessionSetupRequest, SessionSetupResponse, SessionSetupSecurityMode, ShareFlags,
+        ShareType, SigningMode, TransportCapabilities, TransportCapabilityFlags, TreeCapabilities,
+        TreeConnectRequest, TreeConnectResponse, TreeDisconnectRequest, TreeId, WriteRequest,
+        WriteResponse,
     };
     use smolder_proto::smb::status::NtStatus;
     use smolder_proto::smb::transform::TransformHeader;
@@ -1357,9 +2052,17 @@ mod tests {
         }
 
         async fn recv(&mut self) -> std::io::Result<Vec<u8>> {
-            self.reads.pop_front().ok_or_else(|| {
-                std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "no scripted response")
-            })
+            match self.reads.pop_front() {
+                Some(frame) if frame.is_empty() => Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "scripted read timeout",
+                )),
+                Some(frame) => Ok(frame),
+                None => Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "no scripted response",
+                )),
+            }
         }
     }
 
@@ -1540,6 +2243,7 @@ mod tests {
         signing.sign_packet(packet).expect("response should sign");
     }
 
+    #[allow(clippy::type_complexity)]
     fn compound_response_frame(
         elements: Vec<(Command, u32, u64, u64, u32, u16, Vec<u8>)>,
     ) -> Vec<u8> {
@@ -1629,12 +2333,12 @@ mod tests {
     fn smb_session_key_uses_first_16_bytes_and_zero_pads_short_keys() {
         let full_key: Vec<u8> = (0u8..32).collect();
         assert_eq!(
-            super::derive_smb_session_key(Some(&full_key)),
+            super::derive_smb_session_key(Some(&full_key)).map(|key| key.to_vec()),
             Some((0u8..16).collect())
         );
 
         assert_eq!(
-            super::derive_smb_session_key(Some(&[0x41, 0x42, 0x43])),
+            super::derive_smb_session_key(Some(&[0x41, 0x42, 0x43])).map(|key| key.to_vec()),
             Some(vec![
                 0x41, 0x42, 0x43, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
             ])
@@ -2192,7 +2896,7 @@ mod tests {
             .expect("authenticate should succeed");
 
         assert_eq!(connection.state().session_id, SessionId(77));
-        assert_eq!(connection.state().session_key, Some(vec![0x55; 16]));
+        assert_eq!(connection.session_key(), Some(&[0x55; 16][..]));
         assert!(auth_provider.finished);
 
         let transport = connection.into_transport();
@@ -2319,6 +3023,14 @@ mod tests {
             .tree_connect(&TreeConnectRequest::from_unc(r"\\server\share"))
             .await
             .expect("tree connect should succeed");
+        assert_eq!(
+            connection.server_capabilities(),
+            GlobalCapabilities::DFS | GlobalCapabilities::LARGE_MTU
+        );
+        assert_eq!(connection.max_read_size(), 65_536);
+        assert_eq!(connection.max_write_size(), 65_536);
+        assert_eq!(connection.max_transact_size(), 65_536);
+        assert!(!connection.signing_required());
 
         let interfaces = connection
             .query_network_interfaces(16 * 1024)
@@ -2342,6 +3054,129 @@ mod tests {
         assert_eq!(request.max_output_response, 16 * 1024);
     }
 
+    #[tokio::test]
+    async fn fsctl_returns_output_and_rejects_oversized_responses() {
+        let ctl_code = smolder_proto::smb::smb2::CtlCode(0x0009_00a8);
+        let ioctl_response = IoctlResponse {
+            ctl_code,
+            file_id: FileId::NONE,
+            input: Vec::new(),
+            output: vec![1, 2, 3, 4],
+            flags: 0,
+        };
+        let transport = ScriptedTransport::new(vec![
+            response_frame(
+                Command::Negotiate,
+                NtStatus::SUCCESS.to_u32(),
+                0,
+                0,
+                0,
+                NegotiateResponse {
+                    security_mode: SigningMode::ENABLED,
+                    dialect_revision: Dialect::Smb302,
+                    negotiate_contexts: Vec::new(),
+                    server_guid: *b"server-guid-0021",
+                    capabilities: GlobalCapabilities::LARGE_MTU,
+                    max_transact_size: 65_536,
+                    max_read_size: 65_536,
+                    max_write_size: 65_536,
+                    system_time: 1,
+                    server_start_time: 1,
+                    security_buffer: Vec::new(),
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::SessionSetup,
+                NtStatus::SUCCESS.to_u32(),
+                1,
+                55,
+                0,
+                SessionSetupResponse {
+                    session_flags: SessionFlags::empty(),
+                    security_buffer: Vec::new(),
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::TreeConnect,
+                NtStatus::SUCCESS.to_u32(),
+                2,
+                55,
+                9,
+                TreeConnectResponse {
+                    share_type: ShareType::Disk,
+                    share_flags: ShareFlags::empty(),
+                    capabilities: TreeCapabilities::empty(),
+                    maximal_access: 0x0012_019f,
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::Ioctl,
+                NtStatus::SUCCESS.to_u32(),
+                3,
+                55,
+                9,
+                ioctl_response.encode(),
+            ),
+            response_frame(
+                Command::Ioctl,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                55,
+                9,
+                ioctl_response.encode(),
+            ),
+        ]);
+        let session_request = SessionSetupRequest {
+            flags: 0,
+            security_mode: SessionSetupSecurityMode::SIGNING_ENABLED,
+            capabilities: 0,
+            channel: 0,
+            security_buffer: vec![0x60, 0x48],
+            previous_session_id: 0,
+        };
+        let mut connection = Connection::new(transport)
+            .negotiate(&NegotiateRequest {
+                security_mode: SigningMode::ENABLED,
+                capabilities: GlobalCapabilities::LARGE_MTU,
+                client_guid: *b"client-guid-0021",
+                dialects: vec![Dialect::Smb210, Dialect::Smb302],
+                negotiate_contexts: Vec::new(),
+            })
+            .await
+            .expect("negotiate should succeed")
+            .session_setup(&session_request)
+            .await
+            .expect("session setup should succeed")
+            .tree_connect(&TreeConnectRequest::from_unc(r"\\server\share"))
+            .await
+            .expect("tree connect should succeed");
+
+        let output = connection
+            .fsctl(ctl_code, FileId::NONE, vec![0xaa], 64)
+            .await
+            .expect("fsctl should succeed");
+        assert_eq!(output, vec![1, 2, 3, 4]);
+
+        let error = connection
+            .fsctl(ctl_code, FileId::NONE, Vec::new(), 2)
+            .await
+            .expect_err("oversized output should be rejected");
+        assert!(matches!(
+            error,
+            CoreError::InvalidResponse("ioctl response output exceeded max_output_response")
+        ));
+
+        let transport = connection.into_transport();
+        let request = IoctlRequest::decode(&transport.writes[3][4 + Header::LEN..])
+            .expect("fsctl request should decode");
+        assert_eq!(request.ctl_code, ctl_code);
+        assert_eq!(request.max_output_response, 64);
+        assert_eq!(request.input, vec![0xaa]);
+    }
+
     #[tokio::test]
     async fn ioctl_requests_resume_key_for_open_file() {
         let negotiate_response = NegotiateResponse {
@@ -2488,16 +3323,16 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn compound_raw_uses_consecutive_message_ids_and_related_flag() {
+    async fn large_reads_charge_multiple_credits_and_message_ids() {
         let negotiate_response = NegotiateResponse {
             security_mode: SigningMode::ENABLED,
             dialect_revision: Dialect::Smb311,
-            negotiate_contexts: vec![preauth_context(b"server-salt-0100")],
-            server_guid: *b"server-guid-0100",
+            negotiate_contexts: vec![preauth_context(b"server-salt-0101")],
+            server_guid: *b"server-guid-0101",
             capabilities: GlobalCapabilities::LARGE_MTU,
-            max_transact_size: 65_536,
-            max_read_size: 65_536,
-            max_write_size: 65_536,
+            max_transact_size: 1 << 20,
+            max_read_size: 1 << 20,
+            max_write_size: 1 << 20,
             system_time: 1,
             server_start_time: 1,
             security_buffer: vec![0x60, 0x03],
@@ -2516,8 +3351,6 @@ mod tests {
             persistent: 0x11,
             volatile: 0x22,
         };
-        let write_body = WriteRequest::for_file(file_id, 0, b"hello".to_vec()).encode();
-        let flush_body = FlushRequest::for_file(file_id).encode();
         let transport = ScriptedTransport::new(vec![
             response_frame_with_credits(
                 Command::Negotiate,
@@ -2544,39 +3377,169 @@ mod tests {
                 9,
                 tree_response.encode(),
             ),
-            compound_response_frame(vec![
-                (
-                    Command::Write,
-                    NtStatus::SUCCESS.to_u32(),
-                    3,
-                    55,
-                    9,
-                    1,
-                    WriteResponse { count: 5 }.encode(),
-                ),
-                (
-                    Command::Flush,
-                    NtStatus::SUCCESS.to_u32(),
-                    4,
-                    55,
-                    9,
-                    1,
-                    FlushResponse.encode(),
-                ),
-            ]),
-        ]);
-        let negotiate_request = NegotiateRequest {
-            security_mode: SigningMode::ENABLED,
-            capabilities: GlobalCapabilities::LARGE_MTU,
-            client_guid: *b"client-guid-0100",
-            dialects: vec![Dialect::Smb210, Dialect::Smb302, Dialect::Smb311],
-            negotiate_contexts: vec![preauth_context(b"client-salt-0100")],
-        };
-        let session_request = SessionSetupRequest {
-            flags: 0,
-            security_mode: SessionSetupSecurityMode::SIGNING_ENABLED,
-            capabilities: 0,
-            channel: 0,
+            response_frame_with_credits(
+                Command::Read,
+                NtStatus::SUCCESS.to_u32(),
+                3,
+                55,
+                9,
+                2,
+                ReadResponse {
+                    data_remaining: 0,
+                    flags: ReadResponseFlags::empty(),
+                    data: vec![0x5a; 128 * 1024],
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::Write,
+                NtStatus::SUCCESS.to_u32(),
+                5,
+                55,
+                9,
+                WriteResponse { count: 5 }.encode(),
+            ),
+        ]);
+        let negotiate_request = NegotiateRequest {
+            security_mode: SigningMode::ENABLED,
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            client_guid: *b"client-guid-0101",
+            dialects: vec![Dialect::Smb210, Dialect::Smb302, Dialect::Smb311],
+            negotiate_contexts: vec![preauth_context(b"client-salt-0101")],
+        };
+        let session_request = SessionSetupRequest {
+            flags: 0,
+            security_mode: SessionSetupSecurityMode::SIGNING_ENABLED,
+            capabilities: 0,
+            channel: 0,
+            security_buffer: vec![0x60, 0x48],
+            previous_session_id: 0,
+        };
+        let mut connection = Connection::new(transport)
+            .negotiate(&negotiate_request)
+            .await
+            .expect("negotiate should succeed")
+            .session_setup(&session_request)
+            .await
+            .expect("session setup should succeed")
+            .tree_connect(&TreeConnectRequest::from_unc(r"\\server\share"))
+            .await
+            .expect("tree connect should succeed");
+        assert!(connection.supports_multi_credit());
+        let credits_before = connection.available_credits();
+
+        let read = connection
+            .read(&ReadRequest::for_file(file_id, 0, 128 * 1024))
+            .await
+            .expect("large read should succeed");
+        assert_eq!(read.data.len(), 128 * 1024);
+        assert_eq!(connection.available_credits(), credits_before);
+        connection
+            .write(&WriteRequest::for_file(file_id, 0, b"hello".to_vec()))
+            .await
+            .expect("small write should succeed");
+
+        let transport = connection.into_transport();
+        let read_header = outbound_header(&transport.writes[3]);
+        assert_eq!(read_header.command, Command::Read);
+        assert_eq!(read_header.message_id, MessageId(3));
+        assert_eq!(read_header.credit_charge, CreditCharge(2));
+        let write_header = outbound_header(&transport.writes[4]);
+        assert_eq!(write_header.command, Command::Write);
+        assert_eq!(write_header.message_id, MessageId(5));
+        assert_eq!(write_header.credit_charge, CreditCharge(1));
+    }
+
+    #[tokio::test]
+    async fn compound_raw_uses_consecutive_message_ids_and_related_flag() {
+        let negotiate_response = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb311,
+            negotiate_contexts: vec![preauth_context(b"server-salt-0100")],
+            server_guid: *b"server-guid-0100",
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            max_transact_size: 65_536,
+            max_read_size: 65_536,
+            max_write_size: 65_536,
+            system_time: 1,
+            server_start_time: 1,
+            security_buffer: vec![0x60, 0x03],
+        };
+        let session_response = SessionSetupResponse {
+            session_flags: SessionFlags::empty(),
+            security_buffer: vec![0xa1, 0x01],
+        };
+        let tree_response = TreeConnectResponse {
+            share_type: ShareType::Disk,
+            share_flags: ShareFlags::empty(),
+            capabilities: TreeCapabilities::empty(),
+            maximal_access: 0x0012_019f,
+        };
+        let file_id = FileId {
+            persistent: 0x11,
+            volatile: 0x22,
+        };
+        let write_body = WriteRequest::for_file(file_id, 0, b"hello".to_vec()).encode();
+        let flush_body = FlushRequest::for_file(file_id).encode();
+        let transport = ScriptedTransport::new(vec![
+            response_frame_with_credits(
+                Command::Negotiate,
+                NtStatus::SUCCESS.to_u32(),
+                0,
+                0,
+                0,
+                32,
+                negotiate_response.encode(),
+            ),
+            response_frame(
+                Command::SessionSetup,
+                NtStatus::SUCCESS.to_u32(),
+                1,
+                55,
+                0,
+                session_response.encode(),
+            ),
+            response_frame(
+                Command::TreeConnect,
+                NtStatus::SUCCESS.to_u32(),
+                2,
+                55,
+                9,
+                tree_response.encode(),
+            ),
+            compound_response_frame(vec![
+                (
+                    Command::Write,
+                    NtStatus::SUCCESS.to_u32(),
+                    3,
+                    55,
+                    9,
+                    1,
+                    WriteResponse { count: 5 }.encode(),
+                ),
+                (
+                    Command::Flush,
+                    NtStatus::SUCCESS.to_u32(),
+                    4,
+                    55,
+                    9,
+                    1,
+                    FlushResponse.encode(),
+                ),
+            ]),
+        ]);
+        let negotiate_request = NegotiateRequest {
+            security_mode: SigningMode::ENABLED,
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            client_guid: *b"client-guid-0100",
+            dialects: vec![Dialect::Smb210, Dialect::Smb302, Dialect::Smb311],
+            negotiate_contexts: vec![preauth_context(b"client-salt-0100")],
+        };
+        let session_request = SessionSetupRequest {
+            flags: 0,
+            security_mode: SessionSetupSecurityMode::SIGNING_ENABLED,
+            capabilities: 0,
+            channel: 0,
             security_buffer: vec![0x60, 0x48],
             previous_session_id: 0,
         };
@@ -2901,6 +3864,86 @@ mod tests {
         assert_eq!(header.tree_id, TreeId(0));
     }
 
+    #[tokio::test]
+    async fn pinned_process_id_is_stamped_into_every_request_header() {
+        let negotiate_response = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb302,
+            negotiate_contexts: Vec::new(),
+            server_guid: *b"server-guid-0012",
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            max_transact_size: 65_536,
+            max_read_size: 65_536,
+            max_write_size: 65_536,
+            system_time: 1,
+            server_start_time: 1,
+            security_buffer: Vec::new(),
+        };
+        let session_response = SessionSetupResponse {
+            session_flags: SessionFlags::empty(),
+            security_buffer: Vec::new(),
+        };
+        let transport = ScriptedTransport::new(vec![
+            response_frame(
+                Command::Negotiate,
+                NtStatus::SUCCESS.to_u32(),
+                0,
+                0,
+                0,
+                negotiate_response.encode(),
+            ),
+            response_frame(
+                Command::SessionSetup,
+                NtStatus::SUCCESS.to_u32(),
+                1,
+                44,
+                0,
+                session_response.encode(),
+            ),
+            response_frame(
+                Command::Echo,
+                NtStatus::SUCCESS.to_u32(),
+                2,
+                44,
+                0,
+                EchoResponse.encode(),
+            ),
+        ]);
+        let session_request = SessionSetupRequest {
+            flags: 0,
+            security_mode: SessionSetupSecurityMode::SIGNING_ENABLED,
+            capabilities: 0,
+            channel: 0,
+            security_buffer: vec![0x60, 0x48],
+            previous_session_id: 0,
+        };
+
+        let mut connection = Connection::new(transport);
+        assert_eq!(connection.process_id(), 0);
+        connection.set_process_id(0xfeff);
+        let mut connection = connection
+            .negotiate(&NegotiateRequest {
+                security_mode: SigningMode::ENABLED,
+                capabilities: GlobalCapabilities::LARGE_MTU,
+                client_guid: *b"client-guid-0012",
+                dialects: vec![Dialect::Smb210, Dialect::Smb302],
+                negotiate_contexts: Vec::new(),
+            })
+            .await
+            .expect("negotiate should succeed")
+            .session_setup(&session_request)
+            .await
+            .expect("session setup should succeed");
+        connection.echo().await.expect("echo should succeed");
+        assert_eq!(connection.process_id(), 0xfeff);
+
+        let transport = connection.into_transport();
+        assert_eq!(transport.writes.len(), 3);
+        for frame in &transport.writes {
+            assert_eq!(outbound_header(frame).process_id, 0xfeff);
+        }
+    }
+
     #[tokio::test]
     async fn tree_connect_encrypts_when_session_requires_encryption() {
         let session_key = [0x77; 16];
@@ -2994,26 +4037,23 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn authenticate_accepts_compressed_session_setup_response_when_negotiated() {
+    async fn require_encryption_encrypts_when_server_does_not_ask() {
+        let session_key = [0x78; 16];
+        let client_encryption = smb302_encryption_state(&session_key);
+        let server_encryption = peer_encryption_state(client_encryption.as_ref());
         let negotiate_request = NegotiateRequest {
             security_mode: SigningMode::ENABLED,
-            capabilities: GlobalCapabilities::LARGE_MTU,
-            client_guid: *b"client-guid-cmpr",
-            dialects: vec![Dialect::Smb311],
-            negotiate_contexts: vec![
-                preauth_context(b"client-salt-cmpr"),
-                compression_context(CompressionAlgorithm::Lznt1),
-            ],
+            capabilities: GlobalCapabilities::LARGE_MTU | GlobalCapabilities::ENCRYPTION,
+            client_guid: *b"client-guid-enc2",
+            dialects: vec![Dialect::Smb210, Dialect::Smb302],
+            negotiate_contexts: vec![encryption_context(CipherId::Aes128Ccm)],
         };
-        let negotiate_response = NegotiateResponse {
+        let negotiate_response = |capabilities| NegotiateResponse {
             security_mode: SigningMode::ENABLED,
-            dialect_revision: Dialect::Smb311,
-            negotiate_contexts: vec![
-                preauth_context(b"server-salt-cmpr"),
-                compression_context(CompressionAlgorithm::Lznt1),
-            ],
-            server_guid: *b"server-guid-cmpr",
-            capabilities: GlobalCapabilities::LARGE_MTU,
+            dialect_revision: Dialect::Smb302,
+            negotiate_contexts: vec![encryption_context(CipherId::Aes128Ccm)],
+            server_guid: *b"server-guid-enc2",
+            capabilities,
             max_transact_size: 65_536,
             max_read_size: 65_536,
             max_write_size: 65_536,
@@ -3025,6 +4065,12 @@ mod tests {
             session_flags: SessionFlags::empty(),
             security_buffer: Vec::new(),
         };
+        let tree_response = TreeConnectResponse {
+            share_type: ShareType::Disk,
+            share_flags: ShareFlags::empty(),
+            capabilities: TreeCapabilities::empty(),
+            maximal_access: 0x0012_019f,
+        };
         let transport = ScriptedTransport::new(vec![
             response_frame(
                 Command::Negotiate,
@@ -3032,21 +4078,155 @@ mod tests {
                 0,
                 0,
                 0,
-                negotiate_response.encode(),
+                negotiate_response(GlobalCapabilities::LARGE_MTU | GlobalCapabilities::ENCRYPTION)
+                    .encode(),
             ),
-            compressed_response_frame(
+            response_frame(
                 Command::SessionSetup,
                 NtStatus::SUCCESS.to_u32(),
                 1,
-                44,
+                78,
                 0,
                 session_response.encode(),
             ),
-        ]);
-
-        let mut auth_provider = MockAuthProvider {
-            initial_token: vec![0x01, 0x02],
-            challenge_token: Vec::new(),
+            encrypted_response_frame(
+                &server_encryption,
+                Command::TreeConnect,
+                NtStatus::SUCCESS.to_u32(),
+                2,
+                78,
+                9,
+                tree_response.encode(),
+            ),
+        ]);
+        let mut auth_provider = MockAuthProvider {
+            initial_token: vec![0x01, 0x02],
+            challenge_token: Vec::new(),
+            final_token: Vec::new(),
+            session_key: Some(session_key.to_vec()),
+            finished: false,
+        };
+
+        let mut connection = Connection::new(transport)
+            .negotiate(&negotiate_request)
+            .await
+            .expect("negotiate should succeed")
+            .authenticate(&mut auth_provider)
+            .await
+            .expect("authenticate should succeed");
+        assert!(!connection.encryption_required());
+        let application_key = connection
+            .application_key()
+            .expect("application key should derive")
+            .expect("SMB 3.0.2 sessions have an application key");
+        assert_eq!(application_key.len(), 16);
+        connection
+            .require_encryption()
+            .expect("negotiated cipher should satisfy the requirement");
+        let connection = connection
+            .tree_connect(&TreeConnectRequest::from_unc(r"\\server\share"))
+            .await
+            .expect("tree connect should succeed");
+        assert!(connection.state().encryption_required);
+
+        let transport = connection.into_transport();
+        let packet = outbound_encrypted_packet(&transport.writes[2], &server_encryption);
+        let header = Header::decode(&packet[..Header::LEN]).expect("header should decode");
+        assert_eq!(header.command, Command::TreeConnect);
+
+        let transport = ScriptedTransport::new(vec![
+            response_frame(
+                Command::Negotiate,
+                NtStatus::SUCCESS.to_u32(),
+                0,
+                0,
+                0,
+                negotiate_response(GlobalCapabilities::LARGE_MTU).encode(),
+            ),
+            response_frame(
+                Command::SessionSetup,
+                NtStatus::SUCCESS.to_u32(),
+                1,
+                79,
+                0,
+                session_response.encode(),
+            ),
+        ]);
+        let mut auth_provider = MockAuthProvider {
+            initial_token: vec![0x01, 0x02],
+            challenge_token: Vec::new(),
+            final_token: Vec::new(),
+            session_key: Some(session_key.to_vec()),
+            finished: false,
+        };
+        let mut connection = Connection::new(transport)
+            .negotiate(&negotiate_request)
+            .await
+            .expect("negotiate should succeed")
+            .authenticate(&mut auth_provider)
+            .await
+            .expect("authenticate should succeed");
+        let error = connection
+            .require_encryption()
+            .expect_err("a session without a cipher cannot be encrypted");
+        assert!(matches!(error, CoreError::Unsupported(_)));
+        assert!(!connection.encryption_required());
+    }
+
+    #[tokio::test]
+    async fn authenticate_accepts_compressed_session_setup_response_when_negotiated() {
+        let negotiate_request = NegotiateRequest {
+            security_mode: SigningMode::ENABLED,
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            client_guid: *b"client-guid-cmpr",
+            dialects: vec![Dialect::Smb311],
+            negotiate_contexts: vec![
+                preauth_context(b"client-salt-cmpr"),
+                compression_context(CompressionAlgorithm::Lznt1),
+            ],
+        };
+        let negotiate_response = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb311,
+            negotiate_contexts: vec![
+                preauth_context(b"server-salt-cmpr"),
+                compression_context(CompressionAlgorithm::Lznt1),
+            ],
+            server_guid: *b"server-guid-cmpr",
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            max_transact_size: 65_536,
+            max_read_size: 65_536,
+            max_write_size: 65_536,
+            system_time: 1,
+            server_start_time: 1,
+            security_buffer: vec![0x60, 0x03],
+        };
+        let session_response = SessionSetupResponse {
+            session_flags: SessionFlags::empty(),
+            security_buffer: Vec::new(),
+        };
+        let transport = ScriptedTransport::new(vec![
+            response_frame(
+                Command::Negotiate,
+                NtStatus::SUCCESS.to_u32(),
+                0,
+                0,
+                0,
+                negotiate_response.encode(),
+            ),
+            compressed_response_frame(
+                Command::SessionSetup,
+                NtStatus::SUCCESS.to_u32(),
+                1,
+                44,
+                0,
+                session_response.encode(),
+            ),
+        ]);
+
+        let mut auth_provider = MockAuthProvider {
+            initial_token: vec![0x01, 0x02],
+            challenge_token: Vec::new(),
             final_token: Vec::new(),
             session_key: None,
             finished: false,
@@ -3648,6 +4828,151 @@ mod tests {
         assert_eq!(header.tree_id, TreeId(9));
     }
 
+    #[tokio::test]
+    async fn oplock_break_notifications_are_queued_and_acknowledged() {
+        let file_id = FileId {
+            persistent: 0x1122_3344_5566_7788,
+            volatile: 0x8877_6655_4433_2211,
+        };
+        let notification = OplockBreak {
+            oplock_level: OplockLevel::II,
+            file_id,
+        };
+        // A lease break notification: StructureSize 44 and a lease key.
+        let mut lease_break = vec![0; 44];
+        lease_break[..2].copy_from_slice(&44_u16.to_le_bytes());
+        lease_break[8..24].copy_from_slice(&[0x5a; 16]);
+        let transport = ScriptedTransport::new(vec![
+            response_frame(
+                Command::Negotiate,
+                NtStatus::SUCCESS.to_u32(),
+                0,
+                0,
+                0,
+                NegotiateResponse {
+                    security_mode: SigningMode::ENABLED,
+                    dialect_revision: Dialect::Smb302,
+                    negotiate_contexts: Vec::new(),
+                    server_guid: *b"server-guid-0021",
+                    capabilities: GlobalCapabilities::LARGE_MTU,
+                    max_transact_size: 65_536,
+                    max_read_size: 65_536,
+                    max_write_size: 65_536,
+                    system_time: 1,
+                    server_start_time: 1,
+                    security_buffer: Vec::new(),
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::SessionSetup,
+                NtStatus::SUCCESS.to_u32(),
+                1,
+                55,
+                0,
+                SessionSetupResponse {
+                    session_flags: SessionFlags::empty(),
+                    security_buffer: Vec::new(),
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::TreeConnect,
+                NtStatus::SUCCESS.to_u32(),
+                2,
+                55,
+                9,
+                TreeConnectResponse {
+                    share_type: ShareType::Disk,
+                    share_flags: ShareFlags::empty(),
+                    capabilities: TreeCapabilities::empty(),
+                    maximal_access: 0x0012_019f,
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::OplockBreak,
+                NtStatus::SUCCESS.to_u32(),
+                u64::MAX,
+                0,
+                0,
+                notification.encode(),
+            ),
+            response_frame(
+                Command::OplockBreak,
+                NtStatus::SUCCESS.to_u32(),
+                u64::MAX,
+                0,
+                0,
+                lease_break,
+            ),
+            response_frame(
+                Command::Flush,
+                NtStatus::SUCCESS.to_u32(),
+                3,
+                55,
+                9,
+                FlushResponse.encode(),
+            ),
+            response_frame(
+                Command::OplockBreak,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                55,
+                9,
+                notification.encode(),
+            ),
+        ]);
+        let session_request = SessionSetupRequest {
+            flags: 0,
+            security_mode: SessionSetupSecurityMode::SIGNING_ENABLED,
+            capabilities: 0,
+            channel: 0,
+            security_buffer: vec![0x60, 0x48],
+            previous_session_id: 0,
+        };
+        let mut connection = Connection::new(transport)
+            .negotiate(&NegotiateRequest {
+                security_mode: SigningMode::ENABLED,
+                capabilities: GlobalCapabilities::LARGE_MTU,
+                client_guid: *b"client-guid-0021",
+                dialects: vec![Dialect::Smb210, Dialect::Smb302],
+                negotiate_contexts: Vec::new(),
+            })
+            .await
+            .expect("negotiate should succeed")
+            .session_setup(&session_request)
+            .await
+            .expect("session setup should succeed")
+            .tree_connect(&TreeConnectRequest::from_unc(r"\\server\share"))
+            .await
+            .expect("tree connect should succeed");
+
+        connection
+            .flush(&FlushRequest::for_file(file_id))
+            .await
+            .expect("flush should succeed despite the interleaved break");
+        let breaks = connection.take_oplock_breaks();
+        assert_eq!(breaks, vec![notification]);
+        assert!(connection.take_oplock_breaks().is_empty());
+
+        let acknowledged = connection
+            .acknowledge_oplock_break(&breaks[0])
+            .await
+            .expect("acknowledgment should succeed");
+        assert_eq!(acknowledged, notification);
+
+        let transport = connection.into_transport();
+        let header = outbound_header(&transport.writes[4]);
+        assert_eq!(header.command, Command::OplockBreak);
+        assert_eq!(header.tree_id, TreeId(9));
+        assert_eq!(
+            OplockBreak::decode(&transport.writes[4][4 + Header::LEN..])
+                .expect("acknowledgment should decode"),
+            notification
+        );
+    }
+
     #[tokio::test]
     async fn change_notify_handles_interim_async_response() {
         let file_id = FileId {
@@ -3766,22 +5091,272 @@ mod tests {
         assert_eq!(header.tree_id, TreeId(12));
     }
 
-    #[tokio::test]
-    async fn rejects_tampered_signed_tree_connect_response() {
-        let negotiate_request = NegotiateRequest {
-            security_mode: SigningMode::ENABLED,
-            capabilities: GlobalCapabilities::LARGE_MTU,
-            client_guid: *b"client-guid-0003",
-            dialects: vec![Dialect::Smb210, Dialect::Smb302, Dialect::Smb311],
-            negotiate_contexts: vec![preauth_context(b"client-salt-0003")],
-        };
-        let negotiate_response = NegotiateResponse {
-            security_mode: SigningMode::ENABLED,
-            dialect_revision: Dialect::Smb311,
-            negotiate_contexts: vec![preauth_context(b"server-salt-0003")],
-            server_guid: *b"server-guid-0003",
-            capabilities: GlobalCapabilities::LARGE_MTU,
-            max_transact_size: 65_536,
+    async fn scripted_tree_connection(
+        reads: Vec<Vec<u8>>,
+    ) -> Connection<ScriptedTransport, super::TreeConnected> {
+        let mut frames = vec![
+            response_frame(
+                Command::Negotiate,
+                NtStatus::SUCCESS.to_u32(),
+                0,
+                0,
+                0,
+                NegotiateResponse {
+                    security_mode: SigningMode::ENABLED,
+                    dialect_revision: Dialect::Smb302,
+                    negotiate_contexts: Vec::new(),
+                    server_guid: *b"server-guid-cncl",
+                    capabilities: GlobalCapabilities::LARGE_MTU,
+                    max_transact_size: 65_536,
+                    max_read_size: 65_536,
+                    max_write_size: 65_536,
+                    system_time: 1,
+                    server_start_time: 1,
+                    security_buffer: Vec::new(),
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::SessionSetup,
+                NtStatus::SUCCESS.to_u32(),
+                1,
+                66,
+                0,
+                SessionSetupResponse {
+                    session_flags: SessionFlags::empty(),
+                    security_buffer: Vec::new(),
+                }
+                .encode(),
+            ),
+            SessionMessage::new(response_packet_with_credits(
+                Command::TreeConnect,
+                NtStatus::SUCCESS.to_u32(),
+                2,
+                66,
+                12,
+                8,
+                TreeConnectResponse {
+                    share_type: ShareType::Disk,
+                    share_flags: ShareFlags::empty(),
+                    capabilities: TreeCapabilities::empty(),
+                    maximal_access: 0x0012_019f,
+                }
+                .encode(),
+            ))
+            .encode()
+            .expect("response should frame"),
+        ];
+        frames.extend(reads);
+        let transport = ScriptedTransport::new(frames);
+        let session_request = SessionSetupRequest {
+            flags: 0,
+            security_mode: SessionSetupSecurityMode::SIGNING_ENABLED,
+            capabilities: 0,
+            channel: 0,
+            security_buffer: vec![0x60, 0x48],
+            previous_session_id: 0,
+        };
+        Connection::new(transport)
+            .negotiate(&NegotiateRequest {
+                security_mode: SigningMode::ENABLED,
+                capabilities: GlobalCapabilities::LARGE_MTU,
+                client_guid: *b"client-guid-cncl",
+                dialects: vec![Dialect::Smb210, Dialect::Smb302],
+                negotiate_contexts: Vec::new(),
+            })
+            .await
+            .expect("negotiate should succeed")
+            .session_setup(&session_request)
+            .await
+            .expect("session setup should succeed")
+            .tree_connect(&TreeConnectRequest::from_unc(r"\\server\share"))
+            .await
+            .expect("tree connect should succeed")
+    }
+
+    #[tokio::test]
+    async fn cancel_aborts_an_abandoned_async_request() {
+        let file_id = FileId {
+            persistent: 0x0102_0304_0506_0708,
+            volatile: 0x1112_1314_1516_1718,
+        };
+        let mut connection = scripted_tree_connection(vec![
+            async_response_frame(
+                Command::ChangeNotify,
+                NtStatus::PENDING.to_u32(),
+                3,
+                0x4444,
+                66,
+                Vec::new(),
+            ),
+            // An empty scripted frame makes the read time out.
+            Vec::new(),
+        ])
+        .await;
+
+        let request = ChangeNotifyRequest {
+            flags: ChangeNotifyFlags::WATCH_TREE,
+            output_buffer_length: 4096,
+            file_id,
+            completion_filter: CompletionFilter::FILE_NAME,
+        };
+        connection
+            .change_notify(&request)
+            .await
+            .expect_err("the read should time out after the interim response");
+        assert_eq!(connection.outstanding_request(), Some(MessageId(3)));
+
+        let error = connection
+            .echo()
+            .await
+            .expect_err("new requests should wait for the cancel");
+        assert!(matches!(error, CoreError::InvalidInput(_)));
+        let error = connection
+            .cancel(MessageId(4))
+            .await
+            .expect_err("only the outstanding request can be cancelled");
+        assert!(matches!(error, CoreError::InvalidInput(_)));
+
+        connection.transport.reads.extend([
+            async_response_frame(
+                Command::ChangeNotify,
+                NtStatus::CANCELLED.to_u32(),
+                3,
+                0x4444,
+                66,
+                Vec::new(),
+            ),
+            response_frame(
+                Command::Echo,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                66,
+                12,
+                EchoResponse.encode(),
+            ),
+        ]);
+        let status = connection
+            .cancel(MessageId(3))
+            .await
+            .expect("cancel should drain the final response");
+        assert_eq!(status, NtStatus::CANCELLED);
+        assert_eq!(connection.outstanding_request(), None);
+        connection
+            .echo()
+            .await
+            .expect("the connection should be usable after the cancel");
+
+        let transport = connection.into_transport();
+        assert_eq!(transport.writes.len(), 6);
+        let header = outbound_header(&transport.writes[4]);
+        assert_eq!(header.command, Command::Cancel);
+        assert_eq!(header.message_id, MessageId(3));
+        assert!(header.flags.contains(HeaderFlags::ASYNC_COMMAND));
+        assert_eq!(header.async_id, Some(AsyncId(0x4444)));
+        assert_eq!(header.credit_charge, CreditCharge(0));
+        assert_eq!(header.session_id, SessionId(66));
+        assert_eq!(
+            outbound_header(&transport.writes[5]).message_id,
+            MessageId(4)
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_exchanges_do_not_wedge_the_connection() {
+        let mut connection = scripted_tree_connection(vec![
+            response_frame(
+                Command::Echo,
+                NtStatus::SUCCESS.to_u32(),
+                9,
+                66,
+                12,
+                EchoResponse.encode(),
+            ),
+            response_frame(
+                Command::Echo,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                66,
+                12,
+                EchoResponse.encode(),
+            ),
+            Vec::new(),
+            response_frame(
+                Command::Echo,
+                NtStatus::SUCCESS.to_u32(),
+                5,
+                66,
+                12,
+                EchoResponse.encode(),
+            ),
+            response_frame(
+                Command::TreeDisconnect,
+                NtStatus::SUCCESS.to_u32(),
+                6,
+                66,
+                12,
+                smolder_proto::smb::smb2::TreeDisconnectResponse.encode(),
+            ),
+        ])
+        .await;
+
+        let error = connection
+            .echo()
+            .await
+            .expect_err("a response for another message id should fail");
+        assert!(matches!(error, CoreError::InvalidResponse(_)));
+        assert_eq!(connection.outstanding_request(), None);
+        connection
+            .echo()
+            .await
+            .expect("the next request should not be refused");
+
+        let error = connection
+            .echo()
+            .await
+            .expect_err("the scripted read should time out");
+        assert!(
+            matches!(&error, CoreError::Io(error) if error.kind() == std::io::ErrorKind::TimedOut)
+        );
+        assert_eq!(connection.outstanding_request(), Some(MessageId(5)));
+
+        let connection = connection
+            .tree_disconnect()
+            .await
+            .expect("teardown should cancel the timed-out request first");
+        let transport = connection.into_transport();
+        let commands = transport.writes[3..]
+            .iter()
+            .map(|frame| outbound_header(frame).command)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            commands,
+            [
+                Command::Echo,
+                Command::Echo,
+                Command::Echo,
+                Command::Cancel,
+                Command::TreeDisconnect
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_signed_tree_connect_response() {
+        let negotiate_request = NegotiateRequest {
+            security_mode: SigningMode::ENABLED,
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            client_guid: *b"client-guid-0003",
+            dialects: vec![Dialect::Smb210, Dialect::Smb302, Dialect::Smb311],
+            negotiate_contexts: vec![preauth_context(b"client-salt-0003")],
+        };
+        let negotiate_response = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb311,
+            negotiate_contexts: vec![preauth_context(b"server-salt-0003")],
+            server_guid: *b"server-guid-0003",
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            max_transact_size: 65_536,
             max_read_size: 65_536,
             max_write_size: 65_536,
             system_time: 1,
@@ -4095,4 +5670,222 @@ mod tests {
         let transport = connection.into_transport();
         assert_eq!(transport.writes.len(), 4);
     }
+
+    #[tokio::test]
+    async fn negotiate_rejects_dialect_that_was_not_offered() {
+        let negotiate_response = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb311,
+            negotiate_contexts: vec![preauth_context(b"server-salt-0001")],
+            server_guid: *b"server-guid-0001",
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            max_transact_size: 65_536,
+            max_read_size: 65_536,
+            max_write_size: 65_536,
+            system_time: 1,
+            server_start_time: 1,
+            security_buffer: Vec::new(),
+        };
+        let transport = ScriptedTransport::new(vec![response_frame(
+            Command::Negotiate,
+            NtStatus::SUCCESS.to_u32(),
+            0,
+            0,
+            0,
+            negotiate_response.encode(),
+        )]);
+        let negotiate_request = NegotiateRequest {
+            security_mode: SigningMode::ENABLED,
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            client_guid: *b"client-guid-0001",
+            dialects: vec![Dialect::Smb210, Dialect::Smb302],
+            negotiate_contexts: Vec::new(),
+        };
+
+        let error = Connection::new(transport)
+            .negotiate(&negotiate_request)
+            .await
+            .expect_err("unoffered dialect should be rejected");
+        assert!(matches!(
+            error,
+            CoreError::InvalidResponse(
+                "negotiate response selected a dialect that was not offered"
+            )
+        ));
+    }
+
+    #[tokio::test]
+    async fn negotiate_reports_smb1_replies_as_unsupported() {
+        let mut packet = vec![0xff, b'S', b'M', b'B', 0x72];
+        packet.resize(39, 0);
+        let transport = ScriptedTransport::new(vec![SessionMessage::new(packet)
+            .encode()
+            .expect("response should frame")]);
+        let negotiate_request = NegotiateRequest {
+            security_mode: SigningMode::ENABLED,
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            client_guid: *b"client-guid-0001",
+            dialects: vec![Dialect::Smb210],
+            negotiate_contexts: Vec::new(),
+        };
+
+        let error = Connection::new(transport)
+            .negotiate(&negotiate_request)
+            .await
+            .expect_err("SMB1 replies should be rejected");
+        assert!(matches!(
+            error,
+            CoreError::Unsupported(
+                "server replied with SMB1; only SMB2 and later dialects are supported"
+            )
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_response_with_mismatched_message_id() {
+        let negotiate_response = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb210,
+            negotiate_contexts: Vec::new(),
+            server_guid: *b"server-guid-0001",
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            max_transact_size: 65_536,
+            max_read_size: 65_536,
+            max_write_size: 65_536,
+            system_time: 1,
+            server_start_time: 1,
+            security_buffer: Vec::new(),
+        };
+        let transport = ScriptedTransport::new(vec![response_frame(
+            Command::Negotiate,
+            NtStatus::SUCCESS.to_u32(),
+            7,
+            0,
+            0,
+            negotiate_response.encode(),
+        )]);
+        let negotiate_request = NegotiateRequest {
+            security_mode: SigningMode::ENABLED,
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            client_guid: *b"client-guid-0001",
+            dialects: vec![Dialect::Smb210],
+            negotiate_contexts: Vec::new(),
+        };
+
+        let error = Connection::new(transport)
+            .negotiate(&negotiate_request)
+            .await
+            .expect_err("stale message id should be rejected");
+        assert!(matches!(
+            error,
+            CoreError::InvalidResponse("response message id did not match the request")
+        ));
+    }
+
+    #[tokio::test]
+    async fn close_surfaces_invalid_handle_status() {
+        let negotiate_response = NegotiateResponse {
+            security_mode: SigningMode::ENABLED,
+            dialect_revision: Dialect::Smb210,
+            negotiate_contexts: Vec::new(),
+            server_guid: *b"server-guid-0001",
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            max_transact_size: 65_536,
+            max_read_size: 65_536,
+            max_write_size: 65_536,
+            system_time: 1,
+            server_start_time: 1,
+            security_buffer: vec![0x60, 0x03],
+        };
+        let session_response = SessionSetupResponse {
+            session_flags: SessionFlags::empty(),
+            security_buffer: vec![0xa1, 0x01],
+        };
+        let tree_response = TreeConnectResponse {
+            share_type: ShareType::Disk,
+            share_flags: ShareFlags::empty(),
+            capabilities: TreeCapabilities::empty(),
+            maximal_access: 0x0012_019f,
+        };
+
+        let transport = ScriptedTransport::new(vec![
+            response_frame(
+                Command::Negotiate,
+                NtStatus::SUCCESS.to_u32(),
+                0,
+                0,
+                0,
+                negotiate_response.encode(),
+            ),
+            response_frame(
+                Command::SessionSetup,
+                NtStatus::SUCCESS.to_u32(),
+                1,
+                55,
+                0,
+                session_response.encode(),
+            ),
+            response_frame(
+                Command::TreeConnect,
+                NtStatus::SUCCESS.to_u32(),
+                2,
+                55,
+                9,
+                tree_response.encode(),
+            ),
+            response_frame(
+                Command::Close,
+                NtStatus::INVALID_HANDLE.to_u32(),
+                3,
+                55,
+                9,
+                vec![0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            ),
+        ]);
+
+        let negotiate_request = NegotiateRequest {
+            security_mode: SigningMode::ENABLED,
+            capabilities: GlobalCapabilities::LARGE_MTU,
+            client_guid: *b"client-guid-0001",
+            dialects: vec![Dialect::Smb210],
+            negotiate_contexts: Vec::new(),
+        };
+        let session_request = SessionSetupRequest {
+            flags: 0,
+            security_mode: SessionSetupSecurityMode::SIGNING_ENABLED,
+            capabilities: 0,
+            channel: 0,
+            security_buffer: vec![0x60, 0x48],
+            previous_session_id: 0,
+        };
+
+        let mut connection = Connection::new(transport)
+            .negotiate(&negotiate_request)
+            .await
+            .expect("negotiate should succeed")
+            .session_setup(&session_request)
+            .await
+            .expect("session setup should succeed")
+            .tree_connect(&TreeConnectRequest::from_unc(r"\\server\share"))
+            .await
+            .expect("tree connect should succeed");
+
+        let error = connection
+            .close(&CloseRequest {
+                flags: 0,
+                file_id: FileId {
+                    persistent: 1,
+                    volatile: 2,
+                },
+            })
+            .await
+            .expect_err("invalid handle should not be treated as a successful close");
+        assert!(matches!(
+            error,
+            CoreError::UnexpectedStatus {
+                command: Command::Close,
+                status,
+            } if status == NtStatus::INVALID_HANDLE.to_u32()
+        ));
+    }
 }
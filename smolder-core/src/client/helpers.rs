@@ -9,14 +9,16 @@ use smolder_proto::smb::smb2::{
     AsyncId, CipherId, CompressionCapabilities, CreateContext, CreateRequest, CreateResponse,
     Dialect, DurableHandleFlags, DurableHandleReconnect, DurableHandleReconnectV2,
     DurableHandleRequest, DurableHandleRequestV2, GlobalCapabilities, Header, HeaderFlags,
-    NegotiateRequest, NegotiateResponse, PreauthIntegrityCapabilities, SessionFlags,
-    SessionSetupSecurityMode, ShareFlags, SigningMode, TransportCapabilityFlags,
+    IoctlRequest, IoctlResponse, MessageId, NegotiateRequest, NegotiateResponse,
+    PreauthIntegrityCapabilities, SessionFlags, SessionSetupSecurityMode, ShareFlags, SigningMode,
+    TransportCapabilityFlags, OPLOCK_BREAK_MESSAGE_ID, SMB1_PROTOCOL_ID,
 };
 use smolder_proto::smb::status::NtStatus;
 use smolder_proto::smb::transform::{TRANSFORM_PROTOCOL_ID, TransformHeader};
+use zeroize::Zeroizing;
 
 use crate::compression::CompressionState;
-use crate::crypto::{derive_encryption_keys, EncryptionState};
+use crate::crypto::{derive_application_key, derive_encryption_keys, EncryptionState};
 use crate::error::CoreError;
 
 use super::state::{
@@ -28,6 +30,38 @@ pub(super) fn align_to_8(len: usize) -> usize {
     (len + 7) & !7
 }
 
+/// Formats raw packet bytes as lowercase hex for `packet-trace` output.
+#[cfg(feature = "packet-trace")]
+pub(super) fn hex_dump(bytes: &[u8]) -> String {
+    use std::fmt::Write as _;
+
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut out, byte| {
+            let _ = write!(out, "{byte:02x}");
+            out
+        })
+}
+
+/// Number of payload bytes covered by one SMB2 credit.
+pub(crate) const CREDIT_PAYLOAD_SIZE: u32 = 64 * 1024;
+
+pub(super) fn multi_credit_supported(negotiated: &NegotiateResponse) -> bool {
+    negotiated.dialect_revision != Dialect::Smb202
+        && negotiated
+            .capabilities
+            .contains(GlobalCapabilities::LARGE_MTU)
+}
+
+/// Returns the `CreditCharge` for a request moving `payload_len` bytes.
+pub(super) fn credit_charge_for(negotiated: &NegotiateResponse, payload_len: u32) -> u16 {
+    if !multi_credit_supported(negotiated) {
+        return 1;
+    }
+    let charge = payload_len.saturating_sub(1) / CREDIT_PAYLOAD_SIZE + 1;
+    u16::try_from(charge).unwrap_or(u16::MAX)
+}
+
 pub(super) fn split_compound_packets(payload: &[u8]) -> Result<Vec<&[u8]>, CoreError> {
     if payload.len() < Header::LEN {
         return Err(CoreError::InvalidResponse(
@@ -112,6 +146,11 @@ pub(super) fn decode_transport_payload(
     payload: &[u8],
     context: &RequestContext,
 ) -> Result<(Vec<u8>, bool), CoreError> {
+    if payload.starts_with(&SMB1_PROTOCOL_ID) {
+        return Err(CoreError::Unsupported(
+            "server replied with SMB1; only SMB2 and later dialects are supported",
+        ));
+    }
     if payload.starts_with(&TRANSFORM_PROTOCOL_ID) {
         let encryption = context
             .encryption
@@ -274,6 +313,29 @@ pub(super) fn strip_durable_create_contexts(
     Ok(filtered)
 }
 
+/// Returns true for an `OPLOCK_BREAK` notification the server pushed unsolicited.
+pub(super) fn is_oplock_break_notification(header: &Header) -> bool {
+    header.command == smolder_proto::smb::smb2::Command::OplockBreak
+        && header.message_id == MessageId(OPLOCK_BREAK_MESSAGE_ID)
+}
+
+pub(super) fn validate_ioctl_response(
+    request: &IoctlRequest,
+    response: &IoctlResponse,
+) -> Result<(), CoreError> {
+    if response.ctl_code != request.ctl_code {
+        return Err(CoreError::InvalidResponse(
+            "ioctl response control code did not match the request",
+        ));
+    }
+    if response.output.len() > request.max_output_response as usize {
+        return Err(CoreError::InvalidResponse(
+            "ioctl response output exceeded max_output_response",
+        ));
+    }
+    Ok(())
+}
+
 pub(super) fn validate_pending_response(header: &Header) -> Result<AsyncId, CoreError> {
     if !header.flags.contains(HeaderFlags::ASYNC_COMMAND) {
         return Err(CoreError::InvalidResponse(
@@ -549,15 +611,38 @@ pub(super) fn derive_signing_state(
     Ok(Some(Arc::new(signing)))
 }
 
-pub(super) fn derive_smb_session_key(session_key: Option<&[u8]>) -> Option<Vec<u8>> {
+pub(super) fn derive_smb_session_key(session_key: Option<&[u8]>) -> Option<Zeroizing<Vec<u8>>> {
     let session_key = session_key?;
-    let mut smb_session_key = session_key[..session_key.len().min(16)].to_vec();
-    if smb_session_key.len() < 16 {
-        smb_session_key.resize(16, 0);
-    }
+    // Allocate the final length up front so a short key is never copied
+    // into a buffer that a resize would reallocate without zeroing.
+    let len = session_key.len().min(16);
+    let mut smb_session_key = Zeroizing::new(vec![0; 16]);
+    smb_session_key[..len].copy_from_slice(&session_key[..len]);
     Some(smb_session_key)
 }
 
+pub(super) fn session_application_key(
+    negotiated: &NegotiateResponse,
+    session_key: Option<&[u8]>,
+    preauth_integrity: Option<&PreauthIntegrityState>,
+) -> Result<Option<Zeroizing<Vec<u8>>>, CoreError> {
+    let Some(session_key) = session_key else {
+        return Ok(None);
+    };
+    if matches!(
+        negotiated.dialect_revision,
+        Dialect::Smb202 | Dialect::Smb210
+    ) {
+        return Ok(None);
+    }
+    derive_application_key(
+        negotiated.dialect_revision,
+        session_key,
+        preauth_integrity.map(|state| state.hash_value.as_slice()),
+    )
+    .map(Some)
+}
+
 pub(super) fn derive_encryption_state(
     negotiated: &NegotiateResponse,
     session_key: Option<&[u8]>,
@@ -573,7 +658,7 @@ pub(super) fn derive_encryption_state(
         return Ok(None);
     };
     let key_material = match cipher {
-        CipherId::Aes256Ccm | CipherId::Aes256Gcm => session_key.to_vec(),
+        CipherId::Aes256Ccm | CipherId::Aes256Gcm => Zeroizing::new(session_key.to_vec()),
         _ => derive_smb_session_key(Some(session_key))
             .ok_or(CoreError::InvalidInput("missing SMB session key"))?,
     };
@@ -10,7 +10,9 @@ use smolder_proto::smb::smb2::{
     TreeId,
 };
 use smolder_proto::smb::status::NtStatus;
+use zeroize::{Zeroize, Zeroizing};
 
+use crate::auth::NtlmServerInfo;
 use crate::compression::CompressionState;
 use crate::crypto::EncryptionState;
 use crate::error::CoreError;
@@ -46,7 +48,9 @@ pub struct Authenticated {
     /// Preauthentication integrity state for SMB 3.1.1, if negotiated.
     pub preauth_integrity: Option<PreauthIntegrityState>,
     /// Exported session key from the authentication mechanism.
-    pub session_key: Option<Vec<u8>>,
+    ///
+    /// The key bytes are overwritten with zeros when the state is dropped.
+    pub session_key: Option<Zeroizing<Vec<u8>>>,
     /// Whether the session requires signed responses and requests.
     pub signing_required: bool,
     /// Derived request-signing state for the session, if available.
@@ -57,6 +61,8 @@ pub struct Authenticated {
     pub encryption: Option<Arc<EncryptionState>>,
     /// Receive-side compression state, if negotiated.
     pub compression: Option<Arc<CompressionState>>,
+    /// Server identity from the NTLM challenge, when the session used NTLM.
+    pub ntlm_server_info: Option<NtlmServerInfo>,
 }
 
 /// The transport is connected to a tree and can issue file operations.
@@ -77,7 +83,9 @@ pub struct TreeConnected {
     /// Preauthentication integrity state for SMB 3.1.1, if negotiated.
     pub preauth_integrity: Option<PreauthIntegrityState>,
     /// Exported session key from the authentication mechanism.
-    pub session_key: Option<Vec<u8>>,
+    ///
+    /// The key bytes are overwritten with zeros when the state is dropped.
+    pub session_key: Option<Zeroizing<Vec<u8>>>,
     /// Whether the session requires signed responses and requests.
     pub signing_required: bool,
     /// Derived request-signing state for the session, if available.
@@ -88,6 +96,8 @@ pub struct TreeConnected {
     pub encryption: Option<Arc<EncryptionState>>,
     /// Receive-side compression state, if negotiated.
     pub compression: Option<Arc<CompressionState>>,
+    /// Server identity from the NTLM challenge, when the session used NTLM.
+    pub ntlm_server_info: Option<NtlmServerInfo>,
 }
 
 /// SMB 3.1.1 preauthentication transcript state.
@@ -135,6 +145,12 @@ pub struct SigningState {
     pub(super) key: Vec<u8>,
 }
 
+impl Drop for SigningState {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
 impl SigningState {
     pub(super) fn sign_packet(&self, packet: &mut [u8]) -> Result<(), CoreError> {
         if packet.len() < Header::LEN {
@@ -428,6 +444,7 @@ pub(super) struct RequestContext {
     pub(super) encryption: Option<Arc<EncryptionState>>,
     pub(super) compression: Option<Arc<CompressionState>>,
     pub(super) compress_outbound: bool,
+    pub(super) credit_charge: u16,
 }
 
 impl RequestContext {
@@ -446,6 +463,7 @@ impl RequestContext {
             encryption: None,
             compression: None,
             compress_outbound: false,
+            credit_charge: 1,
         }
     }
 
@@ -473,6 +491,11 @@ impl RequestContext {
         self
     }
 
+    pub(super) fn with_credit_charge(mut self, credit_charge: u16) -> Self {
+        self.credit_charge = credit_charge.max(1);
+        self
+    }
+
     pub(super) fn should_encrypt(&self) -> bool {
         self.encryption_required
     }
@@ -11,6 +11,7 @@ use sha2::Sha256;
 use smolder_proto::smb::transform::{TransformHeader, TransformValue};
 use smolder_proto::smb::smb2::{CipherId, Dialect};
 use rand::random;
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::error::CoreError;
 
@@ -28,6 +29,13 @@ pub struct EncryptionKeys {
     pub decrypting_key: Vec<u8>,
 }
 
+impl Drop for EncryptionKeys {
+    fn drop(&mut self) {
+        self.encrypting_key.zeroize();
+        self.decrypting_key.zeroize();
+    }
+}
+
 /// Runtime SMB 3.x sealing state for one authenticated session.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EncryptionState {
@@ -41,15 +49,22 @@ pub struct EncryptionState {
     pub decrypting_key: Vec<u8>,
 }
 
+impl Drop for EncryptionState {
+    fn drop(&mut self) {
+        self.encrypting_key.zeroize();
+        self.decrypting_key.zeroize();
+    }
+}
+
 impl EncryptionState {
     /// Creates a runtime encryption state from derived session keys.
     #[must_use]
-    pub fn new(dialect: Dialect, keys: EncryptionKeys) -> Self {
+    pub fn new(dialect: Dialect, mut keys: EncryptionKeys) -> Self {
         Self {
             dialect,
             cipher: keys.cipher,
-            encrypting_key: keys.encrypting_key,
-            decrypting_key: keys.decrypting_key,
+            encrypting_key: std::mem::take(&mut keys.encrypting_key),
+            decrypting_key: std::mem::take(&mut keys.decrypting_key),
         }
     }
 
@@ -260,7 +275,7 @@ fn encrypt_aes128_ccm(
     key: &[u8],
     nonce: &[u8],
     aad: &[u8],
-    payload: &mut Vec<u8>,
+    payload: &mut [u8],
 ) -> Result<[u8; 16], CoreError> {
     let cipher = Aes128Ccm::new_from_slice(key)
         .map_err(|_| CoreError::InvalidInput("invalid SMB CCM encryption key"))?;
@@ -276,7 +291,7 @@ fn encrypt_aes256_ccm(
     key: &[u8],
     nonce: &[u8],
     aad: &[u8],
-    payload: &mut Vec<u8>,
+    payload: &mut [u8],
 ) -> Result<[u8; 16], CoreError> {
     let cipher = Aes256Ccm::new_from_slice(key)
         .map_err(|_| CoreError::InvalidInput("invalid SMB CCM encryption key"))?;
@@ -292,7 +307,7 @@ fn decrypt_aes128_ccm(
     key: &[u8],
     nonce: &[u8],
     aad: &[u8],
-    payload: &mut Vec<u8>,
+    payload: &mut [u8],
     signature: &[u8; 16],
 ) -> Result<(), CoreError> {
     let cipher = Aes128Ccm::new_from_slice(key)
@@ -311,7 +326,7 @@ fn decrypt_aes256_ccm(
     key: &[u8],
     nonce: &[u8],
     aad: &[u8],
-    payload: &mut Vec<u8>,
+    payload: &mut [u8],
     signature: &[u8; 16],
 ) -> Result<(), CoreError> {
     let cipher = Aes256Ccm::new_from_slice(key)
@@ -330,7 +345,7 @@ fn encrypt_aes128_gcm(
     key: &[u8],
     nonce: &[u8],
     aad: &[u8],
-    payload: &mut Vec<u8>,
+    payload: &mut [u8],
 ) -> Result<[u8; 16], CoreError> {
     encrypt_gcm::<Aes128Gcm>(key, nonce, aad, payload)
 }
@@ -339,7 +354,7 @@ fn encrypt_aes256_gcm(
     key: &[u8],
     nonce: &[u8],
     aad: &[u8],
-    payload: &mut Vec<u8>,
+    payload: &mut [u8],
 ) -> Result<[u8; 16], CoreError> {
     encrypt_gcm::<Aes256Gcm>(key, nonce, aad, payload)
 }
@@ -348,7 +363,7 @@ fn decrypt_aes128_gcm(
     key: &[u8],
     nonce: &[u8],
     aad: &[u8],
-    payload: &mut Vec<u8>,
+    payload: &mut [u8],
     signature: &[u8; 16],
 ) -> Result<(), CoreError> {
     decrypt_gcm::<Aes128Gcm>(key, nonce, aad, payload, signature)
@@ -358,7 +373,7 @@ fn decrypt_aes256_gcm(
     key: &[u8],
     nonce: &[u8],
     aad: &[u8],
-    payload: &mut Vec<u8>,
+    payload: &mut [u8],
     signature: &[u8; 16],
 ) -> Result<(), CoreError> {
     decrypt_gcm::<Aes256Gcm>(key, nonce, aad, payload, signature)
@@ -368,7 +383,7 @@ fn encrypt_gcm<Cipher>(
     key: &[u8],
     nonce: &[u8],
     aad: &[u8],
-    payload: &mut Vec<u8>,
+    payload: &mut [u8],
 ) -> Result<[u8; 16], CoreError>
 where
     Cipher: AeadInPlace + KeyInit,
@@ -387,7 +402,7 @@ fn decrypt_gcm<Cipher>(
     key: &[u8],
     nonce: &[u8],
     aad: &[u8],
-    payload: &mut Vec<u8>,
+    payload: &mut [u8],
     signature: &[u8; 16],
 ) -> Result<(), CoreError>
 where
@@ -405,6 +420,33 @@ where
         .map_err(|_| CoreError::InvalidResponse("SMB GCM signature verification failed"))
 }
 
+/// Derives the SMB 3.x application key from the SMB session key.
+///
+/// Protocols layered over the session, such as DCE/RPC over named pipes, use
+/// this key in place of the session key on SMB 3.x dialects. The key bytes are
+/// overwritten with zeros when dropped.
+pub fn derive_application_key(
+    dialect: Dialect,
+    session_key: &[u8],
+    preauth_hash: Option<&[u8]>,
+) -> Result<Zeroizing<Vec<u8>>, CoreError> {
+    let (label, context) = match dialect {
+        Dialect::Smb202 | Dialect::Smb210 => {
+            return Err(CoreError::Unsupported(
+                "SMB application keys are only available for SMB 3.x dialects",
+            ));
+        }
+        Dialect::Smb300 | Dialect::Smb302 => (b"SMB2APP\0".as_slice(), b"SmbRpc\0".as_slice()),
+        Dialect::Smb311 => (
+            b"SMBAppKey\0".as_slice(),
+            preauth_hash.ok_or(CoreError::InvalidResponse(
+                "SMB 3.1.1 application key requires preauth integrity state",
+            ))?,
+        ),
+    };
+    derive_key(session_key, label, context, 16).map(Zeroizing::new)
+}
+
 fn derive_key(
     key: &[u8],
     label: &[u8],
@@ -445,7 +487,7 @@ mod tests {
     use smolder_proto::smb::smb2::{CipherId, Dialect};
     use smolder_proto::smb::transform::TransformValue;
 
-    use super::{derive_encryption_keys, EncryptionState};
+    use super::{derive_application_key, derive_encryption_keys, EncryptionState};
 
     #[test]
     fn derives_smb300_aes128_ccm_keys() {
@@ -470,6 +512,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn derives_published_smb300_example_keys() {
+        // Session key and expected keys from Microsoft's published SMB 3.0
+        // encryption example, which runs the MS-SMB2 3.1.4.2 KDF.
+        let session_key = unhex("b4546771b515f766a86735532dd6c4f0");
+
+        let keys = derive_encryption_keys(
+            Dialect::Smb300,
+            CipherId::Aes128Ccm,
+            &session_key,
+            None,
+            None,
+        )
+        .expect("SMB 3.0 encryption keys should derive");
+        assert_eq!(
+            hex(&keys.encrypting_key),
+            "261b72350558f2e9dcf613070383edbf"
+        );
+        assert_eq!(
+            hex(&keys.decrypting_key),
+            "8fe2b57ec34d2db5b1a9727f526bbdb5"
+        );
+    }
+
+    #[test]
+    fn derives_application_keys_per_dialect() {
+        // The SMB 3.0.x application key uses the same KDF as the published
+        // encryption example above, over the same session key, with the
+        // `SMB2APP`/`SmbRpc` label and context from MS-SMB2 3.2.5.3.1.
+        let session_key = unhex("b4546771b515f766a86735532dd6c4f0");
+        let preauth_hash = (0u8..64).collect::<Vec<_>>();
+
+        let smb302 = derive_application_key(Dialect::Smb302, &session_key, None)
+            .expect("SMB 3.0.2 application key should derive");
+        assert_eq!(hex(&smb302), "77432f808ce99156b5bc6a3676d730d1");
+
+        let smb311 = derive_application_key(Dialect::Smb311, &session_key, Some(&preauth_hash))
+            .expect("SMB 3.1.1 application key should derive");
+        assert_eq!(hex(&smb311), "067de2b0740d797dac482ead14a71a79");
+
+        assert!(derive_application_key(Dialect::Smb311, &session_key, None).is_err());
+        assert!(derive_application_key(Dialect::Smb210, &session_key, None).is_err());
+    }
+
     #[test]
     fn derives_smb311_aes128_gcm_keys() {
         let session_key = (0u8..16).collect::<Vec<_>>();
@@ -637,4 +723,11 @@ mod tests {
     fn hex(bytes: &[u8]) -> String {
         bytes.iter().map(|byte| format!("{byte:02x}")).collect()
     }
+
+    fn unhex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&value[index..index + 2], 16).expect("hex digit"))
+            .collect()
+    }
 }
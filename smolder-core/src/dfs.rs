@@ -13,9 +13,13 @@ pub struct UncPath {
 
 impl UncPath {
     /// Parses and normalizes a UNC path.
+    ///
+    /// Both `\\server\share\path` and `//server/share/path` forms are
+    /// accepted, and separators may be mixed.
     pub fn parse(value: &str) -> Result<Self, CoreError> {
         let trimmed = value
             .strip_prefix(r"\\")
+            .or_else(|| value.strip_prefix("//"))
             .ok_or(CoreError::PathInvalid("UNC path must start with \\\\"))?;
         let mut parts = trimmed
             .split(['\\', '/'])
@@ -62,6 +66,13 @@ impl UncPath {
         &self.path
     }
 
+    /// Returns the path after the share joined with `\`, or an empty string
+    /// for a share root.
+    #[must_use]
+    pub fn relative_path(&self) -> String {
+        self.path.join(r"\")
+    }
+
     /// Returns the `\\server\share` prefix for this UNC path.
     #[must_use]
     pub fn share_unc(&self) -> String {
@@ -207,6 +218,19 @@ mod tests {
         assert_eq!(path.path(), ["docs", "report.txt"]);
         assert_eq!(path.share_unc(), r"\\server\share");
         assert_eq!(path.as_unc(), r"\\server\share\docs\report.txt");
+        assert_eq!(path.relative_path(), r"docs\report.txt");
+    }
+
+    #[test]
+    fn parses_forward_slash_unc_paths() {
+        let path = UncPath::parse("//server/share/docs/report.txt").expect("UNC path should parse");
+
+        assert_eq!(path.share_unc(), r"\\server\share");
+        assert_eq!(path.relative_path(), r"docs\report.txt");
+        let root = UncPath::parse("//server/share").expect("share root should parse");
+        assert!(root.path().is_empty());
+        assert_eq!(root.relative_path(), "");
+        assert!(UncPath::parse("server/share").is_err());
     }
 
     #[test]
@@ -2,6 +2,7 @@
 
 use crate::auth::AuthError;
 use smolder_proto::smb::smb2::Command;
+use smolder_proto::smb::status::NtStatus;
 use smolder_proto::smb::ProtocolError;
 use thiserror::Error;
 
@@ -13,7 +14,7 @@ pub enum CoreError {
     Io(#[from] std::io::Error),
     /// The local filesystem or local stream returned an I/O failure.
     #[error("local I/O error")]
-    LocalIo(std::io::Error),
+    LocalIo(#[source] std::io::Error),
     /// Packet encoding or decoding failed.
     #[error("protocol error")]
     Protocol(#[from] ProtocolError),
@@ -29,13 +30,27 @@ pub enum CoreError {
         actual: Command,
     },
     /// The server returned a status code that the caller did not allow.
-    #[error("unexpected status code 0x{status:08x} for {command:?}")]
+    #[error("unexpected status {} for {command:?}", NtStatus(*status))]
     UnexpectedStatus {
         /// The command being processed.
         command: Command,
         /// The returned NTSTATUS value.
         status: u32,
     },
+    /// The requested path does not exist on the share.
+    #[error("not found: {0}")]
+    NotFound(String),
+    /// A directory could not be removed because it still has entries.
+    #[error("directory not empty: {0}")]
+    DirectoryNotEmpty(String),
+    /// A byte-range lock conflicts with a lock held through another handle.
+    #[error("lock not granted for {length} bytes at offset {offset}")]
+    LockNotGranted {
+        /// Starting offset of the requested range.
+        offset: u64,
+        /// Length of the requested range.
+        length: u64,
+    },
     /// The response was structurally valid but semantically unusable.
     #[error("invalid response: {0}")]
     InvalidResponse(&'static str),
@@ -60,3 +75,21 @@ pub enum CoreError {
     #[error("operation timed out: {0}")]
     Timeout(&'static str),
 }
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::CoreError;
+
+    #[test]
+    fn io_variants_expose_the_wrapped_error_as_source() {
+        for error in [
+            CoreError::Io(std::io::Error::other("socket reset")),
+            CoreError::LocalIo(std::io::Error::other("disk full")),
+        ] {
+            let source = error.source().expect("io errors should carry a source");
+            assert!(source.downcast_ref::<std::io::Error>().is_some());
+        }
+    }
+}
@@ -5,28 +5,46 @@
 //! without dropping directly into raw typestate orchestration.
 
 use rand::random;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::collections::HashMap;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use smolder_proto::rpc::SyntaxId;
 use smolder_proto::smb::compression::{CompressionAlgorithm, CompressionCapabilityFlags};
+use smolder_proto::smb::filetime::{filetime_to_system_time, system_time_to_filetime};
+use smolder_proto::smb::security::{SecurityDescriptor, SecurityInformation};
 use smolder_proto::smb::smb2::{
-    CloseRequest, CompressionCapabilities, CreateDisposition, CreateOptions, CreateRequest,
-    Dialect, DirectoryInformationEntry, DispositionInformation, EchoResponse, FileAttributes,
-    FileBasicInformation, FileId, FileInfoClass, FileStandardInformation, FlushRequest,
-    GlobalCapabilities, QueryDirectoryFlags, QueryDirectoryRequest, QueryInfoRequest, ReadRequest,
-    RenameInformation, SessionId, SetInfoRequest, ShareAccess, SigningMode, TreeConnectRequest,
-    TreeId, WriteRequest,
+    AllocationInformation, ChangeNotifyFlags, ChangeNotifyRequest, CloseRequest, CompletionFilter,
+    CompressionCapabilities, CreateDisposition, CreateOptions, CreateRequest, DfsReferralRequest,
+    Dialect, DirectoryInformationEntry, DispositionInformation, EchoResponse, EndOfFileInformation,
+    FileAttributes, FileBasicInformation, FileFsAttributeInformation, FileFsFullSizeInformation,
+    FileFsVolumeInformation, FileId, FileInfoClass, FileNotifyInformation, FileStandardInformation,
+    FileSystemAttributes, FileSystemInfoClass, FlushRequest, GlobalCapabilities, IoctlRequest,
+    LockElement, LockFlags, LockRequest, OplockLevel, QueryDirectoryFlags, QueryDirectoryRequest,
+    QueryInfoRequest, ReadRequest, RenameInformation, RequestedOplockLevel, SessionId,
+    SetInfoRequest, ShareAccess, SigningMode, TreeConnectRequest, TreeId, WriteRequest,
 };
+use smolder_proto::smb::status::NtStatus;
+use tokio::time::Instant;
+use zeroize::Zeroizing;
 
-use crate::auth::NtlmCredentials;
 #[cfg(feature = "kerberos-api")]
 use crate::auth::{KerberosCredentials, KerberosTarget};
+use crate::auth::{NtlmCredentials, NtlmServerInfo};
 use crate::client::{
     Authenticated, Connection, DurableHandle, DurableOpenOptions, ResilientHandle, TreeConnected,
+    CREDIT_PAYLOAD_SIZE,
 };
+use crate::dfs::{referrals_from_response, resolve_unc_path, UncPath};
 use crate::error::CoreError;
 use crate::lsarpc::{LsarpcClient, DEFAULT_POLICY_ACCESS};
-use crate::pipe::{connect_session, NamedPipe, PipeAccess, SmbSessionConfig};
+use crate::pipe::{
+    connect_session, connect_session_with_transport, NamedPipe, PipeAccess, SmbSessionConfig,
+};
 #[cfg(feature = "quic")]
 use crate::pipe::{connect_session_quic, connect_tree_quic};
 use crate::rpc::PipeRpcClient;
@@ -34,8 +52,11 @@ use crate::samr::SamrClient;
 use crate::srvsvc::SrvsvcClient;
 #[cfg(feature = "quic")]
 use crate::transport::QuicTransport;
-use crate::transport::{SmbTransport, TokioTcpTransport, TransportProtocol, TransportTarget};
+use crate::transport::{
+    SmbTransport, TokioTcpTransport, TransportProtocol, TransportTarget, TransportTimeouts,
+};
 const MAX_IO_CHUNK_SIZE: usize = u16::MAX as usize;
+const MAX_MULTI_CREDIT_CHUNK_SIZE: u32 = 8 * 1024 * 1024;
 const FILE_READ_DATA: u32 = 0x0000_0001;
 const FILE_WRITE_DATA: u32 = 0x0000_0002;
 const FILE_APPEND_DATA: u32 = 0x0000_0004;
@@ -47,9 +68,10 @@ const FILE_LIST_DIRECTORY: u32 = 0x0000_0001;
 const DELETE: u32 = 0x0001_0000;
 const READ_CONTROL: u32 = 0x0002_0000;
 const SYNCHRONIZE: u32 = 0x0010_0000;
-const WINDOWS_TICK: u64 = 10_000_000;
-const SEC_TO_UNIX_EPOCH: u64 = 11_644_473_600;
 const DIRECTORY_QUERY_BUFFER_SIZE: u32 = 64 * 1024;
+const CHANGE_NOTIFY_BUFFER_SIZE: u32 = 64 * 1024;
+const SECURITY_QUERY_BUFFER_SIZE: u32 = 64 * 1024;
+const DFS_REFERRAL_BUFFER_SIZE: u32 = 64 * 1024;
 
 #[derive(Debug, Clone)]
 enum BuilderAuth {
@@ -71,6 +93,10 @@ pub struct ClientBuilder {
     dialects: Vec<Dialect>,
     client_guid: [u8; 16],
     compression: Option<CompressionCapabilities>,
+    timeouts: TransportTimeouts,
+    retry_policy: RetryPolicy,
+    require_encryption: bool,
+    guest_fallback: bool,
 }
 
 impl ClientBuilder {
@@ -87,6 +113,10 @@ impl ClientBuilder {
             dialects: vec![Dialect::Smb210, Dialect::Smb302, Dialect::Smb311],
             client_guid: random(),
             compression: None,
+            timeouts: TransportTimeouts::default(),
+            retry_policy: RetryPolicy::default(),
+            require_encryption: false,
+            guest_fallback: false,
         }
     }
 
@@ -166,6 +196,51 @@ impl ClientBuilder {
         self
     }
 
+    /// Bounds how long connecting to the server may take. Unbounded by default.
+    #[must_use]
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts = self.timeouts.with_connect_timeout(timeout);
+        self
+    }
+
+    /// Bounds how long receiving one SMB response frame may take. Unbounded by default.
+    #[must_use]
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts = self.timeouts.with_read_timeout(timeout);
+        self
+    }
+
+    /// Bounds how long sending one SMB request frame may take. Unbounded by default.
+    #[must_use]
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts = self.timeouts.with_write_timeout(timeout);
+        self
+    }
+
+    /// Refuses to send plaintext requests once the session is established.
+    ///
+    /// Connecting fails when the server negotiates no SMB 3.x cipher.
+    #[must_use]
+    pub fn with_require_encryption(mut self, require_encryption: bool) -> Self {
+        self.require_encryption = require_encryption;
+        self
+    }
+
+    /// Retries as the `Guest` account when the credentials are rejected with
+    /// `STATUS_LOGON_FAILURE`. Check [`Session::is_guest`] afterwards.
+    #[must_use]
+    pub fn with_guest_fallback(mut self, guest_fallback: bool) -> Self {
+        self.guest_fallback = guest_fallback;
+        self
+    }
+
+    /// Configures how [`ReconnectingShare`] retries operations after transport failures.
+    #[must_use]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
     /// Configures NTLM credentials for the client.
     #[must_use]
     pub fn with_ntlm_credentials(mut self, credentials: NtlmCredentials) -> Self {
@@ -212,21 +287,67 @@ impl ClientBuilder {
         .with_signing_mode(self.signing_mode)
         .with_capabilities(self.capabilities)
         .with_dialects(self.dialects)
-        .with_client_guid(self.client_guid);
+        .with_client_guid(self.client_guid)
+        .with_timeouts(self.timeouts)
+        .with_require_encryption(self.require_encryption)
+        .with_guest_fallback(self.guest_fallback);
         let config = if let Some(compression) = self.compression {
             config.with_compression_capabilities(compression)
         } else {
             config
         };
 
-        Ok(Client::from_session_config(config))
+        Ok(Client::from_session_config(config).with_retry_policy(self.retry_policy))
+    }
+}
+
+/// Retry settings for operations run through [`ReconnectingShare`].
+///
+/// The default policy never retries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that retries a failed operation up to `max_retries` times.
+    #[must_use]
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            backoff: Duration::ZERO,
+        }
+    }
+
+    /// Waits `backoff` multiplied by the attempt number before each retry.
+    #[must_use]
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Returns the maximum number of retries after the first attempt.
+    #[must_use]
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Returns the base delay between retries.
+    #[must_use]
+    pub fn backoff(&self) -> Duration {
+        self.backoff
     }
 }
 
+static NEXT_CLIENT_IDENTITY: AtomicU64 = AtomicU64::new(1);
+
 /// High-level embedded SMB client facade.
 #[derive(Debug, Clone)]
 pub struct Client {
     config: SmbSessionConfig,
+    retry_policy: RetryPolicy,
+    identity: u64,
 }
 
 impl Client {
@@ -239,7 +360,24 @@ impl Client {
     /// Wraps an existing session configuration as a high-level client.
     #[must_use]
     pub fn from_session_config(config: SmbSessionConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            retry_policy: RetryPolicy::default(),
+            identity: NEXT_CLIENT_IDENTITY.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Replaces the retry policy used by [`Client::reconnecting_share`].
+    #[must_use]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Returns the retry policy used by [`Client::reconnecting_share`].
+    #[must_use]
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
     }
 
     /// Returns the underlying session configuration.
@@ -299,6 +437,22 @@ impl Client {
         })
     }
 
+    /// Negotiates and authenticates an SMB session over a caller-supplied transport.
+    ///
+    /// This skips the configured transport target entirely, so embedders can
+    /// run the session over a proxied, TLS-wrapped, or in-memory stream, for
+    /// example one wrapped in [`crate::transport::StreamTransport`].
+    pub async fn connect_with_transport<T>(&self, transport: T) -> Result<Session<T>, CoreError>
+    where
+        T: SmbTransport + Send,
+    {
+        let connection = connect_session_with_transport(transport, &self.config).await?;
+        Ok(Session {
+            server: self.config.server().to_owned(),
+            connection,
+        })
+    }
+
     /// Connects and authenticates an SMB session over QUIC.
     #[cfg(feature = "quic")]
     #[cfg_attr(docsrs, doc(cfg(feature = "quic")))]
@@ -316,10 +470,60 @@ impl Client {
     }
 
     /// Connects, authenticates, and tree-connects to the requested share.
+    ///
+    /// `share` may be a bare share name or a `\\server\share` UNC root naming
+    /// this client's server.
     pub async fn connect_share(&self, share: &str) -> Result<Share, CoreError> {
         self.connect().await?.connect_share(share).await
     }
 
+    /// Connects to the share named by a full UNC path and returns it together
+    /// with the path below the share.
+    ///
+    /// Both `\\server\share\dir\file` and `//server/share/dir/file` forms are
+    /// accepted. The UNC server must match this client's server.
+    pub async fn connect_unc(&self, path: &str) -> Result<(Share, String), CoreError> {
+        let unc = UncPath::parse(path)?;
+        ensure_unc_server(self.server(), &unc)?;
+        let share = self.connect_share(unc.share()).await?;
+        Ok((share, unc.relative_path()))
+    }
+
+    /// Reads the full contents of the file at a UNC path over a fresh session.
+    pub async fn read_unc(&self, path: &str) -> Result<Vec<u8>, CoreError> {
+        let (mut share, path) = self.connect_unc(path).await?;
+        let read_result = share.read(&path).await;
+        let logoff_result = share.logoff().await;
+        match (read_result, logoff_result) {
+            (Ok(data), Ok(())) => Ok(data),
+            (Err(error), _) => Err(error),
+            (Ok(_), Err(error)) => Err(error),
+        }
+    }
+
+    /// Writes the full contents of the file at a UNC path over a fresh session.
+    pub async fn write_unc(&self, path: &str, data: &[u8]) -> Result<(), CoreError> {
+        let (mut share, path) = self.connect_unc(path).await?;
+        let write_result = share.write(&path, data).await;
+        let logoff_result = share.logoff().await;
+        match (write_result, logoff_result) {
+            (Ok(()), Ok(())) => Ok(()),
+            (Err(error), _) => Err(error),
+            (Ok(()), Err(error)) => Err(error),
+        }
+    }
+
+    /// Returns a share handle that reconnects lazily and retries operations
+    /// according to the client's [`RetryPolicy`].
+    #[must_use]
+    pub fn reconnecting_share(&self, share: impl Into<String>) -> ReconnectingShare {
+        ReconnectingShare {
+            client: self.clone(),
+            share_name: share.into(),
+            share: None,
+        }
+    }
+
     /// Connects, authenticates, and tree-connects to the requested share over QUIC.
     #[cfg(feature = "quic")]
     #[cfg_attr(docsrs, doc(cfg(feature = "quic")))]
@@ -329,10 +533,11 @@ impl Client {
                 "client is not configured for an SMB over QUIC transport target",
             ));
         }
-        let connection = connect_tree_quic(&self.config, share).await?;
+        let name = resolve_share_name(self.config.server(), share)?;
+        let connection = connect_tree_quic(&self.config, &name).await?;
         Ok(Share {
             server: self.config.server().to_owned(),
-            name: normalize_share_name(share)?,
+            name,
             connection,
         })
     }
@@ -399,6 +604,375 @@ impl Client {
     }
 }
 
+/// Boxed future returned by operations passed to [`ReconnectingShare::run`].
+pub type ShareFuture<'a, R> = Pin<Box<dyn Future<Output = Result<R, CoreError>> + Send + 'a>>;
+
+/// A share connection that is re-established after transport failures.
+///
+/// The first [`ReconnectingShare::run`] call connects, authenticates, and
+/// tree-connects. When an operation fails with a transport error or timeout,
+/// the connection is dropped, rebuilt from the cached client configuration,
+/// and the operation is replayed until the [`RetryPolicy`] is exhausted.
+/// Operations should therefore be safe to repeat.
+#[derive(Debug)]
+pub struct ReconnectingShare {
+    client: Client,
+    share_name: String,
+    share: Option<Share>,
+}
+
+impl ReconnectingShare {
+    /// Returns the share name this handle connects to.
+    #[must_use]
+    pub fn share_name(&self) -> &str {
+        &self.share_name
+    }
+
+    /// Returns whether a tree connection is currently held.
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        self.share.is_some()
+    }
+
+    /// Runs an operation against the share, reconnecting and retrying on transport failures.
+    pub async fn run<R, F>(&mut self, mut operation: F) -> Result<R, CoreError>
+    where
+        F: for<'a> FnMut(&'a mut Share) -> ShareFuture<'a, R>,
+    {
+        let policy = self.client.retry_policy();
+        let mut attempt = 0;
+        loop {
+            let result = match self.share.as_mut() {
+                Some(share) => operation(share).await,
+                None => match self.client.connect_share(&self.share_name).await {
+                    Ok(share) => operation(self.share.insert(share)).await,
+                    Err(error) => Err(error),
+                },
+            };
+            match result {
+                Err(error) if is_transient_error(&error) => {
+                    self.share = None;
+                    if attempt >= policy.max_retries() {
+                        return Err(error);
+                    }
+                    attempt += 1;
+                    tokio::time::sleep(policy.backoff().saturating_mul(attempt)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Logs off the held connection, if any.
+    pub async fn logoff(self) -> Result<(), CoreError> {
+        match self.share {
+            Some(share) => share.logoff().await,
+            None => Ok(()),
+        }
+    }
+}
+
+fn is_transient_error(error: &CoreError) -> bool {
+    matches!(error, CoreError::Io(_) | CoreError::Timeout(_))
+}
+
+/// A pool of tree-connected shares that amortizes negotiate, authentication,
+/// and tree connect across many operations.
+///
+/// Idle shares are keyed by the [`Client`] that connected them and the share
+/// name, so a share is only handed back to that client or its clones. Clients
+/// built separately never share entries, even for the same server and account,
+/// because their credentials or security settings may differ. Each idle share
+/// is checked with `ECHO` before it is handed out again; shares that fail the
+/// check are dropped and replaced by a fresh connection. Cloning the pool is
+/// cheap and every clone shares the same idle set.
+#[derive(Debug)]
+pub struct SharePool<T = TokioTcpTransport> {
+    inner: Arc<SharePoolInner<T>>,
+}
+
+#[derive(Debug)]
+struct SharePoolInner<T> {
+    max_idle_per_key: usize,
+    idle: Mutex<HashMap<PoolKey, Vec<Share<T>>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PoolKey {
+    client: u64,
+    share: String,
+}
+
+impl PoolKey {
+    fn new(client: &Client, share: &str) -> Result<Self, CoreError> {
+        // Key on the resolved name so bare, UNC and trailing-separator
+        // spellings of one share land on the same idle entry.
+        let share = resolve_share_name(client.server(), share)?;
+        Ok(Self {
+            client: client.identity,
+            share: share.to_lowercase(),
+        })
+    }
+}
+
+impl<T> Clone for SharePool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl SharePool {
+    /// Returns a healthy pooled share, connecting a new one when none is idle.
+    pub async fn acquire(&self, client: &Client, share: &str) -> Result<PooledShare, CoreError> {
+        let key = PoolKey::new(client, share)?;
+        if let Some(share) = self.take_healthy(&key).await {
+            return Ok(self.guard(key, share));
+        }
+        let share = client.connect_share(share).await?;
+        Ok(self.guard(key, share))
+    }
+}
+
+impl<T> SharePool<T>
+where
+    T: SmbTransport + Send,
+{
+    /// Creates a pool that keeps at most `max_idle_per_key` idle shares per key.
+    #[must_use]
+    pub fn new(max_idle_per_key: usize) -> Self {
+        Self {
+            inner: Arc::new(SharePoolInner {
+                max_idle_per_key,
+                idle: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Returns the idle-share limit applied to each key.
+    #[must_use]
+    pub fn max_idle_per_key(&self) -> usize {
+        self.inner.max_idle_per_key
+    }
+
+    /// Returns the number of idle shares currently held across all keys.
+    #[must_use]
+    pub fn idle_count(&self) -> usize {
+        self.inner.lock_idle().values().map(Vec::len).sum()
+    }
+
+    /// Drops every idle share held by the pool.
+    pub fn clear(&self) {
+        self.inner.lock_idle().clear();
+    }
+
+    async fn take_healthy(&self, key: &PoolKey) -> Option<Share<T>> {
+        loop {
+            let mut share = self.inner.take_idle(key)?;
+            if share.connection_mut().echo().await.is_ok() {
+                return Some(share);
+            }
+        }
+    }
+
+    fn guard(&self, key: PoolKey, share: Share<T>) -> PooledShare<T> {
+        PooledShare {
+            pool: Arc::clone(&self.inner),
+            key,
+            share: Some(share),
+        }
+    }
+}
+
+impl<T> SharePoolInner<T> {
+    fn lock_idle(&self) -> std::sync::MutexGuard<'_, HashMap<PoolKey, Vec<Share<T>>>> {
+        self.idle
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn take_idle(&self, key: &PoolKey) -> Option<Share<T>> {
+        self.lock_idle().get_mut(key)?.pop()
+    }
+
+    fn release(&self, key: PoolKey, share: Share<T>) {
+        let mut idle = self.lock_idle();
+        let shares = idle.entry(key).or_default();
+        if shares.len() < self.max_idle_per_key {
+            shares.push(share);
+        }
+    }
+}
+
+/// A share checked out of a [`SharePool`].
+///
+/// The share dereferences to [`Share`] and returns to the pool when dropped,
+/// unless the pool already holds its idle limit for the key. Use
+/// [`PooledShare::detach`] to keep the share out of the pool, for example
+/// before consuming it with [`Share::open`].
+#[derive(Debug)]
+pub struct PooledShare<T = TokioTcpTransport> {
+    pool: Arc<SharePoolInner<T>>,
+    key: PoolKey,
+    share: Option<Share<T>>,
+}
+
+impl<T> PooledShare<T> {
+    /// Removes the share from pool management and returns it.
+    #[must_use]
+    pub fn detach(mut self) -> Share<T> {
+        self.share
+            .take()
+            .expect("pooled share is present until dropped or detached")
+    }
+}
+
+impl<T> Deref for PooledShare<T> {
+    type Target = Share<T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.share
+            .as_ref()
+            .expect("pooled share is present until dropped or detached")
+    }
+}
+
+impl<T> DerefMut for PooledShare<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.share
+            .as_mut()
+            .expect("pooled share is present until dropped or detached")
+    }
+}
+
+impl<T> Drop for PooledShare<T> {
+    fn drop(&mut self) {
+        if let Some(share) = self.share.take() {
+            self.pool.release(self.key.clone(), share);
+        }
+    }
+}
+
+/// A share that several tasks can use in turn, with an optional idle keepalive.
+///
+/// Every operation runs on the guard returned by [`SharedShare::lock`], so the
+/// keepalive `ECHO` never interleaves with a caller's request on the wire.
+/// Cloning is cheap and every clone drives the same share.
+#[derive(Debug)]
+pub struct SharedShare<T = TokioTcpTransport> {
+    inner: Arc<SharedShareInner<T>>,
+}
+
+#[derive(Debug)]
+struct SharedShareInner<T> {
+    share: tokio::sync::Mutex<Share<T>>,
+    last_used: Mutex<Instant>,
+}
+
+impl<T> Clone for SharedShare<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> SharedShare<T>
+where
+    T: SmbTransport + Send + 'static,
+{
+    /// Wraps a tree-connected share for shared use.
+    #[must_use]
+    pub fn new(share: Share<T>) -> Self {
+        Self {
+            inner: Arc::new(SharedShareInner {
+                share: tokio::sync::Mutex::new(share),
+                last_used: Mutex::new(Instant::now()),
+            }),
+        }
+    }
+
+    /// Waits for exclusive access to the share and marks it as in use.
+    pub async fn lock(&self) -> tokio::sync::MutexGuard<'_, Share<T>> {
+        let share = self.inner.share.lock().await;
+        self.inner.touch();
+        share
+    }
+
+    /// Starts a background task that sends `ECHO` whenever the share has been
+    /// idle for `interval`, keeping server and firewall idle timers from
+    /// dropping the connection.
+    ///
+    /// The task stops when the returned handle is aborted or dropped, or after
+    /// the first failed echo.
+    #[must_use]
+    pub fn spawn_keepalive(&self, interval: Duration) -> KeepaliveHandle {
+        let inner = Arc::clone(&self.inner);
+        let task = tokio::spawn(async move {
+            loop {
+                let idle_for = inner.idle_for();
+                if idle_for < interval {
+                    tokio::time::sleep(interval - idle_for).await;
+                    continue;
+                }
+                let mut share = inner.share.lock().await;
+                if inner.idle_for() < interval {
+                    continue;
+                }
+                if share.connection_mut().echo().await.is_err() {
+                    break;
+                }
+                inner.touch();
+            }
+        });
+        KeepaliveHandle { task }
+    }
+}
+
+impl<T> SharedShareInner<T> {
+    fn lock_last_used(&self) -> std::sync::MutexGuard<'_, Instant> {
+        self.last_used
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn touch(&self) {
+        *self.lock_last_used() = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.lock_last_used().elapsed()
+    }
+}
+
+/// Handle to a keepalive task started by [`SharedShare::spawn_keepalive`].
+///
+/// Dropping the handle stops the keepalive.
+#[derive(Debug)]
+pub struct KeepaliveHandle {
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl KeepaliveHandle {
+    /// Stops the keepalive task.
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+
+    /// Returns true once the task has stopped, for example after a failed echo.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Drop for KeepaliveHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
 /// Authenticated SMB session returned by the high-level client.
 #[derive(Debug)]
 pub struct Session<T = TokioTcpTransport> {
@@ -428,6 +1002,40 @@ where
         self.connection.session_key()
     }
 
+    /// Derives the SMB 3.x application key, if the session has one.
+    pub fn application_key(&self) -> Result<Option<Zeroizing<Vec<u8>>>, CoreError> {
+        self.connection.application_key()
+    }
+
+    /// Returns the server names and version reported in the NTLM challenge.
+    ///
+    /// This is `None` for Kerberos sessions.
+    #[must_use]
+    pub fn ntlm_server_info(&self) -> Option<&NtlmServerInfo> {
+        self.connection.ntlm_server_info()
+    }
+
+    /// Returns whether the server logged the session on as a guest.
+    ///
+    /// Servers may map unknown accounts to guest without failing the logon,
+    /// so credentials that "work" can still end up with guest permissions.
+    #[must_use]
+    pub fn is_guest(&self) -> bool {
+        self.connection.is_guest()
+    }
+
+    /// Returns whether the server treated the session as an anonymous null session.
+    #[must_use]
+    pub fn is_null_session(&self) -> bool {
+        self.connection.is_null_session()
+    }
+
+    /// Returns the SMB dialect negotiated with the server.
+    #[must_use]
+    pub fn dialect(&self) -> Dialect {
+        self.connection.dialect()
+    }
+
     /// Returns the wrapped authenticated connection.
     #[must_use]
     pub fn connection(&self) -> &Connection<T, Authenticated> {
@@ -452,8 +1060,11 @@ where
     }
 
     /// Tree-connects to the requested share.
+    ///
+    /// `share` may be a bare share name or a `\\server\share` UNC root naming
+    /// this session's server.
     pub async fn connect_share(self, share: &str) -> Result<Share<T>, CoreError> {
-        let normalized_share = normalize_share_name(share)?;
+        let normalized_share = resolve_share_name(&self.server, share)?;
         let unc = format!(r"\\{}\{}", self.server, normalized_share);
         let connection = self
             .connection
@@ -541,10 +1152,27 @@ where
         self.connect_samr_pipe("lsarpc").await
     }
 
-    /// Logs off the authenticated SMB session.
+    /// Logs off the authenticated SMB session and shuts down the transport.
+    ///
+    /// A transport the server already closed after the `LOGOFF` is not an
+    /// error; any other shutdown failure is returned.
     pub async fn logoff(self) -> Result<(), CoreError> {
-        let _ = self.connection.logoff().await?;
-        Ok(())
+        match self.connection.logoff().await?.shutdown().await {
+            Err(CoreError::Io(error)) if error.kind() == std::io::ErrorKind::NotConnected => Ok(()),
+            result => result,
+        }
+    }
+}
+
+impl Session<TokioTcpTransport> {
+    /// Replaces the read timeout for subsequent responses. `None` leaves reads unbounded.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.connection.transport_mut().set_read_timeout(timeout);
+    }
+
+    /// Replaces the write timeout for subsequent requests. `None` leaves writes unbounded.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.connection.transport_mut().set_write_timeout(timeout);
     }
 }
 
@@ -590,9 +1218,43 @@ where
         self.connection.session_key()
     }
 
-    /// Returns the wrapped tree-connected connection.
-    #[must_use]
-    pub fn connection(&self) -> &Connection<T, TreeConnected> {
+    /// Derives the SMB 3.x application key, if the session has one.
+    pub fn application_key(&self) -> Result<Option<Zeroizing<Vec<u8>>>, CoreError> {
+        self.connection.application_key()
+    }
+
+    /// Returns the server names and version reported in the NTLM challenge.
+    ///
+    /// This is `None` for Kerberos sessions.
+    #[must_use]
+    pub fn ntlm_server_info(&self) -> Option<&NtlmServerInfo> {
+        self.connection.ntlm_server_info()
+    }
+
+    /// Returns whether the server logged the session on as a guest.
+    ///
+    /// Servers may map unknown accounts to guest without failing the logon,
+    /// so credentials that "work" can still end up with guest permissions.
+    #[must_use]
+    pub fn is_guest(&self) -> bool {
+        self.connection.is_guest()
+    }
+
+    /// Returns whether the server treated the session as an anonymous null session.
+    #[must_use]
+    pub fn is_null_session(&self) -> bool {
+        self.connection.is_null_session()
+    }
+
+    /// Returns the SMB dialect negotiated with the server.
+    #[must_use]
+    pub fn dialect(&self) -> Dialect {
+        self.connection.dialect()
+    }
+
+    /// Returns the wrapped tree-connected connection.
+    #[must_use]
+    pub fn connection(&self) -> &Connection<T, TreeConnected> {
         &self.connection
     }
 
@@ -613,16 +1275,22 @@ where
         let normalized_path = normalize_share_path(path)?;
         let dialect = self.connection.state().negotiated.dialect_revision;
         let create_request = options.to_create_request(&normalized_path)?;
-        let (durable_handle, file_id) = if let Some(durable) = options.durable_options(dialect) {
-            let durable_handle = self
-                .connection
-                .create_durable(&create_request, durable)
-                .await?;
-            (Some(durable_handle.clone()), durable_handle.file_id())
-        } else {
-            let response = self.connection.create(&create_request).await?;
-            (None, response.file_id)
-        };
+        let (durable_handle, file_id, oplock_level) =
+            if let Some(durable) = options.durable_options(dialect) {
+                let durable_handle = self
+                    .connection
+                    .create_durable(&create_request, durable)
+                    .await?;
+                let oplock_level = durable_handle.create_response().oplock_level;
+                (
+                    Some(durable_handle.clone()),
+                    durable_handle.file_id(),
+                    oplock_level,
+                )
+            } else {
+                let response = self.connection.create(&create_request).await?;
+                (None, response.file_id, response.oplock_level)
+            };
 
         let resilient_handle = if let Some(timeout) = options.resilient_timeout {
             Some(self.connection.request_resiliency(file_id, timeout).await?)
@@ -636,6 +1304,7 @@ where
             file_id,
             durable_handle,
             resilient_handle,
+            oplock_level,
         })
     }
 
@@ -718,28 +1387,39 @@ where
             .to_create_request(&normalized_path)?;
         let response = self.connection.create(&create_request).await?;
         let file_id = response.file_id;
-        let size = self.stat_by_id(file_id).await?.size;
-        let mut output = Vec::with_capacity(usize::try_from(size).unwrap_or(0));
-        let mut offset = 0u64;
 
-        while offset < size {
-            let remaining = size - offset;
-            let chunk_len = remaining.min(MAX_IO_CHUNK_SIZE as u64) as u32;
-            let response = self
-                .connection
-                .read(&ReadRequest::for_file(file_id, offset, chunk_len))
-                .await?;
-            if response.data.is_empty() {
-                break;
+        let read_result = async {
+            let size = self.stat_by_id(file_id).await?.size;
+            let mut output = Vec::with_capacity(usize::try_from(size).unwrap_or(0));
+            let mut offset = 0u64;
+
+            while offset < size {
+                let remaining = size - offset;
+                let chunk_len = remaining.min(u64::from(self.max_read_size())) as u32;
+                let response = self
+                    .connection
+                    .read(&ReadRequest::for_file(file_id, offset, chunk_len))
+                    .await?;
+                if response.data.is_empty() {
+                    break;
+                }
+                offset = offset.saturating_add(response.data.len() as u64);
+                output.extend_from_slice(&response.data);
             }
-            offset = offset.saturating_add(response.data.len() as u64);
-            output.extend_from_slice(&response.data);
+
+            Ok(output)
         }
+        .await;
 
-        self.connection
+        let close_result = self
+            .connection
             .close(&CloseRequest { flags: 0, file_id })
-            .await?;
-        Ok(output)
+            .await;
+        match (read_result, close_result) {
+            (Ok(output), Ok(_)) => Ok(output),
+            (Err(error), _) => Err(error),
+            (Ok(_), Err(error)) => Err(error),
+        }
     }
 
     /// Reads the full contents of a file on the current tree.
@@ -763,13 +1443,8 @@ where
         let mut offset = 0u64;
 
         while (offset as usize) < data.len() {
-            let chunk_end = (offset as usize + MAX_IO_CHUNK_SIZE).min(data.len());
-            self.connection
-                .write(&WriteRequest::for_file(
-                    file_id,
-                    offset,
-                    data[offset as usize..chunk_end].to_vec(),
-                ))
+            let chunk_end = (offset as usize + self.max_write_size() as usize).min(data.len());
+            self.write_chunk(file_id, offset, &data[offset as usize..chunk_end])
                 .await?;
             offset = chunk_end as u64;
         }
@@ -791,7 +1466,36 @@ where
         self.write(path, data).await
     }
 
+    /// Copies a file to another path on the current tree and returns the bytes copied.
+    ///
+    /// The contents stream through the client in negotiated-size chunks, so
+    /// large files are never buffered in full. An existing destination is
+    /// overwritten. Both handles are closed even when the copy fails, but a
+    /// failed copy may leave a partial destination behind. Copying a path onto
+    /// itself is rejected, since truncating the destination would destroy it.
+    pub async fn copy(&mut self, source: &str, destination: &str) -> Result<u64, CoreError> {
+        self.copy_file::<T>(source, None, destination).await
+    }
+
+    /// Copies a file from the current tree to a path on `target` and returns the bytes copied.
+    ///
+    /// `target` may be another tree on the same server or a share on a
+    /// different server. The behavior otherwise matches [`Share::copy`].
+    pub async fn copy_to<U>(
+        &mut self,
+        source: &str,
+        target: &mut Share<U>,
+        destination: &str,
+    ) -> Result<u64, CoreError>
+    where
+        U: SmbTransport + Send,
+    {
+        self.copy_file(source, Some(target), destination).await
+    }
+
     /// Queries file metadata on the current tree.
+    ///
+    /// A missing file or parent directory is reported as [`CoreError::NotFound`].
     pub async fn stat(&mut self, path: &str) -> Result<FileMetadata, CoreError> {
         let normalized_path = normalize_share_path(path)?;
         let mut create_request = CreateRequest::from_path(&normalized_path);
@@ -799,13 +1503,152 @@ where
         create_request.share_access = ShareAccess::READ | ShareAccess::WRITE | ShareAccess::DELETE;
         create_request.create_disposition = CreateDisposition::Open;
         create_request.create_options = CreateOptions::NON_DIRECTORY_FILE;
-        let response = self.connection.create(&create_request).await?;
+        let response = self
+            .connection
+            .create(&create_request)
+            .await
+            .map_err(|error| not_found_error(error, &normalized_path))?;
         let file_id = response.file_id;
-        let metadata = self.stat_by_id(file_id).await?;
-        self.connection
+
+        let metadata_result = self.stat_by_id(file_id).await;
+        let close_result = self
+            .connection
+            .close(&CloseRequest { flags: 0, file_id })
+            .await;
+        match (metadata_result, close_result) {
+            (Ok(metadata), Ok(_)) => Ok(metadata),
+            (Err(error), _) => Err(error),
+            (Ok(_), Err(error)) => Err(error),
+        }
+    }
+
+    /// Asks the server for DFS referrals covering `path` and returns the target UNC paths.
+    ///
+    /// Call this on an `IPC$` share with a full UNC path inside a DFS
+    /// namespace. Each target has the part of `path` below the referral's
+    /// namespace prefix appended, so it can be opened directly. A path outside
+    /// any namespace surfaces the server's error, usually `STATUS_NOT_FOUND`.
+    pub async fn resolve_dfs(&mut self, path: &str) -> Result<Vec<String>, CoreError> {
+        let unc = UncPath::parse(path)?;
+        let response = self
+            .connection
+            .ioctl(&IoctlRequest::get_dfs_referrals(
+                DfsReferralRequest {
+                    max_referral_level: 4,
+                    request_file_name: unc.as_unc(),
+                },
+                DFS_REFERRAL_BUFFER_SIZE,
+            ))
+            .await?;
+        let response = response
+            .dfs_referral_response()?
+            .ok_or(CoreError::InvalidResponse(
+                "DFS referral IOCTL did not return a DFS referral response",
+            ))?;
+
+        let mut targets = Vec::new();
+        for referral in referrals_from_response(&response)? {
+            if !unc.starts_with(&referral.namespace_path) {
+                continue;
+            }
+            let target = resolve_unc_path(&unc, std::slice::from_ref(&referral)).as_unc();
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        Ok(targets)
+    }
+
+    /// Reads the owner, group, and DACL of a file or directory on the current tree.
+    pub async fn security_descriptor(
+        &mut self,
+        path: &str,
+    ) -> Result<SecurityDescriptor, CoreError> {
+        let normalized_path = normalize_share_path(path)?;
+        let mut create_request = CreateRequest::from_path(&normalized_path);
+        create_request.desired_access = READ_CONTROL | SYNCHRONIZE;
+        create_request.share_access = ShareAccess::READ | ShareAccess::WRITE | ShareAccess::DELETE;
+        create_request.create_disposition = CreateDisposition::Open;
+        create_request.create_options = CreateOptions::empty();
+        let response = self
+            .connection
+            .create(&create_request)
+            .await
+            .map_err(|error| not_found_error(error, &normalized_path))?;
+        let file_id = response.file_id;
+
+        let security_result = self.security_descriptor_by_id(file_id).await;
+        let close_result = self
+            .connection
             .close(&CloseRequest { flags: 0, file_id })
+            .await;
+        match (security_result, close_result) {
+            (Ok(descriptor), Ok(_)) => Ok(descriptor),
+            (Err(error), _) => Err(error),
+            (Ok(_), Err(error)) => Err(error),
+        }
+    }
+
+    /// Reports the size and free space of the volume behind the current tree.
+    ///
+    /// Free space is what the server makes available to the calling user, so
+    /// it already accounts for any per-user quota.
+    pub async fn fs_info(&mut self) -> Result<FsInfo, CoreError> {
+        let buffers = self
+            .query_root_fs_info(&[FileSystemInfoClass::FullSizeInformation])
+            .await?;
+        let info = FileFsFullSizeInformation::decode(&buffers[0]).map_err(CoreError::from)?;
+        Ok(FsInfo::from(info))
+    }
+
+    /// Reports the label and filesystem type of the volume behind the current tree.
+    pub async fn volume_info(&mut self) -> Result<VolumeInfo, CoreError> {
+        let buffers = self
+            .query_root_fs_info(&[
+                FileSystemInfoClass::VolumeInformation,
+                FileSystemInfoClass::AttributeInformation,
+            ])
+            .await?;
+        let volume = FileFsVolumeInformation::decode(&buffers[0]).map_err(CoreError::from)?;
+        let attributes =
+            FileFsAttributeInformation::decode(&buffers[1]).map_err(CoreError::from)?;
+        Ok(VolumeInfo {
+            label: volume.volume_label,
+            serial_number: volume.volume_serial_number,
+            created: system_time_from_windows_ticks(volume.volume_creation_time),
+            file_system_name: attributes.file_system_name,
+            max_component_name_length: attributes.maximum_component_name_length,
+            attributes: attributes.file_system_attributes,
+        })
+    }
+
+    /// Returns whether a file or directory exists on the current tree.
+    ///
+    /// SMB2 has no path-based query, so this still opens the path for
+    /// attribute access and closes it again, but skips the metadata queries.
+    pub async fn exists(&mut self, path: &str) -> Result<bool, CoreError> {
+        let normalized_path = normalize_share_path(path)?;
+        let mut create_request = CreateRequest::from_path(&normalized_path);
+        create_request.desired_access = FILE_READ_ATTRIBUTES | SYNCHRONIZE;
+        create_request.share_access = ShareAccess::READ | ShareAccess::WRITE | ShareAccess::DELETE;
+        create_request.create_disposition = CreateDisposition::Open;
+        create_request.create_options = CreateOptions::empty();
+        let response = match self.connection.create(&create_request).await {
+            Ok(response) => response,
+            Err(error) => {
+                return match not_found_error(error, &normalized_path) {
+                    CoreError::NotFound(_) => Ok(false),
+                    error => Err(error),
+                };
+            }
+        };
+        self.connection
+            .close(&CloseRequest {
+                flags: 0,
+                file_id: response.file_id,
+            })
             .await?;
-        Ok(metadata)
+        Ok(true)
     }
 
     /// Queries file metadata on the current tree.
@@ -821,6 +1664,28 @@ where
     /// The returned list filters out the `.` and `..` placeholders so embedders
     /// get the entries they usually expect from a high-level client facade.
     pub async fn list(&mut self, path: &str) -> Result<Vec<DirectoryEntry>, CoreError> {
+        self.list_matching(path, "*").await
+    }
+
+    /// Enumerates the entries of one directory whose names match `pattern`.
+    ///
+    /// The pattern is sent to the server as the directory search string, so
+    /// wildcards such as `*.log` are evaluated remotely and non-matching
+    /// entries never cross the wire. As with [`Share::list`], the `.` and `..`
+    /// placeholders are filtered out.
+    pub async fn list_matching(
+        &mut self,
+        path: &str,
+        pattern: &str,
+    ) -> Result<Vec<DirectoryEntry>, CoreError> {
+        if pattern.is_empty() {
+            return Err(CoreError::PathInvalid("search pattern must not be empty"));
+        }
+        if pattern.contains(['\\', '/', '\0']) {
+            return Err(CoreError::PathInvalid(
+                "search pattern must not contain separators or NUL bytes",
+            ));
+        }
         let normalized_path = normalize_share_path(path)?;
         let mut create_request = CreateRequest::from_path(&normalized_path);
         create_request.desired_access = FILE_LIST_DIRECTORY | FILE_READ_ATTRIBUTES | SYNCHRONIZE;
@@ -832,22 +1697,22 @@ where
 
         let list_result = async {
             let mut request =
-                QueryDirectoryRequest::for_pattern(file_id, "*", DIRECTORY_QUERY_BUFFER_SIZE);
+                QueryDirectoryRequest::for_pattern(file_id, pattern, DIRECTORY_QUERY_BUFFER_SIZE);
             let mut entries = Vec::new();
 
             loop {
                 let response = self.connection.query_directory(&request).await?;
-                let batch = response
-                    .directory_entries()
-                    .map_err(CoreError::from)?
-                    .into_iter()
-                    .filter(|entry| entry.file_name != "." && entry.file_name != "..")
-                    .map(directory_entry_from_info)
-                    .collect::<Vec<_>>();
-                if batch.is_empty() {
+                let page = response.directory_entries().map_err(CoreError::from)?;
+                // Only an empty page ends the enumeration; a page holding just the
+                // dot placeholders must not cut the listing short.
+                if page.is_empty() {
                     break;
                 }
-                entries.extend(batch);
+                entries.extend(
+                    page.into_iter()
+                        .filter(|entry| entry.file_name != "." && entry.file_name != "..")
+                        .map(directory_entry_from_info),
+                );
                 request.flags = QueryDirectoryFlags::empty();
                 request.file_name.clear();
             }
@@ -867,6 +1732,50 @@ where
         }
     }
 
+    /// Recursively enumerates a directory tree on the current tree.
+    ///
+    /// Directories are walked depth-first and each returned entry is named by
+    /// its path relative to `root`. `max_depth` bounds how many levels below
+    /// `root` are descended into, so `0` returns only the entries of `root`.
+    /// Reparse points such as symbolic links and junctions are reported but
+    /// never followed, which keeps link cycles from looping the walk.
+    pub async fn walk(
+        &mut self,
+        root: &str,
+        max_depth: usize,
+    ) -> Result<Vec<DirectoryEntry>, CoreError> {
+        let root = normalize_share_path(root)?;
+        let mut pending = vec![(String::new(), 0)];
+        let mut entries = Vec::new();
+
+        while let Some((relative, depth)) = pending.pop() {
+            let path = match (root.as_str(), relative.is_empty()) {
+                (_, true) => root.clone(),
+                ("\\", false) => relative.clone(),
+                (root, false) => format!("{root}\\{relative}"),
+            };
+            let mut subdirectories = Vec::new();
+            for mut entry in self.list(&path).await? {
+                if !relative.is_empty() {
+                    entry.name = format!("{relative}\\{}", entry.name);
+                }
+                if depth < max_depth
+                    && entry.is_directory()
+                    && !entry
+                        .metadata
+                        .attributes
+                        .contains(FileAttributes::REPARSE_POINT)
+                {
+                    subdirectories.push((entry.name.clone(), depth + 1));
+                }
+                entries.push(entry);
+            }
+            pending.extend(subdirectories.into_iter().rev());
+        }
+
+        Ok(entries)
+    }
+
     /// Enumerates one directory on the current tree.
     ///
     /// This is an alias for [`Share::list`] that matches the common filesystem
@@ -875,6 +1784,38 @@ where
         self.list(path).await
     }
 
+    /// Opens one directory on the current tree for change notifications.
+    ///
+    /// The returned [`DirectoryWatcher`] keeps the directory handle open, so the
+    /// server keeps recording changes between calls to
+    /// [`DirectoryWatcher::next_changes`]. With `recursive`, changes anywhere in
+    /// the subtree are reported.
+    pub async fn watch(
+        mut self,
+        path: &str,
+        filter: CompletionFilter,
+        recursive: bool,
+    ) -> Result<DirectoryWatcher<T>, CoreError> {
+        let normalized_path = normalize_share_path(path)?;
+        let mut create_request = CreateRequest::from_path(&normalized_path);
+        create_request.desired_access = FILE_LIST_DIRECTORY | SYNCHRONIZE;
+        create_request.share_access = ShareAccess::READ | ShareAccess::WRITE | ShareAccess::DELETE;
+        create_request.create_disposition = CreateDisposition::Open;
+        create_request.create_options = CreateOptions::DIRECTORY_FILE;
+        let response = self.connection.create(&create_request).await?;
+
+        let mut request =
+            ChangeNotifyRequest::for_directory(response.file_id, filter, CHANGE_NOTIFY_BUFFER_SIZE);
+        if recursive {
+            request.flags = ChangeNotifyFlags::WATCH_TREE;
+        }
+        Ok(DirectoryWatcher {
+            share: self,
+            path: normalized_path,
+            request,
+        })
+    }
+
     /// Creates one directory on the current tree.
     pub async fn create_dir(&mut self, path: &str) -> Result<(), CoreError> {
         let normalized_path = normalize_share_path(path)?;
@@ -932,18 +1873,141 @@ where
         }
     }
 
+    /// Replaces the attributes of a file or directory on the current tree.
+    ///
+    /// An empty set clears every attribute, which the server records as
+    /// [`FileAttributes::NORMAL`]. Read the current attributes with
+    /// [`Share::stat`].
+    pub async fn set_attributes(
+        &mut self,
+        path: &str,
+        attributes: FileAttributes,
+    ) -> Result<(), CoreError> {
+        let file_attributes = if attributes.is_empty() {
+            FileAttributes::NORMAL
+        } else {
+            attributes
+        };
+        self.set_basic_info(
+            path,
+            FileBasicInformation {
+                creation_time: 0,
+                last_access_time: 0,
+                last_write_time: 0,
+                change_time: 0,
+                file_attributes,
+            },
+        )
+        .await
+    }
+
+    /// Sets the creation, last-access, and last-write times of a file or directory.
+    ///
+    /// A `None` leaves that timestamp unchanged, and the file's attributes are
+    /// never touched. Restore tools can call this after copying content back.
+    pub async fn set_times(
+        &mut self,
+        path: &str,
+        created: Option<SystemTime>,
+        accessed: Option<SystemTime>,
+        modified: Option<SystemTime>,
+    ) -> Result<(), CoreError> {
+        self.set_basic_info(path, basic_info_for_times(created, accessed, modified))
+            .await
+    }
+
+    async fn set_basic_info(
+        &mut self,
+        path: &str,
+        basic: FileBasicInformation,
+    ) -> Result<(), CoreError> {
+        let normalized_path = normalize_share_path(path)?;
+        let mut create_request = CreateRequest::from_path(&normalized_path);
+        create_request.desired_access = FILE_READ_ATTRIBUTES | FILE_WRITE_ATTRIBUTES | SYNCHRONIZE;
+        create_request.share_access = ShareAccess::READ | ShareAccess::WRITE | ShareAccess::DELETE;
+        create_request.create_disposition = CreateDisposition::Open;
+        create_request.create_options = CreateOptions::empty();
+        let response = self.connection.create(&create_request).await?;
+        let file_id = response.file_id;
+
+        let set_result = self.set_basic_info_by_id(file_id, basic).await;
+        let close_result = self
+            .connection
+            .close(&CloseRequest { flags: 0, file_id })
+            .await;
+        match (set_result, close_result) {
+            (Ok(_), Ok(_)) => Ok(()),
+            (Err(error), _) => Err(error),
+            (Ok(_), Err(error)) => Err(error),
+        }
+    }
+
+    async fn set_basic_info_by_id(
+        &mut self,
+        file_id: FileId,
+        basic: FileBasicInformation,
+    ) -> Result<(), CoreError> {
+        let _ = self
+            .connection
+            .set_info(&SetInfoRequest::for_file_info(
+                file_id,
+                FileInfoClass::BasicInformation,
+                basic.encode(),
+            ))
+            .await?;
+        Ok(())
+    }
+
     /// Removes a file from the current tree by marking it delete-pending and closing it.
     pub async fn remove(&mut self, path: &str) -> Result<(), CoreError> {
         let normalized_path = normalize_share_path(path)?;
-        let mut create_request = CreateRequest::from_path(&normalized_path);
+        self.delete_on_close(&normalized_path, CreateOptions::NON_DIRECTORY_FILE)
+            .await
+    }
+
+    /// Removes an empty directory from the current tree.
+    ///
+    /// A directory that still has entries is reported as
+    /// [`CoreError::DirectoryNotEmpty`] so callers can clear it first.
+    pub async fn remove_dir(&mut self, path: &str) -> Result<(), CoreError> {
+        let normalized_path = normalize_share_path(path)?;
+        match self
+            .delete_on_close(&normalized_path, CreateOptions::DIRECTORY_FILE)
+            .await
+        {
+            Err(CoreError::UnexpectedStatus { status, .. })
+                if status == NtStatus::DIRECTORY_NOT_EMPTY.to_u32() =>
+            {
+                Err(CoreError::DirectoryNotEmpty(normalized_path))
+            }
+            result => result,
+        }
+    }
+
+    /// Removes an empty directory from the current tree.
+    ///
+    /// This is an alias for [`Share::remove_dir`] that matches common shell
+    /// naming.
+    pub async fn rmdir(&mut self, path: &str) -> Result<(), CoreError> {
+        self.remove_dir(path).await
+    }
+
+    async fn delete_on_close(
+        &mut self,
+        normalized_path: &str,
+        create_options: CreateOptions,
+    ) -> Result<(), CoreError> {
+        let mut create_request = CreateRequest::from_path(normalized_path);
         create_request.desired_access =
             DELETE | FILE_READ_ATTRIBUTES | FILE_WRITE_ATTRIBUTES | READ_CONTROL | SYNCHRONIZE;
         create_request.share_access = ShareAccess::READ | ShareAccess::WRITE | ShareAccess::DELETE;
         create_request.create_disposition = CreateDisposition::Open;
-        create_request.create_options = CreateOptions::NON_DIRECTORY_FILE;
+        create_request.create_options = create_options;
         let response = self.connection.create(&create_request).await?;
         let file_id = response.file_id;
-        self.connection
+
+        let remove_result = self
+            .connection
             .set_info(&SetInfoRequest::for_file_info(
                 file_id,
                 FileInfoClass::DispositionInformation,
@@ -952,11 +2016,16 @@ where
                 }
                 .encode(),
             ))
-            .await?;
-        self.connection
+            .await;
+        let close_result = self
+            .connection
             .close(&CloseRequest { flags: 0, file_id })
-            .await?;
-        Ok(())
+            .await;
+        match (remove_result, close_result) {
+            (Ok(_), Ok(_)) => Ok(()),
+            (Err(error), _) => Err(error),
+            (Ok(_), Err(error)) => Err(error),
+        }
     }
 
     /// Opens an existing file on the current tree for read access.
@@ -983,7 +2052,7 @@ where
         })
     }
 
-    /// Disconnects the tree and logs off the SMB session.
+    /// Disconnects the tree, logs off the SMB session, and shuts down the transport.
     pub async fn logoff(self) -> Result<(), CoreError> {
         self.disconnect().await?.logoff().await
     }
@@ -1010,35 +2079,259 @@ where
 
         Ok(metadata_from_info(basic, standard))
     }
-}
 
-/// High-level open options for the embedded client facade.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct OpenOptions {
-    read: bool,
-    write: bool,
-    create: bool,
-    create_new: bool,
-    truncate: bool,
-    durable: Option<DurableOpenOptions>,
-    resilient_timeout: Option<u32>,
-}
+    async fn query_root_fs_info(
+        &mut self,
+        classes: &[FileSystemInfoClass],
+    ) -> Result<Vec<Vec<u8>>, CoreError> {
+        let mut create_request = CreateRequest::from_path("");
+        create_request.desired_access = FILE_READ_ATTRIBUTES | SYNCHRONIZE;
+        create_request.share_access = ShareAccess::READ | ShareAccess::WRITE | ShareAccess::DELETE;
+        create_request.create_disposition = CreateDisposition::Open;
+        create_request.create_options = CreateOptions::DIRECTORY_FILE;
+        let file_id = self.connection.create(&create_request).await?.file_id;
+
+        let query_result = async {
+            let mut buffers = Vec::with_capacity(classes.len());
+            for class in classes {
+                let response = self
+                    .connection
+                    .query_info(&QueryInfoRequest::for_fs_info(file_id, *class))
+                    .await?;
+                buffers.push(response.output_buffer);
+            }
+            Ok(buffers)
+        }
+        .await;
+        let close_result = self
+            .connection
+            .close(&CloseRequest { flags: 0, file_id })
+            .await;
+        match (query_result, close_result) {
+            (Ok(buffers), Ok(_)) => Ok(buffers),
+            (Err(error), _) => Err(error),
+            (Ok(_), Err(error)) => Err(error),
+        }
+    }
 
-impl OpenOptions {
-    /// Builds a default empty option set.
-    #[must_use]
-    pub fn new() -> Self {
-        Self::default()
+    async fn security_descriptor_by_id(
+        &mut self,
+        file_id: FileId,
+    ) -> Result<SecurityDescriptor, CoreError> {
+        let mut request = QueryInfoRequest::for_security(
+            file_id,
+            SecurityInformation::OWNER | SecurityInformation::GROUP | SecurityInformation::DACL,
+        );
+        request.output_buffer_length = SECURITY_QUERY_BUFFER_SIZE;
+        let response = self.connection.query_info(&request).await?;
+        SecurityDescriptor::decode(&response.output_buffer).map_err(CoreError::from)
     }
 
-    /// Enables or disables read access.
-    #[must_use]
-    pub fn read(mut self, read: bool) -> Self {
-        self.read = read;
-        self
+    fn max_read_size(&self) -> u32 {
+        let negotiated = self.connection.state().negotiated.max_read_size;
+        negotiated.min(self.max_io_chunk_size()).max(1)
     }
 
-    /// Enables or disables write access.
+    fn max_write_size(&self) -> u32 {
+        let negotiated = self.connection.state().negotiated.max_write_size;
+        negotiated.min(self.max_io_chunk_size()).max(1)
+    }
+
+    fn max_io_chunk_size(&self) -> u32 {
+        if !self.connection.supports_multi_credit() {
+            return MAX_IO_CHUNK_SIZE as u32;
+        }
+        // Each credit covers 64 KiB, so stay within what the server has granted.
+        self.connection
+            .available_credits()
+            .max(1)
+            .saturating_mul(CREDIT_PAYLOAD_SIZE)
+            .min(MAX_MULTI_CREDIT_CHUNK_SIZE)
+    }
+
+    /// Streams `source` into `destination` on `target`, or on this tree when `target` is `None`.
+    async fn copy_file<U>(
+        &mut self,
+        source: &str,
+        mut target: Option<&mut Share<U>>,
+        destination: &str,
+    ) -> Result<u64, CoreError>
+    where
+        U: SmbTransport + Send,
+    {
+        let same_tree = target.as_deref().is_none_or(|target| {
+            self.server.eq_ignore_ascii_case(&target.server)
+                && self.name.eq_ignore_ascii_case(&target.name)
+        });
+        if same_tree
+            && normalize_share_path(source)?.to_lowercase()
+                == normalize_share_path(destination)?.to_lowercase()
+        {
+            return Err(CoreError::InvalidInput(
+                "copy source and destination are the same file",
+            ));
+        }
+
+        let (source_id, size) = self.open_copy_source(source).await?;
+        let destination_id = match target.as_deref_mut() {
+            Some(target) => target.open_copy_destination(destination).await,
+            None => self.open_copy_destination(destination).await,
+        };
+        let destination_id = match destination_id {
+            Ok(file_id) => file_id,
+            Err(error) => {
+                let _ = self.close_handle(source_id).await;
+                return Err(error);
+            }
+        };
+
+        let write_size = target
+            .as_deref()
+            .map_or_else(|| self.max_write_size(), Share::max_write_size);
+        let chunk_len = self.max_read_size().min(write_size);
+        let copy_result: Result<u64, CoreError> = async {
+            let mut offset = 0u64;
+            while offset < size {
+                let length = (size - offset).min(u64::from(chunk_len)) as u32;
+                let chunk = self.read_chunk(source_id, offset, length).await?;
+                if chunk.is_empty() {
+                    break;
+                }
+                match target.as_deref_mut() {
+                    Some(target) => target.write_chunk(destination_id, offset, &chunk).await?,
+                    None => self.write_chunk(destination_id, offset, &chunk).await?,
+                }
+                offset += chunk.len() as u64;
+            }
+            let flush = FlushRequest::for_file(destination_id);
+            match target.as_deref_mut() {
+                Some(target) => target.connection.flush(&flush).await?,
+                None => self.connection.flush(&flush).await?,
+            };
+            Ok(offset)
+        }
+        .await;
+
+        let source_close = self.close_handle(source_id).await;
+        let destination_close = match target {
+            Some(target) => target.close_handle(destination_id).await,
+            None => self.close_handle(destination_id).await,
+        };
+        let copied = copy_result?;
+        source_close?;
+        destination_close?;
+        Ok(copied)
+    }
+
+    async fn open_copy_source(&mut self, path: &str) -> Result<(FileId, u64), CoreError> {
+        let normalized_path = normalize_share_path(path)?;
+        let create_request = OpenOptions::new()
+            .read(true)
+            .to_create_request(&normalized_path)?;
+        let file_id = self.connection.create(&create_request).await?.file_id;
+        match self.stat_by_id(file_id).await {
+            Ok(metadata) => Ok((file_id, metadata.size)),
+            Err(error) => {
+                let _ = self.close_handle(file_id).await;
+                Err(error)
+            }
+        }
+    }
+
+    async fn open_copy_destination(&mut self, path: &str) -> Result<FileId, CoreError> {
+        let normalized_path = normalize_share_path(path)?;
+        let create_request = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .to_create_request(&normalized_path)?;
+        Ok(self.connection.create(&create_request).await?.file_id)
+    }
+
+    async fn read_chunk(
+        &mut self,
+        file_id: FileId,
+        offset: u64,
+        length: u32,
+    ) -> Result<Vec<u8>, CoreError> {
+        let response = self
+            .connection
+            .read(&ReadRequest::for_file(file_id, offset, length))
+            .await?;
+        Ok(response.data)
+    }
+
+    async fn close_handle(&mut self, file_id: FileId) -> Result<(), CoreError> {
+        let _ = self
+            .connection
+            .close(&CloseRequest { flags: 0, file_id })
+            .await?;
+        Ok(())
+    }
+
+    async fn write_chunk(
+        &mut self,
+        file_id: FileId,
+        offset: u64,
+        chunk: &[u8],
+    ) -> Result<(), CoreError> {
+        let response = self
+            .connection
+            .write(&WriteRequest::for_file(file_id, offset, chunk.to_vec()))
+            .await?;
+        if response.count as usize != chunk.len() {
+            return Err(CoreError::InvalidResponse(
+                "server acknowledged fewer bytes than were written",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Share<TokioTcpTransport> {
+    /// Replaces the read timeout for subsequent responses. `None` leaves reads unbounded.
+    ///
+    /// Tighten this around quick metadata calls and relax it again before
+    /// long transfers on the same tree.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.connection.transport_mut().set_read_timeout(timeout);
+    }
+
+    /// Replaces the write timeout for subsequent requests. `None` leaves writes unbounded.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.connection.transport_mut().set_write_timeout(timeout);
+    }
+}
+
+/// High-level open options for the embedded client facade.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    create: bool,
+    create_new: bool,
+    truncate: bool,
+    delete_on_close: bool,
+    durable: Option<DurableOpenOptions>,
+    resilient_timeout: Option<u32>,
+    oplock: Option<RequestedOplockLevel>,
+}
+
+impl OpenOptions {
+    /// Builds a default empty option set.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables read access.
+    #[must_use]
+    pub fn read(mut self, read: bool) -> Self {
+        self.read = read;
+        self
+    }
+
+    /// Enables or disables write access.
     #[must_use]
     pub fn write(mut self, write: bool) -> Self {
         self.write = write;
@@ -1066,6 +2359,16 @@ impl OpenOptions {
         self
     }
 
+    /// Deletes the file when the last handle to it closes.
+    ///
+    /// This suits temporary files: the server removes the file even if the
+    /// client never sends an explicit delete. It adds `DELETE` access.
+    #[must_use]
+    pub fn delete_on_close(mut self, delete_on_close: bool) -> Self {
+        self.delete_on_close = delete_on_close;
+        self
+    }
+
     /// Requests a durable handle for the opened file.
     #[must_use]
     pub fn durable(mut self, durable: DurableOpenOptions) -> Self {
@@ -1080,6 +2383,15 @@ impl OpenOptions {
         self
     }
 
+    /// Requests an oplock of the given level for the opened file.
+    ///
+    /// The server may grant a lower level; see [`File::oplock_level`].
+    #[must_use]
+    pub fn oplock(mut self, level: RequestedOplockLevel) -> Self {
+        self.oplock = Some(level);
+        self
+    }
+
     fn to_create_request(&self, path: &str) -> Result<CreateRequest, CoreError> {
         if !self.read && !self.write {
             return Err(CoreError::InvalidInput(
@@ -1097,7 +2409,11 @@ impl OpenOptions {
         request.share_access = ShareAccess::READ | ShareAccess::WRITE | ShareAccess::DELETE;
         request.file_attributes = FileAttributes::NORMAL;
         request.create_options = CreateOptions::NON_DIRECTORY_FILE;
+        if self.delete_on_close {
+            request.create_options |= CreateOptions::DELETE_ON_CLOSE;
+        }
         request.create_disposition = create_disposition(self);
+        request.requested_oplock_level = self.oplock.unwrap_or(RequestedOplockLevel::None);
         Ok(request)
     }
 
@@ -1112,20 +2428,6 @@ impl OpenOptions {
     }
 }
 
-impl Default for OpenOptions {
-    fn default() -> Self {
-        Self {
-            read: false,
-            write: false,
-            create: false,
-            create_new: false,
-            truncate: false,
-            durable: None,
-            resilient_timeout: None,
-        }
-    }
-}
-
 /// High-level metadata for an SMB object.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FileMetadata {
@@ -1161,6 +2463,72 @@ impl FileMetadata {
     }
 }
 
+/// Size and free space of the volume behind a share.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsInfo {
+    /// Total volume size in bytes.
+    pub total_bytes: u64,
+    /// Free bytes available to the calling user.
+    pub free_bytes: u64,
+    /// Total allocation units on the volume.
+    pub total_allocation_units: u64,
+    /// Free allocation units available to the calling user.
+    pub free_allocation_units: u64,
+    /// Sectors in each allocation unit.
+    pub sectors_per_allocation_unit: u32,
+    /// Bytes in each sector.
+    pub bytes_per_sector: u32,
+}
+
+impl From<FileFsFullSizeInformation> for FsInfo {
+    fn from(info: FileFsFullSizeInformation) -> Self {
+        let unit_size = info.bytes_per_allocation_unit();
+        Self {
+            total_bytes: info.total_allocation_units.saturating_mul(unit_size),
+            free_bytes: info
+                .caller_available_allocation_units
+                .saturating_mul(unit_size),
+            total_allocation_units: info.total_allocation_units,
+            free_allocation_units: info.caller_available_allocation_units,
+            sectors_per_allocation_unit: info.sectors_per_allocation_unit,
+            bytes_per_sector: info.bytes_per_sector,
+        }
+    }
+}
+
+/// Label and filesystem type of the volume behind a share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeInfo {
+    /// Volume label.
+    pub label: String,
+    /// Volume serial number.
+    pub serial_number: u32,
+    /// Volume creation time.
+    pub created: Option<SystemTime>,
+    /// Filesystem type name, such as `NTFS` or `FAT32`.
+    pub file_system_name: String,
+    /// Longest file name component the filesystem accepts, in characters.
+    pub max_component_name_length: u32,
+    /// Filesystem capability flags.
+    pub attributes: FileSystemAttributes,
+}
+
+impl VolumeInfo {
+    /// Returns true when name lookups on the volume are case-sensitive.
+    #[must_use]
+    pub fn is_case_sensitive(&self) -> bool {
+        self.attributes
+            .contains(FileSystemAttributes::CASE_SENSITIVE_SEARCH)
+    }
+
+    /// Returns true when the volume preserves the case of names.
+    #[must_use]
+    pub fn preserves_case(&self) -> bool {
+        self.attributes
+            .contains(FileSystemAttributes::CASE_PRESERVED_NAMES)
+    }
+}
+
 /// One directory entry returned by the high-level share facade.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DirectoryEntry {
@@ -1186,6 +2554,55 @@ impl DirectoryEntry {
     }
 }
 
+/// One directory held open on a tree-connected share for change notifications.
+#[derive(Debug)]
+pub struct DirectoryWatcher<T = TokioTcpTransport> {
+    share: Share<T>,
+    path: String,
+    request: ChangeNotifyRequest,
+}
+
+impl<T> DirectoryWatcher<T>
+where
+    T: SmbTransport + Send,
+{
+    /// Returns the watched directory path relative to the connected share.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the SMB file identifier of the open directory.
+    #[must_use]
+    pub fn file_id(&self) -> FileId {
+        self.request.file_id
+    }
+
+    /// Waits for the next batch of changes under the watched directory.
+    ///
+    /// The call blocks until the server reports a change matching the filter,
+    /// then returns the reported records. Changes made between calls are
+    /// buffered by the server against the open handle. An empty result means
+    /// the server answered `STATUS_NOTIFY_ENUM_DIR` because too many changes
+    /// occurred to be listed, so callers should rescan the directory.
+    pub async fn next_changes(&mut self) -> Result<Vec<FileNotifyInformation>, CoreError> {
+        let response = self.share.connection.change_notify(&self.request).await?;
+        response.notifications().map_err(CoreError::from)
+    }
+
+    /// Closes the watched directory and returns the share wrapper.
+    pub async fn close(mut self) -> Result<Share<T>, CoreError> {
+        self.share
+            .connection
+            .close(&CloseRequest {
+                flags: 0,
+                file_id: self.request.file_id,
+            })
+            .await?;
+        Ok(self.share)
+    }
+}
+
 /// One open file handle on a tree-connected share.
 #[derive(Debug)]
 pub struct File<T = TokioTcpTransport> {
@@ -1194,6 +2611,7 @@ pub struct File<T = TokioTcpTransport> {
     file_id: FileId,
     durable_handle: Option<DurableHandle>,
     resilient_handle: Option<ResilientHandle>,
+    oplock_level: OplockLevel,
 }
 
 impl<T> File<T>
@@ -1224,6 +2642,12 @@ where
         self.resilient_handle
     }
 
+    /// Returns the oplock level the server granted when the file was opened.
+    #[must_use]
+    pub fn oplock_level(&self) -> OplockLevel {
+        self.oplock_level
+    }
+
     /// Returns the wrapped tree-connected connection.
     #[must_use]
     pub fn connection(&self) -> &Connection<T, TreeConnected> {
@@ -1244,7 +2668,7 @@ where
 
         while offset < metadata.size {
             let remaining = metadata.size - offset;
-            let chunk_len = remaining.min(MAX_IO_CHUNK_SIZE as u64) as u32;
+            let chunk_len = remaining.min(u64::from(self.share.max_read_size())) as u32;
             let response = self
                 .share
                 .connection
@@ -1272,14 +2696,10 @@ where
     pub async fn write_all(&mut self, data: &[u8]) -> Result<(), CoreError> {
         let mut offset = 0u64;
         while (offset as usize) < data.len() {
-            let chunk_end = (offset as usize + MAX_IO_CHUNK_SIZE).min(data.len());
+            let chunk_end =
+                (offset as usize + self.share.max_write_size() as usize).min(data.len());
             self.share
-                .connection
-                .write(&WriteRequest::for_file(
-                    self.file_id,
-                    offset,
-                    data[offset as usize..chunk_end].to_vec(),
-                ))
+                .write_chunk(self.file_id, offset, &data[offset as usize..chunk_end])
                 .await?;
             offset = chunk_end as u64;
         }
@@ -1304,11 +2724,125 @@ where
         self.flush().await
     }
 
+    /// Truncates or extends the open file to exactly `size` bytes.
+    ///
+    /// Call this after overwriting an existing file with shorter content so
+    /// no stale tail bytes remain. Extended regions read back as zeros.
+    pub async fn set_end_of_file(&mut self, size: u64) -> Result<(), CoreError> {
+        let _ = self
+            .share
+            .connection
+            .set_info(&SetInfoRequest::for_file_info(
+                self.file_id,
+                FileInfoClass::EndOfFileInformation,
+                EndOfFileInformation { end_of_file: size }.encode(),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    /// Asks the server to reserve `size` bytes of storage for the open file.
+    ///
+    /// This does not change the file's length. Servers round the value to
+    /// their cluster size, and a value below the current length truncates.
+    pub async fn set_allocation_size(&mut self, size: u64) -> Result<(), CoreError> {
+        let _ = self
+            .share
+            .connection
+            .set_info(&SetInfoRequest::for_file_info(
+                self.file_id,
+                FileInfoClass::AllocationInformation,
+                AllocationInformation {
+                    allocation_size: size,
+                }
+                .encode(),
+            ))
+            .await?;
+        Ok(())
+    }
+
+    /// Takes a byte-range lock on the open file without waiting for conflicting locks.
+    ///
+    /// Exclusive locks block other handles from reading or writing the range;
+    /// shared locks only block writes. A conflict returns
+    /// [`CoreError::LockNotGranted`].
+    pub async fn lock_range(
+        &mut self,
+        offset: u64,
+        length: u64,
+        exclusive: bool,
+    ) -> Result<(), CoreError> {
+        let mode = if exclusive {
+            LockFlags::EXCLUSIVE_LOCK
+        } else {
+            LockFlags::SHARED_LOCK
+        };
+        self.send_lock(offset, length, mode | LockFlags::FAIL_IMMEDIATELY)
+            .await
+            .map_err(|error| match error {
+                CoreError::UnexpectedStatus { status, .. }
+                    if status == NtStatus::LOCK_NOT_GRANTED.to_u32()
+                        || status == NtStatus::FILE_LOCK_CONFLICT.to_u32() =>
+                {
+                    CoreError::LockNotGranted { offset, length }
+                }
+                error => error,
+            })
+    }
+
+    /// Releases a byte-range lock previously taken with [`File::lock_range`].
+    ///
+    /// The offset and length must match the locked range exactly.
+    pub async fn unlock_range(&mut self, offset: u64, length: u64) -> Result<(), CoreError> {
+        self.send_lock(offset, length, LockFlags::UNLOCK).await
+    }
+
+    async fn send_lock(
+        &mut self,
+        offset: u64,
+        length: u64,
+        flags: LockFlags,
+    ) -> Result<(), CoreError> {
+        let request = LockRequest::for_file(
+            self.file_id,
+            vec![LockElement {
+                offset,
+                length,
+                flags,
+            }],
+        );
+        let _ = self.share.connection.lock(&request).await?;
+        Ok(())
+    }
+
     /// Queries metadata for the open file handle.
     pub async fn stat(&mut self) -> Result<FileMetadata, CoreError> {
         self.share.stat_by_id(self.file_id).await
     }
 
+    /// Reads the owner, group, and DACL of the open file.
+    pub async fn security_descriptor(&mut self) -> Result<SecurityDescriptor, CoreError> {
+        self.share.security_descriptor_by_id(self.file_id).await
+    }
+
+    /// Sets the creation, last-access, and last-write times of the open file.
+    ///
+    /// A `None` leaves that timestamp unchanged. The handle must have been
+    /// opened with write access.
+    pub async fn set_times(
+        &mut self,
+        created: Option<SystemTime>,
+        accessed: Option<SystemTime>,
+        modified: Option<SystemTime>,
+    ) -> Result<(), CoreError> {
+        self.share
+            .set_basic_info_by_id(
+                self.file_id,
+                basic_info_for_times(created, accessed, modified),
+            )
+            .await
+    }
+
     /// Requests handle resiliency for the current file and stores the result for future reconnects.
     pub async fn request_resiliency(&mut self, timeout: u32) -> Result<ResilientHandle, CoreError> {
         let resilient = self
@@ -1367,6 +2901,32 @@ fn normalize_share_name(share: &str) -> Result<String, CoreError> {
     Ok(share.to_owned())
 }
 
+fn resolve_share_name(server: &str, share: &str) -> Result<String, CoreError> {
+    if share.starts_with(r"\\") || share.starts_with("//") {
+        // `\\IPC$` style inputs have no share component and fall through to
+        // the bare-name normalization below.
+        if let Ok(unc) = UncPath::parse(share) {
+            ensure_unc_server(server, &unc)?;
+            if !unc.path().is_empty() {
+                return Err(CoreError::PathInvalid(
+                    "share UNC must not include a path below the share",
+                ));
+            }
+            return normalize_share_name(unc.share());
+        }
+    }
+    normalize_share_name(share)
+}
+
+fn ensure_unc_server(server: &str, unc: &UncPath) -> Result<(), CoreError> {
+    if !unc.server().eq_ignore_ascii_case(server) {
+        return Err(CoreError::InvalidInput(
+            "UNC path names a different server than the client",
+        ));
+    }
+    Ok(())
+}
+
 fn normalize_share_path(path: &str) -> Result<String, CoreError> {
     if path.contains('\0') {
         return Err(CoreError::PathInvalid("path must not contain NUL bytes"));
@@ -1429,6 +2989,9 @@ fn desired_access_mask(options: &OpenOptions) -> u32 {
         desired_access |=
             FILE_WRITE_DATA | FILE_APPEND_DATA | FILE_WRITE_EA | FILE_WRITE_ATTRIBUTES;
     }
+    if options.delete_on_close {
+        desired_access |= DELETE;
+    }
     desired_access
 }
 
@@ -1450,6 +3013,18 @@ fn dialect_supports_durable_v2(dialect: Dialect) -> bool {
     matches!(dialect, Dialect::Smb300 | Dialect::Smb302 | Dialect::Smb311)
 }
 
+fn not_found_error(error: CoreError, normalized_path: &str) -> CoreError {
+    match error {
+        CoreError::UnexpectedStatus { status, .. }
+            if status == NtStatus::OBJECT_NAME_NOT_FOUND.to_u32()
+                || status == NtStatus::OBJECT_PATH_NOT_FOUND.to_u32() =>
+        {
+            CoreError::NotFound(normalized_path.to_owned())
+        }
+        error => error,
+    }
+}
+
 fn metadata_from_info(
     basic: FileBasicInformation,
     standard: FileStandardInformation,
@@ -1489,26 +3064,40 @@ fn directory_entry_from_info(entry: DirectoryInformationEntry) -> DirectoryEntry
 }
 
 fn system_time_from_windows_ticks(value: u64) -> Option<SystemTime> {
-    if value == 0 {
-        return None;
-    }
+    (value != 0).then(|| filetime_to_system_time(value))
+}
 
-    let unix_ticks = value.checked_sub(SEC_TO_UNIX_EPOCH * WINDOWS_TICK)?;
-    Some(UNIX_EPOCH + Duration::from_nanos(unix_ticks.saturating_mul(100)))
+fn basic_info_for_times(
+    created: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+) -> FileBasicInformation {
+    // A zero FILETIME in SET_INFO tells the server to keep the current value.
+    let ticks = |time: Option<SystemTime>| time.map_or(0, system_time_to_filetime);
+    FileBasicInformation {
+        creation_time: ticks(created),
+        last_access_time: ticks(accessed),
+        last_write_time: ticks(modified),
+        change_time: 0,
+        file_attributes: FileAttributes::empty(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::VecDeque;
+    use std::time::{Duration, UNIX_EPOCH};
 
     use async_trait::async_trait;
     use smolder_proto::smb::netbios::SessionMessage;
     use smolder_proto::smb::smb2::{
-        CloseResponse, Command, CreateDisposition, CreateResponse, Dialect, FileAttributes, FileId,
-        FlushResponse, GlobalCapabilities, Header, MessageId, NegotiateRequest, NegotiateResponse,
-        OplockLevel, QueryDirectoryResponse, QueryInfoResponse, SessionFlags, SessionSetupResponse,
+        CloseRequest, CloseResponse, Command, CreateDisposition, CreateOptions, CreateRequest,
+        CreateResponse, Dialect, EchoResponse, FileAttributes, FileId, FileSystemInfoClass,
+        FlushResponse, GlobalCapabilities, Header, InfoClass, LockFlags, LockRequest, LockResponse,
+        MessageId, NegotiateRequest, NegotiateResponse, OplockLevel, QueryDirectoryResponse,
+        QueryInfoResponse, ReadRequest, RequestedOplockLevel, SessionFlags, SessionSetupResponse,
         ShareFlags, ShareType, SigningMode, TreeCapabilities, TreeConnectRequest,
-        TreeConnectResponse, TreeId, WriteResponse,
+        TreeConnectResponse, TreeId, WriteRequest, WriteResponse,
     };
     use smolder_proto::smb::status::NtStatus;
 
@@ -1517,18 +3106,22 @@ mod tests {
     #[cfg(feature = "kerberos-api")]
     use crate::auth::{KerberosCredentials, KerberosTarget};
     use crate::client::Connection;
+    use crate::error::CoreError;
+    use crate::mock_server::MockServer;
     use crate::transport::Transport;
-    use crate::transport::{TransportProtocol, TransportTarget};
+    use crate::transport::{StreamTransport, TransportProtocol, TransportTarget};
 
     use super::{
-        normalize_pipe_name, normalize_share_name, normalize_share_path, Client, ClientBuilder,
-        FileMetadata, OpenOptions, Share,
+        normalize_pipe_name, normalize_share_name, normalize_share_path, resolve_share_name,
+        Client, ClientBuilder, FileMetadata, OpenOptions, PoolKey, RetryPolicy, Share, SharePool,
+        SharedShare,
     };
 
     #[derive(Debug)]
     struct ScriptedTransport {
         reads: VecDeque<Vec<u8>>,
         writes: Vec<Vec<u8>>,
+        shutdown_error: Option<std::io::ErrorKind>,
     }
 
     impl ScriptedTransport {
@@ -1536,6 +3129,7 @@ mod tests {
             Self {
                 reads: reads.into(),
                 writes: Vec::new(),
+                shutdown_error: None,
             }
         }
     }
@@ -1552,6 +3146,13 @@ mod tests {
                 std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "no scripted response")
             })
         }
+
+        async fn shutdown(&mut self) -> std::io::Result<()> {
+            match self.shutdown_error {
+                Some(kind) => Err(std::io::Error::new(kind, "scripted shutdown failure")),
+                None => Ok(()),
+            }
+        }
     }
 
     fn response_frame(
@@ -1575,6 +3176,47 @@ mod tests {
             .expect("response should frame")
     }
 
+    fn create_frame(
+        message_id: u64,
+        file_id: FileId,
+        file_attributes: FileAttributes,
+        size: u64,
+    ) -> Vec<u8> {
+        let create_response = CreateResponse {
+            oplock_level: OplockLevel::None,
+            file_attributes,
+            allocation_size: size,
+            end_of_file: size,
+            file_id,
+            create_contexts: Vec::new(),
+        };
+        response_frame(
+            Command::Create,
+            NtStatus::SUCCESS.to_u32(),
+            message_id,
+            11,
+            7,
+            create_response.encode(),
+        )
+    }
+
+    fn close_frame(message_id: u64, file_attributes: FileAttributes, size: u64) -> Vec<u8> {
+        let close_response = CloseResponse {
+            flags: 0,
+            allocation_size: size,
+            end_of_file: size,
+            file_attributes,
+        };
+        response_frame(
+            Command::Close,
+            NtStatus::SUCCESS.to_u32(),
+            message_id,
+            11,
+            7,
+            close_response.encode(),
+        )
+    }
+
     fn directory_entries_buffer(entries: &[(u32, FileAttributes, u64, &str)]) -> Vec<u8> {
         let mut buffer = Vec::new();
 
@@ -1608,7 +3250,36 @@ mod tests {
         buffer
     }
 
+    fn outbound_requests<R>(
+        share: Share<ScriptedTransport>,
+        command: Command,
+        decode: fn(&[u8]) -> Result<R, smolder_proto::smb::ProtocolError>,
+    ) -> Vec<R> {
+        share
+            .connection
+            .into_transport()
+            .writes
+            .iter()
+            .map(|frame| SessionMessage::decode(frame).expect("frame should decode"))
+            .filter(|frame| {
+                Header::decode(&frame.payload[..Header::LEN])
+                    .expect("header should decode")
+                    .command
+                    == command
+            })
+            .map(|frame| decode(&frame.payload[Header::LEN..]).expect("request should decode"))
+            .collect()
+    }
+
     async fn build_share(reads: Vec<Vec<u8>>) -> Share<ScriptedTransport> {
+        build_share_with_io_limits(65_536, 65_536, reads).await
+    }
+
+    async fn build_share_with_io_limits(
+        max_read_size: u32,
+        max_write_size: u32,
+        reads: Vec<Vec<u8>>,
+    ) -> Share<ScriptedTransport> {
         let negotiate_response = NegotiateResponse {
             security_mode: SigningMode::ENABLED,
             dialect_revision: Dialect::Smb302,
@@ -1616,8 +3287,8 @@ mod tests {
             server_guid: *b"server-guid-0001",
             capabilities: GlobalCapabilities::LARGE_MTU,
             max_transact_size: 65_536,
-            max_read_size: 65_536,
-            max_write_size: 65_536,
+            max_read_size,
+            max_write_size,
             system_time: 1,
             server_start_time: 1,
             security_buffer: Vec::new(),
@@ -1726,6 +3397,21 @@ mod tests {
         assert_eq!(config.client_guid(), b"0123456789abcdef");
     }
 
+    #[test]
+    fn builder_threads_transport_timeouts_into_session_config() {
+        let client = ClientBuilder::new("server")
+            .with_connect_timeout(Duration::from_secs(5))
+            .with_read_timeout(Duration::from_secs(30))
+            .with_ntlm_credentials(NtlmCredentials::new("user", "pass"))
+            .build()
+            .expect("builder should produce a client");
+
+        let timeouts = client.session_config().timeouts();
+        assert_eq!(timeouts.connect_timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(timeouts.read_timeout(), Some(Duration::from_secs(30)));
+        assert_eq!(timeouts.write_timeout(), None);
+    }
+
     #[test]
     fn builder_can_override_transport_target() {
         let client = ClientBuilder::new("server")
@@ -1800,7 +3486,28 @@ mod tests {
     }
 
     #[test]
-    fn open_options_map_to_expected_create_request() {
+    fn resolve_share_name_accepts_unc_share_roots() {
+        assert_eq!(
+            resolve_share_name("server", r"\\SERVER\data").expect("UNC root should resolve"),
+            "data"
+        );
+        assert_eq!(
+            resolve_share_name("server", "//server/data/").expect("slash UNC should resolve"),
+            "data"
+        );
+        assert_eq!(
+            resolve_share_name("server", r"\\IPC$\").expect("bare share should resolve"),
+            "IPC$"
+        );
+        assert!(matches!(
+            resolve_share_name("server", r"\\other\data"),
+            Err(crate::error::CoreError::InvalidInput(_))
+        ));
+        assert!(resolve_share_name("server", r"\\server\data\dir").is_err());
+    }
+
+    #[test]
+    fn open_options_map_to_expected_create_request() {
         let request = OpenOptions::new()
             .write(true)
             .create(true)
@@ -1836,18 +3543,17 @@ mod tests {
         assert!(normalize_pipe_name("\0bad").is_err());
     }
 
+    #[tokio::test]
+    async fn share_reports_negotiated_dialect() {
+        let share = build_share(Vec::new()).await;
+        assert_eq!(share.dialect(), Dialect::Smb302);
+    }
+
     #[tokio::test]
     async fn share_read_queries_metadata_and_reads_contents() {
-        let create_response = CreateResponse {
-            oplock_level: OplockLevel::None,
-            file_attributes: FileAttributes::ARCHIVE,
-            allocation_size: 5,
-            end_of_file: 5,
-            file_id: FileId {
-                persistent: 1,
-                volatile: 2,
-            },
-            create_contexts: Vec::new(),
+        let file_id = FileId {
+            persistent: 1,
+            volatile: 2,
         };
         let basic = QueryInfoResponse {
             output_buffer: {
@@ -1880,14 +3586,7 @@ mod tests {
         };
 
         let mut share = build_share(vec![
-            response_frame(
-                Command::Create,
-                NtStatus::SUCCESS.to_u32(),
-                3,
-                11,
-                7,
-                create_response.encode(),
-            ),
+            create_frame(3, file_id, FileAttributes::ARCHIVE, 5),
             response_frame(
                 Command::QueryInfo,
                 NtStatus::SUCCESS.to_u32(),
@@ -1912,39 +3611,190 @@ mod tests {
                 7,
                 read_response.encode(),
             ),
+            close_frame(7, FileAttributes::ARCHIVE, 5),
+        ])
+        .await;
+
+        let data = share.read("notes.txt").await.expect("read should succeed");
+        assert_eq!(data, b"hello");
+    }
+
+    #[tokio::test]
+    async fn share_read_splits_reads_at_negotiated_max_read_size() {
+        let file_id = FileId {
+            persistent: 1,
+            volatile: 2,
+        };
+        let basic = QueryInfoResponse {
+            output_buffer: {
+                let mut buffer = Vec::new();
+                buffer.extend_from_slice(&1u64.to_le_bytes());
+                buffer.extend_from_slice(&2u64.to_le_bytes());
+                buffer.extend_from_slice(&3u64.to_le_bytes());
+                buffer.extend_from_slice(&4u64.to_le_bytes());
+                buffer.extend_from_slice(&FileAttributes::ARCHIVE.bits().to_le_bytes());
+                buffer.extend_from_slice(&0u32.to_le_bytes());
+                buffer
+            },
+        };
+        let standard = QueryInfoResponse {
+            output_buffer: {
+                let mut buffer = Vec::new();
+                buffer.extend_from_slice(&5u64.to_le_bytes());
+                buffer.extend_from_slice(&5u64.to_le_bytes());
+                buffer.extend_from_slice(&1u32.to_le_bytes());
+                buffer.push(0);
+                buffer.push(0);
+                buffer.extend_from_slice(&0u16.to_le_bytes());
+                buffer
+            },
+        };
+        let read_response = |data: &[u8]| smolder_proto::smb::smb2::ReadResponse {
+            data_remaining: 0,
+            flags: smolder_proto::smb::smb2::ReadResponseFlags::empty(),
+            data: data.to_vec(),
+        };
+
+        let mut share = build_share_with_io_limits(
+            4,
+            65_536,
+            vec![
+                create_frame(3, file_id, FileAttributes::ARCHIVE, 5),
+                response_frame(
+                    Command::QueryInfo,
+                    NtStatus::SUCCESS.to_u32(),
+                    4,
+                    11,
+                    7,
+                    basic.encode(),
+                ),
+                response_frame(
+                    Command::QueryInfo,
+                    NtStatus::SUCCESS.to_u32(),
+                    5,
+                    11,
+                    7,
+                    standard.encode(),
+                ),
+                response_frame(
+                    Command::Read,
+                    NtStatus::SUCCESS.to_u32(),
+                    6,
+                    11,
+                    7,
+                    read_response(b"hell").encode(),
+                ),
+                response_frame(
+                    Command::Read,
+                    NtStatus::SUCCESS.to_u32(),
+                    7,
+                    11,
+                    7,
+                    read_response(b"o").encode(),
+                ),
+                close_frame(8, FileAttributes::ARCHIVE, 5),
+            ],
+        )
+        .await;
+
+        let data = share.read("notes.txt").await.expect("read should succeed");
+        assert_eq!(data, b"hello");
+
+        let reads = outbound_requests(share, Command::Read, ReadRequest::decode);
+        assert_eq!(reads.len(), 2);
+        assert_eq!((reads[0].offset, reads[0].length), (0, 4));
+        assert_eq!((reads[1].offset, reads[1].length), (4, 1));
+    }
+
+    #[tokio::test]
+    async fn share_read_closes_handle_when_read_fails() {
+        let file_id = FileId {
+            persistent: 1,
+            volatile: 2,
+        };
+        let basic = QueryInfoResponse {
+            output_buffer: {
+                let mut buffer = Vec::new();
+                buffer.extend_from_slice(&1u64.to_le_bytes());
+                buffer.extend_from_slice(&2u64.to_le_bytes());
+                buffer.extend_from_slice(&3u64.to_le_bytes());
+                buffer.extend_from_slice(&4u64.to_le_bytes());
+                buffer.extend_from_slice(&FileAttributes::ARCHIVE.bits().to_le_bytes());
+                buffer.extend_from_slice(&0u32.to_le_bytes());
+                buffer
+            },
+        };
+        let standard = QueryInfoResponse {
+            output_buffer: {
+                let mut buffer = Vec::new();
+                buffer.extend_from_slice(&5u64.to_le_bytes());
+                buffer.extend_from_slice(&5u64.to_le_bytes());
+                buffer.extend_from_slice(&1u32.to_le_bytes());
+                buffer.push(0);
+                buffer.push(0);
+                buffer.extend_from_slice(&0u16.to_le_bytes());
+                buffer
+            },
+        };
+
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::ARCHIVE, 5),
             response_frame(
-                Command::Close,
+                Command::QueryInfo,
                 NtStatus::SUCCESS.to_u32(),
+                4,
+                11,
                 7,
+                basic.encode(),
+            ),
+            response_frame(
+                Command::QueryInfo,
+                NtStatus::SUCCESS.to_u32(),
+                5,
                 11,
                 7,
-                CloseResponse {
-                    flags: 0,
-                    allocation_size: 5,
-                    end_of_file: 5,
-                    file_attributes: FileAttributes::ARCHIVE,
-                }
-                .encode(),
+                standard.encode(),
+            ),
+            response_frame(
+                Command::Read,
+                NtStatus::ACCESS_DENIED.to_u32(),
+                6,
+                11,
+                7,
+                Vec::new(),
             ),
+            close_frame(7, FileAttributes::ARCHIVE, 5),
         ])
         .await;
 
-        let data = share.read("notes.txt").await.expect("read should succeed");
-        assert_eq!(data, b"hello");
+        let error = share
+            .read("notes.txt")
+            .await
+            .expect_err("read failure should be returned");
+        assert!(matches!(
+            error,
+            crate::error::CoreError::UnexpectedStatus {
+                command: Command::Read,
+                ..
+            }
+        ));
+
+        let closes = outbound_requests(share, Command::Close, CloseRequest::decode);
+        assert_eq!(closes.len(), 1);
+        assert_eq!(
+            closes[0].file_id,
+            FileId {
+                persistent: 1,
+                volatile: 2,
+            }
+        );
     }
 
     #[tokio::test]
     async fn share_get_alias_reads_contents() {
-        let create_response = CreateResponse {
-            oplock_level: OplockLevel::None,
-            file_attributes: FileAttributes::ARCHIVE,
-            allocation_size: 5,
-            end_of_file: 5,
-            file_id: FileId {
-                persistent: 1,
-                volatile: 2,
-            },
-            create_contexts: Vec::new(),
+        let file_id = FileId {
+            persistent: 1,
+            volatile: 2,
         };
         let basic = QueryInfoResponse {
             output_buffer: {
@@ -1977,14 +3827,7 @@ mod tests {
         };
 
         let mut share = build_share(vec![
-            response_frame(
-                Command::Create,
-                NtStatus::SUCCESS.to_u32(),
-                3,
-                11,
-                7,
-                create_response.encode(),
-            ),
+            create_frame(3, file_id, FileAttributes::ARCHIVE, 5),
             response_frame(
                 Command::QueryInfo,
                 NtStatus::SUCCESS.to_u32(),
@@ -2009,20 +3852,7 @@ mod tests {
                 7,
                 read_response.encode(),
             ),
-            response_frame(
-                Command::Close,
-                NtStatus::SUCCESS.to_u32(),
-                7,
-                11,
-                7,
-                CloseResponse {
-                    flags: 0,
-                    allocation_size: 5,
-                    end_of_file: 5,
-                    file_attributes: FileAttributes::ARCHIVE,
-                }
-                .encode(),
-            ),
+            close_frame(7, FileAttributes::ARCHIVE, 5),
         ])
         .await;
 
@@ -2032,27 +3862,13 @@ mod tests {
 
     #[tokio::test]
     async fn share_put_alias_writes_contents() {
-        let create_response = CreateResponse {
-            oplock_level: OplockLevel::None,
-            file_attributes: FileAttributes::ARCHIVE,
-            allocation_size: 5,
-            end_of_file: 5,
-            file_id: FileId {
-                persistent: 1,
-                volatile: 2,
-            },
-            create_contexts: Vec::new(),
+        let file_id = FileId {
+            persistent: 1,
+            volatile: 2,
         };
 
         let mut share = build_share(vec![
-            response_frame(
-                Command::Create,
-                NtStatus::SUCCESS.to_u32(),
-                3,
-                11,
-                7,
-                create_response.encode(),
-            ),
+            create_frame(3, file_id, FileAttributes::ARCHIVE, 5),
             response_frame(
                 Command::Write,
                 NtStatus::SUCCESS.to_u32(),
@@ -2069,27 +3885,99 @@ mod tests {
                 7,
                 FlushResponse.encode(),
             ),
+            close_frame(6, FileAttributes::ARCHIVE, 5),
+        ])
+        .await;
+
+        share
+            .put("notes.txt", b"hello")
+            .await
+            .expect("put should succeed");
+    }
+
+    #[tokio::test]
+    async fn share_write_splits_writes_at_negotiated_max_write_size() {
+        let file_id = FileId {
+            persistent: 1,
+            volatile: 2,
+        };
+
+        let mut share = build_share_with_io_limits(
+            65_536,
+            4,
+            vec![
+                create_frame(3, file_id, FileAttributes::ARCHIVE, 0),
+                response_frame(
+                    Command::Write,
+                    NtStatus::SUCCESS.to_u32(),
+                    4,
+                    11,
+                    7,
+                    WriteResponse { count: 4 }.encode(),
+                ),
+                response_frame(
+                    Command::Write,
+                    NtStatus::SUCCESS.to_u32(),
+                    5,
+                    11,
+                    7,
+                    WriteResponse { count: 1 }.encode(),
+                ),
+                response_frame(
+                    Command::Flush,
+                    NtStatus::SUCCESS.to_u32(),
+                    6,
+                    11,
+                    7,
+                    FlushResponse.encode(),
+                ),
+                close_frame(7, FileAttributes::ARCHIVE, 5),
+            ],
+        )
+        .await;
+
+        share
+            .write("notes.txt", b"hello")
+            .await
+            .expect("write should succeed");
+
+        let writes = outbound_requests(share, Command::Write, WriteRequest::decode);
+        assert_eq!(writes.len(), 2);
+        assert_eq!((writes[0].offset, writes[1].offset), (0, 4));
+        assert_eq!(writes[0].data, b"hell");
+        assert_eq!(writes[1].data, b"o");
+    }
+
+    #[tokio::test]
+    async fn share_write_rejects_short_write_acknowledgement() {
+        let file_id = FileId {
+            persistent: 1,
+            volatile: 2,
+        };
+
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::ARCHIVE, 0),
             response_frame(
-                Command::Close,
+                Command::Write,
                 NtStatus::SUCCESS.to_u32(),
-                6,
+                4,
                 11,
                 7,
-                CloseResponse {
-                    flags: 0,
-                    allocation_size: 5,
-                    end_of_file: 5,
-                    file_attributes: FileAttributes::ARCHIVE,
-                }
-                .encode(),
+                WriteResponse { count: 3 }.encode(),
             ),
         ])
         .await;
 
-        share
-            .put("notes.txt", b"hello")
+        let error = share
+            .write("notes.txt", b"hello")
             .await
-            .expect("put should succeed");
+            .expect_err("short write should be rejected");
+        assert!(matches!(
+            error,
+            crate::error::CoreError::InvalidResponse(
+                "server acknowledged fewer bytes than were written"
+            )
+        ));
     }
 
     #[tokio::test]
@@ -2179,33 +4067,915 @@ mod tests {
         assert!(metadata.is_file());
     }
 
+    fn dfs_referral_buffer(dfs_path: &str, targets: &[&str]) -> Vec<u8> {
+        let dfs_path = smolder_proto::smb::smb2::utf16le(dfs_path);
+        let header_len = 8 + 24 * targets.len();
+        let mut strings = dfs_path.clone();
+        strings.extend_from_slice(&[0, 0]);
+        let target_offsets = targets
+            .iter()
+            .map(|target| {
+                let offset = header_len + strings.len();
+                strings.extend_from_slice(&smolder_proto::smb::smb2::utf16le(target));
+                strings.extend_from_slice(&[0, 0]);
+                offset
+            })
+            .collect::<Vec<_>>();
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&(targets.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&0x0000_0002u32.to_le_bytes());
+        for (index, target_offset) in target_offsets.iter().enumerate() {
+            let entry_start = 8 + 24 * index;
+            bytes.extend_from_slice(&4u16.to_le_bytes());
+            bytes.extend_from_slice(&24u16.to_le_bytes());
+            bytes.extend_from_slice(&0u16.to_le_bytes());
+            bytes.extend_from_slice(&0u16.to_le_bytes());
+            bytes.extend_from_slice(&300u32.to_le_bytes());
+            bytes.extend_from_slice(&((header_len - entry_start) as u16).to_le_bytes());
+            bytes.extend_from_slice(&0u16.to_le_bytes());
+            bytes.extend_from_slice(&((target_offset - entry_start) as u16).to_le_bytes());
+            bytes.extend_from_slice(&[0u8; 6]);
+        }
+        bytes.extend_from_slice(&strings);
+        bytes
+    }
+
     #[tokio::test]
-    async fn share_list_decodes_directory_entries_and_filters_dot_entries() {
-        let create_response = CreateResponse {
-            oplock_level: OplockLevel::None,
-            file_attributes: FileAttributes::DIRECTORY,
-            allocation_size: 0,
-            end_of_file: 0,
-            file_id: FileId {
-                persistent: 10,
-                volatile: 20,
-            },
-            create_contexts: Vec::new(),
-        };
-        let first_page = QueryDirectoryResponse {
-            output_buffer: directory_entries_buffer(&[
-                (1, FileAttributes::DIRECTORY, 0, "."),
-                (2, FileAttributes::ARCHIVE, 5, "notes.txt"),
-            ]),
-        };
-        let second_page = QueryDirectoryResponse {
-            output_buffer: directory_entries_buffer(&[
-                (3, FileAttributes::DIRECTORY, 0, ".."),
-                (4, FileAttributes::DIRECTORY, 0, "nested"),
+    async fn share_resolve_dfs_returns_each_target_with_the_remaining_path() {
+        let share = build_share(vec![response_frame(
+            Command::Ioctl,
+            NtStatus::SUCCESS.to_u32(),
+            3,
+            11,
+            7,
+            smolder_proto::smb::smb2::IoctlResponse {
+                ctl_code: smolder_proto::smb::smb2::CtlCode::FSCTL_DFS_GET_REFERRALS,
+                file_id: FileId::NONE,
+                input: Vec::new(),
+                output: dfs_referral_buffer(
+                    r"\\corp\dfs\team",
+                    &[r"\\fs1\team", r"\\fs2\team-replica", r"\\fs1\team"],
+                ),
+                flags: 0,
+            }
+            .encode(),
+        )])
+        .await;
+        let mut share = share;
+
+        let targets = share
+            .resolve_dfs("//corp/dfs/team/reports/q3.xlsx")
+            .await
+            .expect("DFS referral should resolve");
+        assert_eq!(
+            targets,
+            vec![
+                r"\\fs1\team\reports\q3.xlsx".to_owned(),
+                r"\\fs2\team-replica\reports\q3.xlsx".to_owned(),
+            ]
+        );
+
+        let requests = outbound_requests(
+            share,
+            Command::Ioctl,
+            smolder_proto::smb::smb2::IoctlRequest::decode,
+        );
+        assert_eq!(requests.len(), 1);
+        let referral_request =
+            smolder_proto::smb::smb2::DfsReferralRequest::decode(&requests[0].input)
+                .expect("referral request should decode");
+        assert_eq!(
+            referral_request.request_file_name,
+            r"\\corp\dfs\team\reports\q3.xlsx"
+        );
+    }
+
+    #[tokio::test]
+    async fn share_security_descriptor_queries_owner_group_and_dacl() {
+        use smolder_proto::smb::security::{
+            Ace, AceFlags, AceType, Acl, SecurityDescriptor, SecurityDescriptorControl, Sid,
+        };
+
+        let system = Sid {
+            revision: 1,
+            identifier_authority: [0, 0, 0, 0, 0, 5],
+            sub_authorities: vec![18],
+        };
+        let descriptor = SecurityDescriptor {
+            control: SecurityDescriptorControl::SELF_RELATIVE
+                | SecurityDescriptorControl::DACL_PRESENT,
+            owner: Some(system.clone()),
+            group: Some(system.clone()),
+            sacl: None,
+            dacl: Some(Acl {
+                revision: 2,
+                aces: vec![Ace::new(
+                    AceType::AccessAllowed,
+                    AceFlags::empty(),
+                    0x001f_01ff,
+                    system,
+                )],
+            }),
+        };
+        let mut share = build_share(vec![
+            create_frame(
+                3,
+                FileId {
+                    persistent: 92,
+                    volatile: 93,
+                },
+                FileAttributes::ARCHIVE,
+                0,
+            ),
+            response_frame(
+                Command::QueryInfo,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                11,
+                7,
+                QueryInfoResponse {
+                    output_buffer: descriptor.encode().expect("descriptor should encode"),
+                }
+                .encode(),
+            ),
+            close_frame(5, FileAttributes::ARCHIVE, 0),
+        ])
+        .await;
+
+        let decoded = share
+            .security_descriptor("docs/report.txt")
+            .await
+            .expect("security query should succeed");
+        assert_eq!(decoded, descriptor);
+
+        let requests = outbound_requests(
+            share,
+            Command::QueryInfo,
+            smolder_proto::smb::smb2::QueryInfoRequest::decode,
+        );
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].info_class, InfoClass::Security);
+        assert_eq!(requests[0].additional_information, 0x0000_0007);
+    }
+
+    #[tokio::test]
+    async fn share_fs_info_reports_volume_size_and_caller_free_space() {
+        let mut share = build_share(vec![
+            create_frame(
+                3,
+                FileId {
+                    persistent: 94,
+                    volatile: 95,
+                },
+                FileAttributes::DIRECTORY,
+                0,
+            ),
+            response_frame(
+                Command::QueryInfo,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                11,
+                7,
+                QueryInfoResponse {
+                    output_buffer: smolder_proto::smb::smb2::FileFsFullSizeInformation {
+                        total_allocation_units: 1_000,
+                        caller_available_allocation_units: 250,
+                        actual_available_allocation_units: 300,
+                        sectors_per_allocation_unit: 8,
+                        bytes_per_sector: 512,
+                    }
+                    .encode(),
+                }
+                .encode(),
+            ),
+            close_frame(5, FileAttributes::DIRECTORY, 0),
+        ])
+        .await;
+
+        let info = share.fs_info().await.expect("fs info should succeed");
+        assert_eq!(info.total_bytes, 4_096_000);
+        assert_eq!(info.free_bytes, 1_024_000);
+        assert_eq!(info.free_allocation_units, 250);
+        assert_eq!(info.bytes_per_sector, 512);
+
+        let requests = outbound_requests(
+            share,
+            Command::QueryInfo,
+            smolder_proto::smb::smb2::QueryInfoRequest::decode,
+        );
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].info_class,
+            InfoClass::FileSystem(FileSystemInfoClass::FullSizeInformation)
+        );
+        assert_eq!(
+            requests[0].file_id,
+            FileId {
+                persistent: 94,
+                volatile: 95
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn share_volume_info_reports_label_and_filesystem_type() {
+        use smolder_proto::smb::smb2::{
+            FileFsAttributeInformation, FileFsVolumeInformation, FileSystemAttributes,
+        };
+
+        let mut share = build_share(vec![
+            create_frame(
+                3,
+                FileId {
+                    persistent: 96,
+                    volatile: 97,
+                },
+                FileAttributes::DIRECTORY,
+                0,
+            ),
+            response_frame(
+                Command::QueryInfo,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                11,
+                7,
+                QueryInfoResponse {
+                    output_buffer: FileFsVolumeInformation {
+                        volume_creation_time: 0,
+                        volume_serial_number: 0x1234_abcd,
+                        supports_objects: false,
+                        volume_label: "Backup".to_string(),
+                    }
+                    .encode(),
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::QueryInfo,
+                NtStatus::SUCCESS.to_u32(),
+                5,
+                11,
+                7,
+                QueryInfoResponse {
+                    output_buffer: FileFsAttributeInformation {
+                        file_system_attributes: FileSystemAttributes::CASE_PRESERVED_NAMES
+                            | FileSystemAttributes::UNICODE_ON_DISK,
+                        maximum_component_name_length: 255,
+                        file_system_name: "NTFS".to_string(),
+                    }
+                    .encode(),
+                }
+                .encode(),
+            ),
+            close_frame(6, FileAttributes::DIRECTORY, 0),
+        ])
+        .await;
+
+        let info = share
+            .volume_info()
+            .await
+            .expect("volume info should succeed");
+        assert_eq!(info.label, "Backup");
+        assert_eq!(info.serial_number, 0x1234_abcd);
+        assert_eq!(info.created, None);
+        assert_eq!(info.file_system_name, "NTFS");
+        assert_eq!(info.max_component_name_length, 255);
+        assert!(info.preserves_case());
+        assert!(!info.is_case_sensitive());
+
+        let requests = outbound_requests(
+            share,
+            Command::QueryInfo,
+            smolder_proto::smb::smb2::QueryInfoRequest::decode,
+        );
+        let classes = requests
+            .iter()
+            .map(|request| request.info_class)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            classes,
+            vec![
+                InfoClass::FileSystem(FileSystemInfoClass::VolumeInformation),
+                InfoClass::FileSystem(FileSystemInfoClass::AttributeInformation),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn share_list_decodes_directory_entries_and_filters_dot_entries() {
+        let file_id = FileId {
+            persistent: 10,
+            volatile: 20,
+        };
+        let first_page = QueryDirectoryResponse {
+            output_buffer: directory_entries_buffer(&[
+                (1, FileAttributes::DIRECTORY, 0, "."),
+                (2, FileAttributes::ARCHIVE, 5, "notes.txt"),
+            ]),
+        };
+        let second_page = QueryDirectoryResponse {
+            output_buffer: directory_entries_buffer(&[
+                (3, FileAttributes::DIRECTORY, 0, ".."),
+                (4, FileAttributes::DIRECTORY, 0, "nested"),
+            ]),
+        };
+
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::DIRECTORY, 0),
+            response_frame(
+                Command::QueryDirectory,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                11,
+                7,
+                first_page.encode(),
+            ),
+            response_frame(
+                Command::QueryDirectory,
+                NtStatus::SUCCESS.to_u32(),
+                5,
+                11,
+                7,
+                second_page.encode(),
+            ),
+            response_frame(
+                Command::QueryDirectory,
+                NtStatus::NO_MORE_FILES.to_u32(),
+                6,
+                11,
+                7,
+                Vec::new(),
+            ),
+            close_frame(7, FileAttributes::DIRECTORY, 0),
+        ])
+        .await;
+
+        let entries = share.list("\\").await.expect("list should succeed");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "notes.txt");
+        assert!(entries[0].is_file());
+        assert_eq!(entries[0].metadata.size, 5);
+        assert_eq!(entries[1].name, "nested");
+        assert!(entries[1].is_directory());
+    }
+
+    #[tokio::test]
+    async fn share_list_keeps_paging_past_pages_holding_only_dot_entries() {
+        let file_id = FileId {
+            persistent: 10,
+            volatile: 20,
+        };
+        let pages = [
+            directory_entries_buffer(&[
+                (1, FileAttributes::DIRECTORY, 0, "."),
+                (2, FileAttributes::DIRECTORY, 0, ".."),
             ]),
+            directory_entries_buffer(&[(3, FileAttributes::ARCHIVE, 1, "a.txt")]),
+            directory_entries_buffer(&[(4, FileAttributes::ARCHIVE, 2, "b.txt")]),
+        ];
+
+        let mut reads = vec![create_frame(3, file_id, FileAttributes::DIRECTORY, 0)];
+        for (index, output_buffer) in pages.into_iter().enumerate() {
+            reads.push(response_frame(
+                Command::QueryDirectory,
+                NtStatus::SUCCESS.to_u32(),
+                4 + index as u64,
+                11,
+                7,
+                QueryDirectoryResponse { output_buffer }.encode(),
+            ));
+        }
+        reads.push(response_frame(
+            Command::QueryDirectory,
+            NtStatus::NO_MORE_FILES.to_u32(),
+            7,
+            11,
+            7,
+            Vec::new(),
+        ));
+        reads.push(close_frame(8, FileAttributes::DIRECTORY, 0));
+        let mut share = build_share(reads).await;
+
+        let entries = share.list("\\").await.expect("list should succeed");
+        let names = entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a.txt", "b.txt"]);
+
+        let queries = outbound_requests(
+            share,
+            Command::QueryDirectory,
+            smolder_proto::smb::smb2::QueryDirectoryRequest::decode,
+        );
+        assert_eq!(queries.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn share_list_matching_sends_the_pattern_as_the_search_string() {
+        let file_id = FileId {
+            persistent: 10,
+            volatile: 20,
+        };
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::DIRECTORY, 0),
+            response_frame(
+                Command::QueryDirectory,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                11,
+                7,
+                QueryDirectoryResponse {
+                    output_buffer: directory_entries_buffer(&[(
+                        1,
+                        FileAttributes::ARCHIVE,
+                        9,
+                        "server.log",
+                    )]),
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::QueryDirectory,
+                NtStatus::NO_MORE_FILES.to_u32(),
+                5,
+                11,
+                7,
+                Vec::new(),
+            ),
+            close_frame(6, FileAttributes::DIRECTORY, 0),
+        ])
+        .await;
+
+        let error = share
+            .list_matching("logs", "nested\\*.log")
+            .await
+            .expect_err("patterns with separators should be rejected");
+        assert!(matches!(error, crate::error::CoreError::PathInvalid(_)));
+
+        let entries = share
+            .list_matching("logs", "*.log")
+            .await
+            .expect("list should succeed");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "server.log");
+
+        let queries = outbound_requests(
+            share,
+            Command::QueryDirectory,
+            smolder_proto::smb::smb2::QueryDirectoryRequest::decode,
+        );
+        assert_eq!(
+            queries[0].file_name,
+            "*.log"
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn share_list_matching_returns_nothing_when_no_entry_matches() {
+        let file_id = FileId {
+            persistent: 10,
+            volatile: 20,
+        };
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::DIRECTORY, 0),
+            response_frame(
+                Command::QueryDirectory,
+                NtStatus::NO_SUCH_FILE.to_u32(),
+                4,
+                11,
+                7,
+                Vec::new(),
+            ),
+            close_frame(5, FileAttributes::DIRECTORY, 0),
+        ])
+        .await;
+
+        let entries = share
+            .list_matching("logs", "*.missing")
+            .await
+            .expect("a pattern without matches should list nothing");
+        assert!(entries.is_empty());
+
+        let closes = outbound_requests(share, Command::Close, CloseRequest::decode);
+        assert_eq!(closes.len(), 1);
+        assert_eq!(closes[0].file_id, file_id);
+    }
+
+    #[tokio::test]
+    async fn share_walk_descends_to_max_depth_and_skips_reparse_points() {
+        let listings = [
+            directory_entries_buffer(&[
+                (1, FileAttributes::DIRECTORY, 0, "."),
+                (2, FileAttributes::ARCHIVE, 3, "a.txt"),
+                (3, FileAttributes::DIRECTORY, 0, "sub"),
+                (
+                    4,
+                    FileAttributes::DIRECTORY | FileAttributes::REPARSE_POINT,
+                    0,
+                    "link",
+                ),
+            ]),
+            directory_entries_buffer(&[
+                (1, FileAttributes::ARCHIVE, 4, "b.txt"),
+                (2, FileAttributes::DIRECTORY, 0, "deep"),
+            ]),
+        ];
+
+        let mut reads = Vec::new();
+        let mut message_id = 3;
+        for (index, output_buffer) in listings.into_iter().enumerate() {
+            let file_id = FileId {
+                persistent: 10 + index as u64,
+                volatile: 20,
+            };
+            reads.extend([
+                create_frame(message_id, file_id, FileAttributes::DIRECTORY, 0),
+                response_frame(
+                    Command::QueryDirectory,
+                    NtStatus::SUCCESS.to_u32(),
+                    message_id + 1,
+                    11,
+                    7,
+                    QueryDirectoryResponse { output_buffer }.encode(),
+                ),
+                response_frame(
+                    Command::QueryDirectory,
+                    NtStatus::NO_MORE_FILES.to_u32(),
+                    message_id + 2,
+                    11,
+                    7,
+                    Vec::new(),
+                ),
+                close_frame(message_id + 3, FileAttributes::DIRECTORY, 0),
+            ]);
+            message_id += 4;
+        }
+        let mut share = build_share(reads).await;
+
+        let entries = share.walk("\\", 1).await.expect("walk should succeed");
+        let names = entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a.txt", "sub", "link", "sub\\b.txt", "sub\\deep"]);
+
+        let creates = outbound_requests(share, Command::Create, CreateRequest::decode);
+        assert_eq!(creates.len(), 2);
+        assert_eq!(
+            creates[1].name,
+            "sub"
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn share_logoff_tolerates_a_transport_the_server_already_closed() {
+        let teardown = || {
+            vec![
+                response_frame(
+                    Command::TreeDisconnect,
+                    NtStatus::SUCCESS.to_u32(),
+                    3,
+                    11,
+                    7,
+                    smolder_proto::smb::smb2::TreeDisconnectResponse.encode(),
+                ),
+                response_frame(
+                    Command::Logoff,
+                    NtStatus::SUCCESS.to_u32(),
+                    4,
+                    11,
+                    0,
+                    smolder_proto::smb::smb2::LogoffResponse.encode(),
+                ),
+            ]
+        };
+
+        let mut share = build_share(teardown()).await;
+        share.connection.transport_mut().shutdown_error = Some(std::io::ErrorKind::NotConnected);
+        share
+            .logoff()
+            .await
+            .expect("a peer that already hung up should not fail logoff");
+
+        let mut share = build_share(teardown()).await;
+        share.connection.transport_mut().shutdown_error = Some(std::io::ErrorKind::BrokenPipe);
+        let error = share
+            .logoff()
+            .await
+            .expect_err("other shutdown failures should still be reported");
+        assert!(matches!(
+            error,
+            crate::error::CoreError::Io(ref error) if error.kind() == std::io::ErrorKind::BrokenPipe
+        ));
+    }
+
+    #[tokio::test]
+    async fn share_watch_keeps_the_directory_open_across_notifications() {
+        let notifications = vec![
+            smolder_proto::smb::smb2::FileNotifyInformation {
+                action: smolder_proto::smb::smb2::FileNotifyAction::Added,
+                file_name: "report.txt".to_owned(),
+            },
+            smolder_proto::smb::smb2::FileNotifyInformation {
+                action: smolder_proto::smb::smb2::FileNotifyAction::Removed,
+                file_name: "nested\\old.txt".to_owned(),
+            },
+        ];
+        let file_id = FileId {
+            persistent: 90,
+            volatile: 91,
+        };
+        let share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::DIRECTORY, 0),
+            response_frame(
+                Command::ChangeNotify,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                11,
+                7,
+                smolder_proto::smb::smb2::ChangeNotifyResponse::from_notifications(&notifications)
+                    .encode(),
+            ),
+            response_frame(
+                Command::ChangeNotify,
+                NtStatus::NOTIFY_ENUM_DIR.to_u32(),
+                5,
+                11,
+                7,
+                smolder_proto::smb::smb2::ChangeNotifyResponse::from_notifications(&[]).encode(),
+            ),
+            close_frame(6, FileAttributes::DIRECTORY, 0),
+        ])
+        .await;
+
+        let mut watcher = share
+            .watch(
+                "docs",
+                smolder_proto::smb::smb2::CompletionFilter::FILE_NAME,
+                true,
+            )
+            .await
+            .expect("watch should open the directory");
+        assert_eq!(watcher.path(), "docs");
+        assert_eq!(watcher.file_id(), file_id);
+
+        let changes = watcher
+            .next_changes()
+            .await
+            .expect("first notification should succeed");
+        assert_eq!(changes, notifications);
+        let overflow = watcher
+            .next_changes()
+            .await
+            .expect("STATUS_NOTIFY_ENUM_DIR should not be an error");
+        assert!(overflow.is_empty());
+        let share = watcher.close().await.expect("close should succeed");
+
+        let requests = outbound_requests(
+            share,
+            Command::ChangeNotify,
+            smolder_proto::smb::smb2::ChangeNotifyRequest::decode,
+        );
+        assert_eq!(requests.len(), 2);
+        for request in &requests {
+            assert_eq!(
+                request.flags,
+                smolder_proto::smb::smb2::ChangeNotifyFlags::WATCH_TREE
+            );
+            assert_eq!(request.file_id, file_id);
+        }
+    }
+
+    #[tokio::test]
+    async fn share_create_dir_creates_and_closes_directory_handle() {
+        let file_id = FileId {
+            persistent: 30,
+            volatile: 40,
+        };
+
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::DIRECTORY, 0),
+            close_frame(4, FileAttributes::DIRECTORY, 0),
+        ])
+        .await;
+
+        share
+            .create_dir("nested")
+            .await
+            .expect("create_dir should succeed");
+    }
+
+    #[tokio::test]
+    async fn share_rename_sets_rename_information_and_closes_handle() {
+        let file_id = FileId {
+            persistent: 50,
+            volatile: 60,
+        };
+
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::ARCHIVE, 5),
+            response_frame(
+                Command::SetInfo,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                11,
+                7,
+                smolder_proto::smb::smb2::SetInfoResponse.encode(),
+            ),
+            close_frame(5, FileAttributes::ARCHIVE, 5),
+        ])
+        .await;
+
+        share
+            .rename("notes.txt", "archive\\notes.txt")
+            .await
+            .expect("rename should succeed");
+    }
+
+    #[tokio::test]
+    async fn share_rename_reports_collision_and_closes_handle() {
+        let file_id = FileId {
+            persistent: 50,
+            volatile: 60,
+        };
+
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::ARCHIVE, 5),
+            response_frame(
+                Command::SetInfo,
+                NtStatus::OBJECT_NAME_COLLISION.to_u32(),
+                4,
+                11,
+                7,
+                Vec::new(),
+            ),
+            close_frame(5, FileAttributes::ARCHIVE, 5),
+        ])
+        .await;
+
+        let error = share
+            .rename("notes.txt", "existing.txt")
+            .await
+            .expect_err("rename onto an existing file should fail");
+        assert!(matches!(
+            error,
+            crate::error::CoreError::UnexpectedStatus {
+                command: Command::SetInfo,
+                status,
+            } if status == NtStatus::OBJECT_NAME_COLLISION.to_u32()
+        ));
+
+        let closes = outbound_requests(share, Command::Close, CloseRequest::decode);
+        assert_eq!(closes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn share_remove_closes_handle_when_delete_is_refused() {
+        let file_id = FileId {
+            persistent: 50,
+            volatile: 60,
+        };
+
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::READONLY, 5),
+            response_frame(
+                Command::SetInfo,
+                NtStatus::CANNOT_DELETE.to_u32(),
+                4,
+                11,
+                7,
+                Vec::new(),
+            ),
+            close_frame(5, FileAttributes::READONLY, 5),
+        ])
+        .await;
+
+        let error = share
+            .remove("notes.txt")
+            .await
+            .expect_err("read-only file should not be removed");
+        assert!(matches!(
+            error,
+            crate::error::CoreError::UnexpectedStatus {
+                command: Command::SetInfo,
+                status,
+            } if status == NtStatus::CANNOT_DELETE.to_u32()
+        ));
+
+        let closes = outbound_requests(share, Command::Close, CloseRequest::decode);
+        assert_eq!(closes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn share_remove_dir_reports_non_empty_directory_and_closes_handle() {
+        let file_id = FileId {
+            persistent: 70,
+            volatile: 80,
+        };
+
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::DIRECTORY, 0),
+            response_frame(
+                Command::SetInfo,
+                NtStatus::DIRECTORY_NOT_EMPTY.to_u32(),
+                4,
+                11,
+                7,
+                Vec::new(),
+            ),
+            close_frame(5, FileAttributes::DIRECTORY, 0),
+        ])
+        .await;
+
+        let error = share
+            .remove_dir("/docs/archive/")
+            .await
+            .expect_err("non-empty directory should not be removed");
+        assert!(matches!(
+            error,
+            crate::error::CoreError::DirectoryNotEmpty(ref path) if path == r"docs\archive"
+        ));
+
+        let creates = outbound_requests(share, Command::Create, CreateRequest::decode);
+        assert_eq!(creates.len(), 1);
+        assert_eq!(creates[0].create_options, CreateOptions::DIRECTORY_FILE);
+    }
+
+    #[tokio::test]
+    async fn share_exists_and_stat_report_missing_paths() {
+        let file_id = FileId {
+            persistent: 90,
+            volatile: 91,
+        };
+
+        let mut share = build_share(vec![
+            response_frame(
+                Command::Create,
+                NtStatus::OBJECT_NAME_NOT_FOUND.to_u32(),
+                3,
+                11,
+                7,
+                Vec::new(),
+            ),
+            response_frame(
+                Command::Create,
+                NtStatus::OBJECT_PATH_NOT_FOUND.to_u32(),
+                4,
+                11,
+                7,
+                Vec::new(),
+            ),
+            create_frame(5, file_id, FileAttributes::DIRECTORY, 0),
+            close_frame(6, FileAttributes::DIRECTORY, 0),
+        ])
+        .await;
+
+        assert!(!share
+            .exists("missing.txt")
+            .await
+            .expect("missing file should not be an error"));
+        let error = share
+            .stat("missing/notes.txt")
+            .await
+            .expect_err("missing parent should fail");
+        assert!(matches!(
+            error,
+            crate::error::CoreError::NotFound(ref path) if path == r"missing\notes.txt"
+        ));
+        assert!(share
+            .exists("docs")
+            .await
+            .expect("existing directory should be found"));
+
+        let creates = outbound_requests(share, Command::Create, CreateRequest::decode);
+        assert_eq!(creates.len(), 3);
+        assert!(creates[2].create_options.is_empty());
+    }
+
+    #[tokio::test]
+    async fn file_lock_range_reports_conflicts_and_unlocks() {
+        let create_response = CreateResponse {
+            oplock_level: OplockLevel::None,
+            file_attributes: FileAttributes::ARCHIVE,
+            allocation_size: 4096,
+            end_of_file: 128,
+            file_id: FileId {
+                persistent: 70,
+                volatile: 80,
+            },
+            create_contexts: Vec::new(),
         };
 
-        let mut share = build_share(vec![
+        let share = build_share(vec![
             response_frame(
                 Command::Create,
                 NtStatus::SUCCESS.to_u32(),
@@ -2215,28 +4985,28 @@ mod tests {
                 create_response.encode(),
             ),
             response_frame(
-                Command::QueryDirectory,
-                NtStatus::SUCCESS.to_u32(),
+                Command::Lock,
+                NtStatus::LOCK_NOT_GRANTED.to_u32(),
                 4,
                 11,
                 7,
-                first_page.encode(),
+                Vec::new(),
             ),
             response_frame(
-                Command::QueryDirectory,
+                Command::Lock,
                 NtStatus::SUCCESS.to_u32(),
                 5,
                 11,
                 7,
-                second_page.encode(),
+                LockResponse.encode(),
             ),
             response_frame(
-                Command::QueryDirectory,
-                NtStatus::NO_MORE_FILES.to_u32(),
+                Command::Lock,
+                NtStatus::SUCCESS.to_u32(),
                 6,
                 11,
                 7,
-                Vec::new(),
+                LockResponse.encode(),
             ),
             response_frame(
                 Command::Close,
@@ -2246,39 +5016,74 @@ mod tests {
                 7,
                 CloseResponse {
                     flags: 0,
-                    allocation_size: 0,
-                    end_of_file: 0,
-                    file_attributes: FileAttributes::DIRECTORY,
+                    allocation_size: 4096,
+                    end_of_file: 128,
+                    file_attributes: FileAttributes::ARCHIVE,
                 }
                 .encode(),
             ),
         ])
         .await;
 
-        let entries = share.list("\\").await.expect("list should succeed");
-        assert_eq!(entries.len(), 2);
-        assert_eq!(entries[0].name, "notes.txt");
-        assert!(entries[0].is_file());
-        assert_eq!(entries[0].metadata.size, 5);
-        assert_eq!(entries[1].name, "nested");
-        assert!(entries[1].is_directory());
+        let mut file = share
+            .open("notes.txt", OpenOptions::new().read(true).write(true))
+            .await
+            .expect("open should succeed");
+        let error = file
+            .lock_range(1 << 40, 16, true)
+            .await
+            .expect_err("conflicting lock should fail");
+        assert!(matches!(
+            error,
+            crate::error::CoreError::LockNotGranted {
+                offset: 1_099_511_627_776,
+                length: 16,
+            }
+        ));
+        file.lock_range(1 << 40, 16, false)
+            .await
+            .expect("shared lock should succeed");
+        file.unlock_range(1 << 40, 16)
+            .await
+            .expect("unlock should succeed");
+        let share = file.close().await.expect("close should succeed");
+
+        let locks = outbound_requests(share, Command::Lock, LockRequest::decode);
+        let flags = locks
+            .iter()
+            .map(|request| {
+                assert_eq!(request.file_id, create_response.file_id);
+                assert_eq!(request.locks.len(), 1);
+                assert_eq!(request.locks[0].offset, 1 << 40);
+                assert_eq!(request.locks[0].length, 16);
+                request.locks[0].flags
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            flags,
+            vec![
+                LockFlags::EXCLUSIVE_LOCK | LockFlags::FAIL_IMMEDIATELY,
+                LockFlags::SHARED_LOCK | LockFlags::FAIL_IMMEDIATELY,
+                LockFlags::UNLOCK,
+            ]
+        );
     }
 
     #[tokio::test]
-    async fn share_create_dir_creates_and_closes_directory_handle() {
+    async fn open_requests_an_oplock_and_records_the_granted_level() {
         let create_response = CreateResponse {
-            oplock_level: OplockLevel::None,
-            file_attributes: FileAttributes::DIRECTORY,
-            allocation_size: 0,
-            end_of_file: 0,
+            oplock_level: OplockLevel::II,
+            file_attributes: FileAttributes::ARCHIVE,
+            allocation_size: 4096,
+            end_of_file: 128,
             file_id: FileId {
-                persistent: 30,
-                volatile: 40,
+                persistent: 71,
+                volatile: 81,
             },
             create_contexts: Vec::new(),
         };
 
-        let mut share = build_share(vec![
+        let share = build_share(vec![
             response_frame(
                 Command::Create,
                 NtStatus::SUCCESS.to_u32(),
@@ -2295,36 +5100,358 @@ mod tests {
                 7,
                 CloseResponse {
                     flags: 0,
-                    allocation_size: 0,
-                    end_of_file: 0,
-                    file_attributes: FileAttributes::DIRECTORY,
+                    allocation_size: 4096,
+                    end_of_file: 128,
+                    file_attributes: FileAttributes::ARCHIVE,
                 }
                 .encode(),
             ),
         ])
         .await;
 
+        let file = share
+            .open(
+                "notes.txt",
+                OpenOptions::new()
+                    .read(true)
+                    .oplock(RequestedOplockLevel::Batch),
+            )
+            .await
+            .expect("open should succeed");
+        assert_eq!(file.oplock_level(), OplockLevel::II);
+        let share = file.close().await.expect("close should succeed");
+
+        let creates = outbound_requests(share, Command::Create, CreateRequest::decode);
+        assert_eq!(creates.len(), 1);
+        assert_eq!(
+            creates[0].requested_oplock_level,
+            RequestedOplockLevel::Batch
+        );
+    }
+
+    #[tokio::test]
+    async fn reconnecting_share_retries_transport_failures_with_fresh_connections() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("listener should bind");
+        let port = listener.local_addr().expect("listener address").port();
+        let accepts = tokio::spawn(async move {
+            for _ in 0..3 {
+                let (stream, _) = listener.accept().await.expect("client should connect");
+                drop(stream);
+            }
+        });
+
+        let client = ClientBuilder::new("127.0.0.1")
+            .with_port(port)
+            .with_ntlm_credentials(NtlmCredentials::new("user", "pass"))
+            .with_retry_policy(RetryPolicy::new(2).with_backoff(Duration::from_millis(1)))
+            .build()
+            .expect("builder should produce a client");
+        assert_eq!(client.retry_policy().max_retries(), 2);
+
+        let mut share = client.reconnecting_share("data");
+        let mut calls = 0;
+        let error = share
+            .run(|share| {
+                calls += 1;
+                Box::pin(share.exists("notes.txt"))
+            })
+            .await
+            .expect_err("a server that drops connections should fail");
+        assert!(matches!(error, crate::error::CoreError::Io(_)));
+        assert_eq!(calls, 0);
+        assert!(!share.is_connected());
+        accepts
+            .await
+            .expect("one attempt plus two retries should connect");
+    }
+
+    #[tokio::test]
+    async fn share_pool_reuses_healthy_shares_and_discards_dead_ones() {
+        let pool = SharePool::<ScriptedTransport>::new(1);
+        let key = PoolKey {
+            client: 1,
+            share: "share".to_owned(),
+        };
+        let healthy = build_share(vec![response_frame(
+            Command::Echo,
+            NtStatus::SUCCESS.to_u32(),
+            3,
+            11,
+            7,
+            EchoResponse.encode(),
+        )])
+        .await;
+        drop(pool.guard(key.clone(), healthy));
+        assert_eq!(pool.idle_count(), 1);
+
+        let reused = pool
+            .take_healthy(&key)
+            .await
+            .expect("an echoing share should be reused");
+        assert_eq!(pool.idle_count(), 0);
+        let reused = pool.guard(key.clone(), reused);
+        drop(pool.guard(key.clone(), build_share(Vec::new()).await));
+        drop(reused);
+        assert_eq!(pool.idle_count(), 1, "the idle limit caps released shares");
+
+        assert!(
+            pool.take_healthy(&key).await.is_none(),
+            "a share that cannot echo should be discarded"
+        );
+        assert_eq!(pool.idle_count(), 0);
+
+        let detached = pool.guard(key, build_share(Vec::new()).await).detach();
+        assert_eq!(detached.tree_id(), TreeId(7));
+        assert_eq!(pool.idle_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn share_pool_acquire_reuses_a_live_connection() {
+        let server = crate::mock_server::MockServer::start().await;
+        let client = server.client();
+        let pool = SharePool::new(2);
+
+        let first = pool
+            .acquire(&client, "share")
+            .await
+            .expect("first acquire should connect");
+        drop(first);
+        assert_eq!(pool.idle_count(), 1);
+        let mut second = pool
+            .acquire(&client, "SHARE")
+            .await
+            .expect("second acquire should reuse the idle share");
+        second
+            .write("pooled.txt", b"pooled")
+            .await
+            .expect("the reused share should still work");
+
+        let commands = server.commands();
+        let negotiates = commands
+            .iter()
+            .filter(|command| **command == Command::Negotiate)
+            .count();
+        assert_eq!(negotiates, 1);
+        assert!(commands.contains(&Command::Echo));
+        assert_eq!(server.file("pooled.txt").as_deref(), Some(&b"pooled"[..]));
+    }
+
+    #[tokio::test]
+    async fn share_pool_keys_bare_and_unc_share_names_together() {
+        let server = crate::mock_server::MockServer::start().await;
+        let client = server.client();
+        let pool = SharePool::new(2);
+        let unc = format!(r"\\{}\share", client.server());
+
+        for name in ["share", unc.as_str(), r"share\"] {
+            drop(
+                pool.acquire(&client, name)
+                    .await
+                    .expect("acquire should reuse the pooled share"),
+            );
+            assert_eq!(pool.idle_count(), 1, "{name} should map to one entry");
+        }
+
+        let negotiates = server
+            .commands()
+            .iter()
+            .filter(|command| **command == Command::Negotiate)
+            .count();
+        assert_eq!(negotiates, 1);
+    }
+
+    #[tokio::test]
+    async fn share_pool_never_hands_a_share_to_a_different_client() {
+        let server = crate::mock_server::MockServer::start().await;
+        let owner = server.client();
+        let other = server
+            .client_builder()
+            .with_ntlm_credentials(NtlmCredentials::new("user", "wrong password"))
+            .build()
+            .expect("client should build");
+        let pool = SharePool::new(2);
+
+        drop(
+            pool.acquire(&owner, "share")
+                .await
+                .expect("owner acquire should connect"),
+        );
+        assert_eq!(pool.idle_count(), 1);
+        drop(
+            pool.acquire(&other, "share")
+                .await
+                .expect("the mock server accepts any password"),
+        );
+        assert_eq!(pool.idle_count(), 2, "the owner's idle share is not reused");
+        drop(
+            pool.acquire(&owner.clone(), "share")
+                .await
+                .expect("a clone of the owner reuses its idle share"),
+        );
+
+        let negotiates = server
+            .commands()
+            .iter()
+            .filter(|command| **command == Command::Negotiate)
+            .count();
+        assert_eq!(negotiates, 2);
+    }
+
+    #[tokio::test]
+    async fn client_reads_and_writes_files_by_unc_path() {
+        let server = crate::mock_server::MockServer::builder()
+            .with_file("data\\report.csv", b"a,b\n1,2\n")
+            .start()
+            .await;
+        let client = server.client();
+
+        let report = client
+            .read_unc(r"\\127.0.0.1\share\data\report.csv")
+            .await
+            .expect("UNC read should succeed");
+        assert_eq!(report, b"a,b\n1,2\n");
+
+        client
+            .write_unc("//127.0.0.1/share/data/copy.csv", &report)
+            .await
+            .expect("UNC write should succeed");
+        assert_eq!(server.file("data\\copy.csv").as_deref(), Some(&report[..]));
+
+        let error = client
+            .read_unc(r"\\elsewhere\share\data\report.csv")
+            .await
+            .expect_err("a different server should be rejected");
+        assert!(matches!(error, crate::error::CoreError::InvalidInput(_)));
+    }
+
+    #[tokio::test]
+    async fn share_set_attributes_updates_basic_information_and_closes_handle() {
+        let file_id = FileId {
+            persistent: 70,
+            volatile: 80,
+        };
+        let close_response = CloseResponse {
+            flags: 0,
+            allocation_size: 5,
+            end_of_file: 5,
+            file_attributes: FileAttributes::READONLY | FileAttributes::HIDDEN,
+        };
+
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::ARCHIVE, 5),
+            response_frame(
+                Command::SetInfo,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                11,
+                7,
+                smolder_proto::smb::smb2::SetInfoResponse.encode(),
+            ),
+            response_frame(
+                Command::Close,
+                NtStatus::SUCCESS.to_u32(),
+                5,
+                11,
+                7,
+                close_response.encode(),
+            ),
+        ])
+        .await;
+
         share
-            .create_dir("nested")
+            .set_attributes(
+                "docs/report.txt",
+                FileAttributes::READONLY | FileAttributes::HIDDEN,
+            )
             .await
-            .expect("create_dir should succeed");
+            .expect("set attributes should succeed");
+
+        let requests = outbound_requests(
+            share,
+            Command::SetInfo,
+            smolder_proto::smb::smb2::SetInfoRequest::decode,
+        );
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].file_info_class,
+            smolder_proto::smb::smb2::FileInfoClass::BasicInformation
+        );
+        let basic = smolder_proto::smb::smb2::FileBasicInformation::decode(&requests[0].buffer)
+            .expect("basic information should decode");
+        assert_eq!(
+            basic.file_attributes,
+            FileAttributes::READONLY | FileAttributes::HIDDEN
+        );
+        assert_eq!(
+            (
+                basic.creation_time,
+                basic.last_access_time,
+                basic.last_write_time,
+                basic.change_time
+            ),
+            (0, 0, 0, 0)
+        );
     }
 
     #[tokio::test]
-    async fn share_rename_sets_rename_information_and_closes_handle() {
+    async fn share_set_times_leaves_unset_fields_unchanged() {
+        let file_id = FileId {
+            persistent: 71,
+            volatile: 81,
+        };
+
+        let mut share = build_share(vec![
+            create_frame(3, file_id, FileAttributes::ARCHIVE, 5),
+            response_frame(
+                Command::SetInfo,
+                NtStatus::SUCCESS.to_u32(),
+                4,
+                11,
+                7,
+                smolder_proto::smb::smb2::SetInfoResponse.encode(),
+            ),
+            close_frame(5, FileAttributes::ARCHIVE, 5),
+        ])
+        .await;
+
+        let modified = UNIX_EPOCH + Duration::from_secs(1_609_459_200);
+        share
+            .set_times("docs/report.txt", None, None, Some(modified))
+            .await
+            .expect("set times should succeed");
+
+        let requests = outbound_requests(
+            share,
+            Command::SetInfo,
+            smolder_proto::smb::smb2::SetInfoRequest::decode,
+        );
+        assert_eq!(requests.len(), 1);
+        let basic = smolder_proto::smb::smb2::FileBasicInformation::decode(&requests[0].buffer)
+            .expect("basic information should decode");
+        assert_eq!(basic.creation_time, 0);
+        assert_eq!(basic.last_access_time, 0);
+        assert_eq!(basic.last_write_time, 132_539_328_000_000_000);
+        assert_eq!(basic.change_time, 0);
+        assert!(basic.file_attributes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn file_set_end_of_file_and_allocation_size_send_set_info() {
         let create_response = CreateResponse {
             oplock_level: OplockLevel::None,
             file_attributes: FileAttributes::ARCHIVE,
-            allocation_size: 5,
-            end_of_file: 5,
+            allocation_size: 4096,
+            end_of_file: 128,
             file_id: FileId {
-                persistent: 50,
-                volatile: 60,
+                persistent: 72,
+                volatile: 82,
             },
             create_contexts: Vec::new(),
         };
 
-        let mut share = build_share(vec![
+        let share = build_share(vec![
             response_frame(
                 Command::Create,
                 NtStatus::SUCCESS.to_u32(),
@@ -2342,15 +5469,23 @@ mod tests {
                 smolder_proto::smb::smb2::SetInfoResponse.encode(),
             ),
             response_frame(
-                Command::Close,
+                Command::SetInfo,
                 NtStatus::SUCCESS.to_u32(),
                 5,
                 11,
                 7,
+                smolder_proto::smb::smb2::SetInfoResponse.encode(),
+            ),
+            response_frame(
+                Command::Close,
+                NtStatus::SUCCESS.to_u32(),
+                6,
+                11,
+                7,
                 CloseResponse {
                     flags: 0,
-                    allocation_size: 5,
-                    end_of_file: 5,
+                    allocation_size: 1 << 20,
+                    end_of_file: 16,
                     file_attributes: FileAttributes::ARCHIVE,
                 }
                 .encode(),
@@ -2358,9 +5493,345 @@ mod tests {
         ])
         .await;
 
-        share
-            .rename("notes.txt", "archive\\notes.txt")
+        let mut file = share
+            .open("notes.txt", OpenOptions::new().read(true).write(true))
             .await
-            .expect("rename should succeed");
+            .expect("open should succeed");
+        file.set_end_of_file(16)
+            .await
+            .expect("truncate should succeed");
+        file.set_allocation_size(1 << 20)
+            .await
+            .expect("allocation should succeed");
+        let share = file.close().await.expect("close should succeed");
+
+        let requests = outbound_requests(
+            share,
+            Command::SetInfo,
+            smolder_proto::smb::smb2::SetInfoRequest::decode,
+        );
+        let sent = requests
+            .iter()
+            .map(|request| {
+                assert_eq!(request.file_id, create_response.file_id);
+                (request.file_info_class, request.buffer.clone())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sent,
+            vec![
+                (
+                    smolder_proto::smb::smb2::FileInfoClass::EndOfFileInformation,
+                    16u64.to_le_bytes().to_vec()
+                ),
+                (
+                    smolder_proto::smb::smb2::FileInfoClass::AllocationInformation,
+                    (1u64 << 20).to_le_bytes().to_vec()
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn silent_guest_mapping_is_reported_on_the_session() {
+        let server = MockServer::start().await;
+        let session = server
+            .client()
+            .connect()
+            .await
+            .expect("the mock server should authenticate");
+        assert!(!session.is_guest());
+        assert!(!session.is_null_session());
+
+        let server = MockServer::builder().with_guest_sessions().start().await;
+        let session = server
+            .client()
+            .connect()
+            .await
+            .expect("guest-mapped logons still succeed");
+        assert!(session.is_guest());
+        assert!(!session.is_null_session());
+    }
+
+    #[tokio::test]
+    async fn ntlm_server_identity_is_kept_on_sessions_and_shares() {
+        let server = MockServer::start().await;
+        let session = server
+            .client()
+            .connect()
+            .await
+            .expect("the mock server should authenticate");
+        let info = session
+            .ntlm_server_info()
+            .expect("an NTLM session should keep the challenge identity");
+        assert_eq!(info.netbios_computer_name.as_deref(), Some("MOCKSERVER"));
+        assert_eq!(info.netbios_domain_name.as_deref(), Some("MOCK"));
+
+        let share = session
+            .connect_share("share")
+            .await
+            .expect("tree connect should succeed");
+        assert_eq!(
+            share
+                .ntlm_server_info()
+                .and_then(|info| info.netbios_computer_name.as_deref()),
+            Some("MOCKSERVER")
+        );
+        share.logoff().await.expect("logoff should succeed");
+    }
+
+    #[tokio::test]
+    async fn rejected_logons_fall_back_to_guest_when_enabled() {
+        let server = MockServer::builder()
+            .with_failed_logons(1)
+            .with_guest_sessions()
+            .start()
+            .await;
+        let error = server
+            .client()
+            .connect()
+            .await
+            .expect_err("rejected credentials should fail without fallback");
+        assert!(matches!(
+            error,
+            CoreError::UnexpectedStatus {
+                command: Command::SessionSetup,
+                status,
+            } if status == NtStatus::LOGON_FAILURE.to_u32()
+        ));
+
+        let server = MockServer::builder()
+            .with_failed_logons(1)
+            .with_guest_sessions()
+            .start()
+            .await;
+        let client = server
+            .client_builder()
+            .with_guest_fallback(true)
+            .build()
+            .expect("client should build");
+        let share = client
+            .connect_share("share")
+            .await
+            .expect("guest fallback should connect");
+        assert!(share.is_guest());
+        share.logoff().await.expect("logoff should succeed");
+
+        let negotiates = server
+            .commands()
+            .into_iter()
+            .filter(|command| *command == Command::Negotiate)
+            .count();
+        assert_eq!(negotiates, 2);
+    }
+
+    #[tokio::test]
+    async fn reconnecting_share_replays_a_read_on_a_fresh_connection() {
+        let server = MockServer::builder()
+            .with_file("notes.txt", b"retried contents")
+            .with_dropped_requests(Command::Read, 1)
+            .start()
+            .await;
+        let client = server
+            .client_builder()
+            .with_retry_policy(RetryPolicy::new(1))
+            .build()
+            .expect("client should build");
+
+        let mut share = client.reconnecting_share("share");
+        let mut calls = 0;
+        let contents = share
+            .run(|share| {
+                calls += 1;
+                Box::pin(share.read("notes.txt"))
+            })
+            .await
+            .expect("the retried read should succeed");
+        assert_eq!(contents, b"retried contents");
+        assert_eq!(calls, 2);
+        assert!(share.is_connected());
+
+        let commands = server.commands();
+        let count = |wanted| {
+            commands
+                .iter()
+                .filter(|command| **command == wanted)
+                .count()
+        };
+        assert_eq!(count(Command::Negotiate), 2);
+        assert_eq!(count(Command::Read), 2);
+        share.logoff().await.expect("logoff should succeed");
+    }
+
+    #[tokio::test]
+    async fn delete_on_close_removes_the_file_when_its_handle_closes() {
+        let server = MockServer::start().await;
+        let share = server
+            .client()
+            .connect_share("share")
+            .await
+            .expect("the mock server should authenticate and tree connect");
+
+        let mut file = share
+            .open(
+                "scratch.tmp",
+                OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .delete_on_close(true),
+            )
+            .await
+            .expect("temporary file should be created");
+        file.write_all(b"scratch")
+            .await
+            .expect("write should succeed");
+        let metadata = file.stat().await.expect("open file should be queryable");
+        assert_eq!(metadata.size, 7);
+        assert!(server.file("scratch.tmp").is_some());
+
+        let mut share = file.close().await.expect("close should succeed");
+        assert!(server.file("scratch.tmp").is_none());
+        assert!(!share
+            .exists("scratch.tmp")
+            .await
+            .expect("exists should succeed"));
+        share.logoff().await.expect("logoff should succeed");
+    }
+
+    #[tokio::test]
+    async fn copies_stream_between_paths_and_shares_in_chunks() {
+        let contents = (0..150_000u32).map(|i| i as u8).collect::<Vec<_>>();
+        let source = MockServer::builder()
+            .with_file("src\\big.bin", &contents)
+            .start()
+            .await;
+        let target = MockServer::start().await;
+        let mut share = source
+            .client()
+            .connect_share("share")
+            .await
+            .expect("source share should connect");
+        let mut target_share = target
+            .client()
+            .connect_share("share")
+            .await
+            .expect("target share should connect");
+
+        let copied = share
+            .copy("src/big.bin", "src/copy.bin")
+            .await
+            .expect("same-share copy should succeed");
+        assert_eq!(copied, contents.len() as u64);
+        assert_eq!(source.file("src/copy.bin"), Some(contents.clone()));
+
+        let copied = share
+            .copy_to("src/big.bin", &mut target_share, "dst/big.bin")
+            .await
+            .expect("cross-share copy should succeed");
+        assert_eq!(copied, contents.len() as u64);
+        assert_eq!(target.file("dst/big.bin"), Some(contents.clone()));
+        let contents_before_self_copy = contents;
+
+        let reads = source
+            .commands()
+            .into_iter()
+            .filter(|command| *command == Command::Read)
+            .count();
+        assert_eq!(reads, 6);
+
+        let error = share
+            .copy("src/missing.bin", "src/never.bin")
+            .await
+            .expect_err("missing source should fail");
+        assert!(matches!(error, CoreError::UnexpectedStatus { .. }));
+        assert!(source.file("src/never.bin").is_none());
+
+        let error = share
+            .copy("src/big.bin", "SRC\\Big.bin")
+            .await
+            .expect_err("copying a file onto itself should be rejected");
+        assert!(matches!(error, CoreError::InvalidInput(_)));
+        let mut same_tree = source
+            .client()
+            .connect_share("share")
+            .await
+            .expect("second source share should connect");
+        let error = share
+            .copy_to("src/big.bin", &mut same_tree, "src/big.bin")
+            .await
+            .expect_err("copying onto itself through another handle should be rejected");
+        assert!(matches!(error, CoreError::InvalidInput(_)));
+        assert_eq!(source.file("src/big.bin"), Some(contents_before_self_copy));
+        same_tree.logoff().await.expect("logoff should succeed");
+
+        share.logoff().await.expect("logoff should succeed");
+        target_share.logoff().await.expect("logoff should succeed");
+    }
+
+    #[tokio::test]
+    async fn client_runs_a_session_over_a_caller_supplied_stream() {
+        let server = MockServer::builder()
+            .with_file("seed.txt", b"over a wrapped stream")
+            .start()
+            .await;
+        let stream = tokio::net::TcpStream::connect(("127.0.0.1", server.port()))
+            .await
+            .expect("the mock server should accept a raw stream");
+
+        let session = server
+            .client()
+            .connect_with_transport(StreamTransport::new(stream))
+            .await
+            .expect("the session should authenticate over the wrapped stream");
+        let mut share = session
+            .connect_share("share")
+            .await
+            .expect("tree connect should succeed");
+        let contents = share
+            .read("seed.txt")
+            .await
+            .expect("seeded file should be readable");
+        assert_eq!(contents, b"over a wrapped stream");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keepalive_echoes_only_while_the_shared_share_is_idle() {
+        let server = MockServer::builder()
+            .with_file("seed.txt", b"still connected")
+            .start()
+            .await;
+        let share = server
+            .client()
+            .connect_share("share")
+            .await
+            .expect("the mock server should authenticate and tree connect");
+        let echoes = || {
+            server
+                .commands()
+                .iter()
+                .filter(|command| **command == Command::Echo)
+                .count()
+        };
+
+        let shared = SharedShare::new(share);
+        let keepalive = shared.spawn_keepalive(Duration::from_secs(30));
+        server.wait_for_commands(Command::Echo, 2).await;
+
+        {
+            let mut share = shared.lock().await;
+            let before = echoes();
+            tokio::time::advance(Duration::from_secs(120)).await;
+            tokio::task::yield_now().await;
+            assert_eq!(echoes(), before, "keepalive must wait for the lock");
+            let contents = share
+                .read("seed.txt")
+                .await
+                .expect("seeded file should be readable");
+            assert_eq!(contents, b"still connected");
+        }
+
+        keepalive.abort();
+        tokio::task::yield_now().await;
+        assert!(keepalive.is_finished());
     }
 }
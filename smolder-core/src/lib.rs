@@ -102,6 +102,9 @@ pub mod dfs;
 pub mod error;
 pub mod facade;
 pub mod lsarpc;
+#[cfg(test)]
+mod mock_server;
+mod ndr;
 pub mod pipe;
 pub mod rpc;
 pub mod samr;
@@ -130,11 +133,13 @@ pub mod prelude {
         DurableOpenOptions, Negotiated, ResilientHandle, TreeConnected,
     };
     pub use crate::compression::CompressionState;
-    pub use crate::crypto::{EncryptionKeys, derive_encryption_keys};
+    pub use crate::crypto::{EncryptionKeys, derive_application_key, derive_encryption_keys};
     pub use crate::dfs::{DfsReferral, UncPath, resolve_unc_path};
     pub use crate::error::CoreError;
     pub use crate::facade::{
-        Client, ClientBuilder, DirectoryEntry, File, FileMetadata, OpenOptions, Session, Share,
+        Client, ClientBuilder, DirectoryEntry, DirectoryWatcher, File, FileMetadata, FsInfo,
+        KeepaliveHandle, OpenOptions, PooledShare, ReconnectingShare, RetryPolicy, Session, Share,
+        ShareFuture, SharePool, SharedShare, VolumeInfo,
     };
     pub use crate::lsarpc::{
         DEFAULT_POLICY_ACCESS, LOOKUP_POLICY_ACCESS, LsaDomainInfo, LsaSid, LsaSidNameUse,
@@ -161,7 +166,8 @@ pub mod prelude {
     #[cfg_attr(docsrs, doc(cfg(feature = "quic")))]
     pub use crate::transport::QuicTransport;
     pub use crate::transport::{
-        SmbTransport, TokioTcpTransport, Transport, TransportProtocol, TransportTarget,
+        SmbTransport, StreamTransport, TokioTcpTransport, Transport, TransportProtocol,
+        TransportTarget, TransportTimeouts,
     };
 }
 
@@ -1,8 +1,10 @@
 //! Typed `lsarpc` DCE/RPC helpers built on top of named pipes.
 
 use smolder_proto::rpc::{SyntaxId, Uuid};
+use smolder_proto::smb::security::Sid;
 
 use crate::error::CoreError;
+use crate::ndr;
 use crate::rpc::PipeRpcClient;
 use crate::transport::TokioTcpTransport;
 
@@ -51,6 +53,16 @@ pub struct LsaSid {
     pub sub_authorities: Vec<u32>,
 }
 
+impl From<LsaSid> for Sid {
+    fn from(sid: LsaSid) -> Self {
+        Self {
+            revision: sid.revision,
+            identifier_authority: sid.identifier_authority,
+            sub_authorities: sid.sub_authorities,
+        }
+    }
+}
+
 /// Decoded `POLICY_PRIMARY_DOMAIN_INFO` or `POLICY_ACCOUNT_DOMAIN_INFO`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LsaDomainInfo {
@@ -600,6 +612,7 @@ fn parse_lookup_names_response(response: &[u8]) -> Result<Vec<LsaTranslatedSid>,
             ));
         }
 
+        let entries = ndr::check_count(entries, 12, reader.remaining(), "TranslatedSids.Entries")?;
         let mut translated = Vec::with_capacity(entries);
         for _ in 0..entries {
             translated.push((
@@ -865,7 +878,7 @@ impl<'a> NdrReader<'a> {
         }
         self.align(4, field)?;
         let max_count = self.read_u32(field)? as usize;
-        if max_count * 2 < header.length || header.maximum_length < header.length {
+        if max_count.saturating_mul(2) < header.length || header.maximum_length < header.length {
             return Err(CoreError::InvalidResponse(field));
         }
         let expected_units = header.length / 2;
@@ -887,6 +900,7 @@ impl<'a> NdrReader<'a> {
             }
         }
 
+        let units_to_read = ndr::check_count(units_to_read, 2, self.remaining(), field)?;
         let mut code_units = Vec::with_capacity(units_to_read);
         for _ in 0..units_to_read {
             code_units.push(self.read_u16(field)?);
@@ -978,6 +992,8 @@ mod tests {
         parse_primary_domain_info_response, parse_server_role_response,
         should_retry_legacy_policy_query,
     };
+    use smolder_proto::smb::security::Sid;
+
     use crate::error::CoreError;
 
     struct ResponseWriter {
@@ -1194,6 +1210,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lsa_sid_converts_to_a_displayable_sid() {
+        let sid = LsaSid {
+            revision: 1,
+            identifier_authority: [0, 0, 0, 0, 0, 5],
+            sub_authorities: vec![21, 42, 84, 500],
+        };
+
+        assert_eq!(Sid::from(sid).to_string(), "S-1-5-21-42-84-500");
+    }
+
     #[test]
     fn parse_primary_domain_info_response_decodes_name_and_sid() {
         let sid = LsaSid {
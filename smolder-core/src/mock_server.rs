@@ -0,0 +1,606 @@
+//! Loopback SMB2 server used to exercise the client end to end in tests.
+//!
+//! The server speaks just enough SMB 3.0.2 over direct TCP to carry the facade
+//! through negotiate, NTLM session setup, tree connect, and simple file I/O
+//! against an in-memory file table. Responses are never signed, so clients
+//! must not require signing. Use [`MockServerBuilder::with_status`] to make a
+//! command fail with a chosen `NTSTATUS`, or
+//! [`MockServerBuilder::with_dropped_requests`] to make it fail at the
+//! transport.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use smolder_proto::smb::smb2::{
+    CloseRequest, CloseResponse, Command, CreateDisposition, CreateOptions, CreateRequest,
+    CreateResponse, Dialect, EchoResponse, FileAttributes, FileId, FileInfoClass, FlushResponse,
+    GlobalCapabilities, Header, HeaderFlags, InfoClass, LogoffResponse, NegotiateResponse,
+    OplockLevel, QueryInfoRequest, QueryInfoResponse, ReadRequest, ReadResponse, ReadResponseFlags,
+    SessionFlags, SessionId, SessionSetupResponse, ShareFlags, ShareType, SigningMode,
+    TreeCapabilities, TreeConnectResponse, TreeDisconnectResponse, TreeId, WriteRequest,
+    WriteResponse,
+};
+use smolder_proto::smb::status::NtStatus;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::auth::{test_challenge_token, NtlmCredentials};
+use crate::facade::{Client, ClientBuilder};
+
+const SESSION_ID: SessionId = SessionId(0x5151);
+const TREE_ID: TreeId = TreeId(7);
+const CREDIT_GRANT: u16 = 64;
+const MAX_IO_SIZE: u32 = 65_536;
+
+type FileTable = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+/// Configures a [`MockServer`] before it starts listening.
+#[derive(Debug, Default)]
+pub(crate) struct MockServerBuilder {
+    statuses: HashMap<Command, NtStatus>,
+    dropped_requests: HashMap<Command, u32>,
+    files: HashMap<String, Vec<u8>>,
+    failed_logons: u32,
+    guest_sessions: bool,
+}
+
+impl MockServerBuilder {
+    /// Makes every request for `command` fail with `status`.
+    pub(crate) fn with_status(mut self, command: Command, status: NtStatus) -> Self {
+        self.statuses.insert(command, status);
+        self
+    }
+
+    /// Closes the connection instead of answering the first `count` requests
+    /// for `command`.
+    pub(crate) fn with_dropped_requests(mut self, command: Command, count: u32) -> Self {
+        self.dropped_requests.insert(command, count);
+        self
+    }
+
+    /// Rejects the first `count` logons with `STATUS_LOGON_FAILURE`.
+    pub(crate) fn with_failed_logons(mut self, count: u32) -> Self {
+        self.failed_logons = count;
+        self
+    }
+
+    /// Marks every successful session as logged on as guest.
+    pub(crate) fn with_guest_sessions(mut self) -> Self {
+        self.guest_sessions = true;
+        self
+    }
+
+    /// Seeds the file table with `contents` at `path`.
+    pub(crate) fn with_file(mut self, path: &str, contents: &[u8]) -> Self {
+        self.files.insert(file_key(path), contents.to_vec());
+        self
+    }
+
+    /// Binds a loopback listener and starts serving connections.
+    pub(crate) async fn start(self) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("mock server should bind a loopback port");
+        let addr = listener
+            .local_addr()
+            .expect("listener should have an address");
+        let files: FileTable = Arc::new(Mutex::new(self.files));
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let command_recorded = Arc::new(Notify::new());
+        let statuses = Arc::new(self.statuses);
+        let dropped_requests = Arc::new(Mutex::new(self.dropped_requests));
+        let failed_logons = Arc::new(AtomicU32::new(self.failed_logons));
+        let guest_sessions = self.guest_sessions;
+
+        let task = {
+            let files = Arc::clone(&files);
+            let commands = Arc::clone(&commands);
+            let command_recorded = Arc::clone(&command_recorded);
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let connection = MockConnection {
+                        statuses: Arc::clone(&statuses),
+                        dropped_requests: Arc::clone(&dropped_requests),
+                        failed_logons: Arc::clone(&failed_logons),
+                        guest_sessions,
+                        files: Arc::clone(&files),
+                        commands: Arc::clone(&commands),
+                        command_recorded: Arc::clone(&command_recorded),
+                        open_files: HashMap::new(),
+                        next_volatile_id: 1,
+                        session_setup_rounds: 0,
+                    };
+                    tokio::spawn(connection.serve(stream));
+                }
+            })
+        };
+
+        MockServer {
+            addr,
+            files,
+            commands,
+            command_recorded,
+            task,
+        }
+    }
+}
+
+/// A loopback SMB2 server that answers with canned, spec-shaped responses.
+#[derive(Debug)]
+pub(crate) struct MockServer {
+    addr: SocketAddr,
+    files: FileTable,
+    commands: Arc<Mutex<Vec<Command>>>,
+    command_recorded: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Returns a builder for a server that fails selected commands.
+    pub(crate) fn builder() -> MockServerBuilder {
+        MockServerBuilder::default()
+    }
+
+    /// Starts a server that accepts every supported command.
+    pub(crate) async fn start() -> Self {
+        Self::builder().start().await
+    }
+
+    /// Returns the loopback port the server is listening on.
+    pub(crate) fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Returns a facade client builder configured to reach this server.
+    pub(crate) fn client_builder(&self) -> ClientBuilder {
+        Client::builder("127.0.0.1")
+            .with_port(self.port())
+            .with_signing_mode(SigningMode::ENABLED)
+            .with_dialects(vec![Dialect::Smb210, Dialect::Smb302])
+            .with_ntlm_credentials(NtlmCredentials::new("user", "password"))
+    }
+
+    /// Returns a facade client configured to reach this server.
+    pub(crate) fn client(&self) -> Client {
+        self.client_builder()
+            .build()
+            .expect("mock client configuration should be valid")
+    }
+
+    /// Returns the current contents of a file in the server's table.
+    pub(crate) fn file(&self, path: &str) -> Option<Vec<u8>> {
+        lock(&self.files).get(&file_key(path)).cloned()
+    }
+
+    /// Returns every command the server has received, in arrival order.
+    pub(crate) fn commands(&self) -> Vec<Command> {
+        lock(&self.commands).clone()
+    }
+
+    /// Waits until the server has received `count` requests for `command`.
+    pub(crate) async fn wait_for_commands(&self, command: Command, count: usize) {
+        loop {
+            let recorded = self.command_recorded.notified();
+            let seen = lock(&self.commands)
+                .iter()
+                .filter(|seen| **seen == command)
+                .count();
+            if seen >= count {
+                return;
+            }
+            recorded.await;
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct OpenFile {
+    path: String,
+    delete_on_close: bool,
+}
+
+struct MockConnection {
+    statuses: Arc<HashMap<Command, NtStatus>>,
+    dropped_requests: Arc<Mutex<HashMap<Command, u32>>>,
+    failed_logons: Arc<AtomicU32>,
+    guest_sessions: bool,
+    files: FileTable,
+    commands: Arc<Mutex<Vec<Command>>>,
+    command_recorded: Arc<Notify>,
+    open_files: HashMap<u64, OpenFile>,
+    next_volatile_id: u64,
+    session_setup_rounds: u32,
+}
+
+impl MockConnection {
+    async fn serve(mut self, mut stream: TcpStream) {
+        loop {
+            let mut prefix = [0u8; 4];
+            if stream.read_exact(&mut prefix).await.is_err() {
+                return;
+            }
+            let length = u32::from_be_bytes([0, prefix[1], prefix[2], prefix[3]]) as usize;
+            let mut packet = vec![0u8; length];
+            if stream.read_exact(&mut packet).await.is_err() || packet.len() < Header::LEN {
+                return;
+            }
+            let Ok(header) = Header::decode(&packet[..Header::LEN]) else {
+                return;
+            };
+            lock(&self.commands).push(header.command);
+            self.command_recorded.notify_waiters();
+            if self.drops(header.command) {
+                return;
+            }
+
+            let (status, body) = self.respond(&header, &packet[Header::LEN..]);
+            let response = response_packet(&header, status, body);
+            let mut frame = Vec::with_capacity(4 + response.len());
+            frame.extend_from_slice(&(response.len() as u32).to_be_bytes());
+            frame.extend_from_slice(&response);
+            if stream.write_all(&frame).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn drops(&self, command: Command) -> bool {
+        match lock(&self.dropped_requests).get_mut(&command) {
+            Some(remaining) if *remaining > 0 => {
+                *remaining -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn respond(&mut self, header: &Header, body: &[u8]) -> (NtStatus, Vec<u8>) {
+        if let Some(status) = self.statuses.get(&header.command) {
+            return (*status, error_body());
+        }
+        match header.command {
+            Command::Negotiate => (NtStatus::SUCCESS, negotiate_response().encode()),
+            Command::SessionSetup => self.session_setup(),
+            Command::Logoff => (NtStatus::SUCCESS, LogoffResponse.encode()),
+            Command::TreeConnect => (
+                NtStatus::SUCCESS,
+                TreeConnectResponse {
+                    share_type: ShareType::Disk,
+                    share_flags: ShareFlags::empty(),
+                    capabilities: TreeCapabilities::empty(),
+                    maximal_access: 0x001f_01ff,
+                }
+                .encode(),
+            ),
+            Command::TreeDisconnect => (NtStatus::SUCCESS, TreeDisconnectResponse.encode()),
+            Command::Echo => (NtStatus::SUCCESS, EchoResponse.encode()),
+            Command::Create => self.create(body),
+            Command::Close => self.close(body),
+            Command::Flush => (NtStatus::SUCCESS, FlushResponse.encode()),
+            Command::Read => self.read(body),
+            Command::Write => self.write(body),
+            Command::QueryInfo => self.query_info(body),
+            _ => (NtStatus::NOT_IMPLEMENTED, error_body()),
+        }
+    }
+
+    fn session_setup(&mut self) -> (NtStatus, Vec<u8>) {
+        self.session_setup_rounds += 1;
+        if self.session_setup_rounds == 1 {
+            let response = SessionSetupResponse {
+                session_flags: SessionFlags::empty(),
+                security_buffer: test_challenge_token(*b"mockchal"),
+            };
+            return (NtStatus::MORE_PROCESSING_REQUIRED, response.encode());
+        }
+        let rejected = self
+            .failed_logons
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |remaining| {
+                remaining.checked_sub(1)
+            })
+            .is_ok();
+        if rejected {
+            return (NtStatus::LOGON_FAILURE, error_body());
+        }
+        let session_flags = if self.guest_sessions {
+            SessionFlags::IS_GUEST
+        } else {
+            SessionFlags::empty()
+        };
+        let response = SessionSetupResponse {
+            session_flags,
+            security_buffer: Vec::new(),
+        };
+        (NtStatus::SUCCESS, response.encode())
+    }
+
+    fn create(&mut self, body: &[u8]) -> (NtStatus, Vec<u8>) {
+        let Ok(request) = CreateRequest::decode(body) else {
+            return (NtStatus::INVALID_PARAMETER, error_body());
+        };
+        let path = file_key(&utf16le_to_string(&request.name));
+        let size = {
+            let mut files = lock(&self.files);
+            let exists = files.contains_key(&path);
+            match request.create_disposition {
+                CreateDisposition::Open | CreateDisposition::Overwrite if !exists => {
+                    return (NtStatus::OBJECT_NAME_NOT_FOUND, error_body());
+                }
+                CreateDisposition::Create if exists => {
+                    return (NtStatus::OBJECT_NAME_COLLISION, error_body());
+                }
+                CreateDisposition::Overwrite
+                | CreateDisposition::OverwriteIf
+                | CreateDisposition::Supersede => {
+                    files.insert(path.clone(), Vec::new());
+                }
+                _ => {}
+            }
+            files.entry(path.clone()).or_default().len() as u64
+        };
+        let volatile = self.next_volatile_id;
+        self.next_volatile_id += 1;
+        self.open_files.insert(
+            volatile,
+            OpenFile {
+                path,
+                delete_on_close: request
+                    .create_options
+                    .contains(CreateOptions::DELETE_ON_CLOSE),
+            },
+        );
+
+        let response = CreateResponse {
+            oplock_level: OplockLevel::None,
+            file_attributes: FileAttributes::ARCHIVE,
+            allocation_size: size,
+            end_of_file: size,
+            file_id: FileId {
+                persistent: volatile,
+                volatile,
+            },
+            create_contexts: Vec::new(),
+        };
+        (NtStatus::SUCCESS, response.encode())
+    }
+
+    fn close(&mut self, body: &[u8]) -> (NtStatus, Vec<u8>) {
+        let Ok(request) = CloseRequest::decode(body) else {
+            return (NtStatus::INVALID_PARAMETER, error_body());
+        };
+        let Some(open) = self.open_files.remove(&request.file_id.volatile) else {
+            return (NtStatus::INVALID_HANDLE, error_body());
+        };
+        if open.delete_on_close {
+            lock(&self.files).remove(&open.path);
+        }
+        let response = CloseResponse {
+            flags: 0,
+            allocation_size: 0,
+            end_of_file: 0,
+            file_attributes: FileAttributes::ARCHIVE,
+        };
+        (NtStatus::SUCCESS, response.encode())
+    }
+
+    fn read(&mut self, body: &[u8]) -> (NtStatus, Vec<u8>) {
+        let Ok(request) = ReadRequest::decode(body) else {
+            return (NtStatus::INVALID_PARAMETER, error_body());
+        };
+        let Some(contents) = self.contents(request.file_id) else {
+            return (NtStatus::INVALID_HANDLE, error_body());
+        };
+        let start = usize::try_from(request.offset).unwrap_or(usize::MAX);
+        if start >= contents.len() {
+            return (NtStatus::END_OF_FILE, error_body());
+        }
+        let end = start
+            .saturating_add(request.length as usize)
+            .min(contents.len());
+        let response = ReadResponse {
+            data_remaining: 0,
+            flags: ReadResponseFlags::empty(),
+            data: contents[start..end].to_vec(),
+        };
+        (NtStatus::SUCCESS, response.encode())
+    }
+
+    fn write(&mut self, body: &[u8]) -> (NtStatus, Vec<u8>) {
+        let Ok(request) = WriteRequest::decode(body) else {
+            return (NtStatus::INVALID_PARAMETER, error_body());
+        };
+        let Some(open) = self.open_files.get(&request.file_id.volatile) else {
+            return (NtStatus::INVALID_HANDLE, error_body());
+        };
+        let mut files = lock(&self.files);
+        let contents = files.entry(open.path.clone()).or_default();
+        let start = request.offset as usize;
+        let end = start + request.data.len();
+        if contents.len() < end {
+            contents.resize(end, 0);
+        }
+        contents[start..end].copy_from_slice(&request.data);
+        let response = WriteResponse {
+            count: request.data.len() as u32,
+        };
+        (NtStatus::SUCCESS, response.encode())
+    }
+
+    fn query_info(&mut self, body: &[u8]) -> (NtStatus, Vec<u8>) {
+        let Ok(request) = QueryInfoRequest::decode(body) else {
+            return (NtStatus::INVALID_PARAMETER, error_body());
+        };
+        let Some(contents) = self.contents(request.file_id) else {
+            return (NtStatus::INVALID_HANDLE, error_body());
+        };
+        let size = contents.len() as u64;
+        let mut output_buffer = Vec::new();
+        match request.info_class {
+            InfoClass::File(FileInfoClass::BasicInformation) => {
+                for _ in 0..4 {
+                    output_buffer.extend_from_slice(&0u64.to_le_bytes());
+                }
+                output_buffer.extend_from_slice(&FileAttributes::ARCHIVE.bits().to_le_bytes());
+                output_buffer.extend_from_slice(&0u32.to_le_bytes());
+            }
+            InfoClass::File(FileInfoClass::StandardInformation) => {
+                output_buffer.extend_from_slice(&size.to_le_bytes());
+                output_buffer.extend_from_slice(&size.to_le_bytes());
+                output_buffer.extend_from_slice(&1u32.to_le_bytes());
+                output_buffer.extend_from_slice(&[0, 0, 0, 0]);
+            }
+            _ => return (NtStatus::NOT_IMPLEMENTED, error_body()),
+        }
+        (
+            NtStatus::SUCCESS,
+            QueryInfoResponse { output_buffer }.encode(),
+        )
+    }
+
+    fn contents(&self, file_id: FileId) -> Option<Vec<u8>> {
+        let open = self.open_files.get(&file_id.volatile)?;
+        lock(&self.files).get(&open.path).cloned()
+    }
+}
+
+fn negotiate_response() -> NegotiateResponse {
+    NegotiateResponse {
+        security_mode: SigningMode::ENABLED,
+        dialect_revision: Dialect::Smb302,
+        negotiate_contexts: Vec::new(),
+        server_guid: *b"smolder-mock-srv",
+        capabilities: GlobalCapabilities::LARGE_MTU,
+        max_transact_size: MAX_IO_SIZE,
+        max_read_size: MAX_IO_SIZE,
+        max_write_size: MAX_IO_SIZE,
+        system_time: 0,
+        server_start_time: 0,
+        security_buffer: Vec::new(),
+    }
+}
+
+fn response_packet(request: &Header, status: NtStatus, body: Vec<u8>) -> Vec<u8> {
+    let mut header = Header::new(request.command, request.message_id);
+    header.status = status.to_u32();
+    header.flags = HeaderFlags::SERVER_TO_REDIR;
+    header.credit_request_response = CREDIT_GRANT;
+    if request.command != Command::Negotiate {
+        header.session_id = SESSION_ID;
+    }
+    if !matches!(
+        request.command,
+        Command::Negotiate | Command::SessionSetup | Command::Logoff
+    ) {
+        header.tree_id = TREE_ID;
+    }
+    let mut packet = header.encode();
+    packet.extend_from_slice(&body);
+    packet
+}
+
+fn error_body() -> Vec<u8> {
+    let mut body = Vec::with_capacity(9);
+    body.extend_from_slice(&9u16.to_le_bytes());
+    body.extend_from_slice(&[0; 7]);
+    body
+}
+
+fn file_key(path: &str) -> String {
+    path.trim_start_matches(['\\', '/'])
+        .replace('/', "\\")
+        .to_lowercase()
+}
+
+fn utf16le_to_string(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect::<Vec<_>>();
+    String::from_utf16_lossy(&units)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+mod tests {
+    use smolder_proto::smb::smb2::Command;
+    use smolder_proto::smb::status::NtStatus;
+
+    use super::MockServer;
+    use crate::error::CoreError;
+
+    #[tokio::test]
+    async fn facade_reads_and_writes_files_against_the_mock_server() {
+        let server = MockServer::builder()
+            .with_file("docs\\seed.txt", b"seeded contents")
+            .start()
+            .await;
+        let mut share = server
+            .client()
+            .connect_share("share")
+            .await
+            .expect("the mock server should authenticate and tree connect");
+
+        let contents = share
+            .read("docs/seed.txt")
+            .await
+            .expect("seeded file should be readable");
+        assert_eq!(contents, b"seeded contents");
+
+        share
+            .write("out.txt", b"written through smolder")
+            .await
+            .expect("write should succeed");
+        assert_eq!(
+            server.file("out.txt").as_deref(),
+            Some(&b"written through smolder"[..])
+        );
+        share.logoff().await.expect("logoff should succeed");
+
+        let commands = server.commands();
+        assert_eq!(
+            commands[..4],
+            [
+                Command::Negotiate,
+                Command::SessionSetup,
+                Command::SessionSetup,
+                Command::TreeConnect,
+            ]
+        );
+        assert!(commands.contains(&Command::Read));
+        assert!(commands.contains(&Command::Write));
+    }
+
+    #[tokio::test]
+    async fn injected_statuses_surface_as_unexpected_status_errors() {
+        let server = MockServer::builder()
+            .with_status(Command::TreeConnect, NtStatus::ACCESS_DENIED)
+            .start()
+            .await;
+
+        let error = server
+            .client()
+            .connect_share("share")
+            .await
+            .expect_err("tree connect should be refused");
+        assert!(matches!(
+            error,
+            CoreError::UnexpectedStatus {
+                command: Command::TreeConnect,
+                status,
+            } if status == NtStatus::ACCESS_DENIED.to_u32()
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+//! NDR decoding helpers shared by the typed DCE/RPC clients.
+
+use crate::error::CoreError;
+
+/// Checks a wire element count against the bytes left to decode.
+///
+/// Counts in NDR responses are server-controlled, so they are bounded by the
+/// unread bytes before any allocation is sized from them. `field` names the
+/// count in the returned error.
+pub(crate) fn check_count(
+    count: usize,
+    element_len: usize,
+    remaining: usize,
+    field: &'static str,
+) -> Result<usize, CoreError> {
+    if count
+        .checked_mul(element_len)
+        .is_none_or(|len| len > remaining)
+    {
+        return Err(CoreError::InvalidResponse(field));
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_count;
+    use crate::error::CoreError;
+
+    #[test]
+    fn check_count_bounds_counts_by_the_remaining_bytes() {
+        assert_eq!(check_count(3, 4, 12, "Entries").expect("count fits"), 3);
+        assert!(matches!(
+            check_count(4, 4, 12, "Entries"),
+            Err(CoreError::InvalidResponse("Entries"))
+        ));
+        assert!(matches!(
+            check_count(usize::MAX, 2, usize::MAX, "Entries"),
+            Err(CoreError::InvalidResponse("Entries"))
+        ));
+    }
+}
@@ -26,7 +26,9 @@ use crate::client::{Authenticated, Connection, TreeConnected};
 use crate::error::CoreError;
 #[cfg(feature = "quic")]
 use crate::transport::QuicTransport;
-use crate::transport::{SmbTransport, TokioTcpTransport, TransportProtocol, TransportTarget};
+use crate::transport::{
+    SmbTransport, TokioTcpTransport, TransportProtocol, TransportTarget, TransportTimeouts,
+};
 
 const FILE_READ_DATA: u32 = 0x0000_0001;
 const FILE_WRITE_DATA: u32 = 0x0000_0002;
@@ -45,6 +47,9 @@ pub struct SmbSessionConfig {
     dialects: Vec<Dialect>,
     client_guid: [u8; 16],
     compression: Option<CompressionCapabilities>,
+    timeouts: TransportTimeouts,
+    require_encryption: bool,
+    guest_fallback: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -71,6 +76,9 @@ impl SmbSessionConfig {
             dialects: vec![Dialect::Smb210, Dialect::Smb302, Dialect::Smb311],
             client_guid: random(),
             compression: None,
+            timeouts: TransportTimeouts::default(),
+            require_encryption: false,
+            guest_fallback: false,
         }
     }
 
@@ -95,6 +103,9 @@ impl SmbSessionConfig {
             dialects: vec![Dialect::Smb210, Dialect::Smb302, Dialect::Smb311],
             client_guid: random(),
             compression: None,
+            timeouts: TransportTimeouts::default(),
+            require_encryption: false,
+            guest_fallback: false,
         }
     }
 
@@ -160,6 +171,35 @@ impl SmbSessionConfig {
         self
     }
 
+    /// Overrides the connect, read, and write timeouts used by TCP transports.
+    #[must_use]
+    pub fn with_timeouts(mut self, timeouts: TransportTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Refuses to run the session unencrypted once it is established.
+    ///
+    /// When enabled, every request after session setup is encrypted even if
+    /// the server does not demand it, and connecting fails with
+    /// [`CoreError::Unsupported`] when the server negotiates no SMB 3.x cipher.
+    #[must_use]
+    pub fn with_require_encryption(mut self, require_encryption: bool) -> Self {
+        self.require_encryption = require_encryption;
+        self
+    }
+
+    /// Retries as the `Guest` account when the configured credentials are
+    /// rejected with `STATUS_LOGON_FAILURE`.
+    ///
+    /// The retry runs over a fresh connection. Check `is_guest` on the
+    /// resulting session to learn whether the fallback was taken.
+    #[must_use]
+    pub fn with_guest_fallback(mut self, guest_fallback: bool) -> Self {
+        self.guest_fallback = guest_fallback;
+        self
+    }
+
     /// Returns the logical SMB server name used for auth and share access.
     #[must_use]
     pub fn server(&self) -> &str {
@@ -225,6 +265,41 @@ impl SmbSessionConfig {
     pub fn compression_capabilities(&self) -> Option<&CompressionCapabilities> {
         self.compression.as_ref()
     }
+
+    /// Returns the configured transport timeouts.
+    #[must_use]
+    pub fn timeouts(&self) -> TransportTimeouts {
+        self.timeouts
+    }
+
+    /// Returns whether sessions must be encrypted once established.
+    #[must_use]
+    pub fn require_encryption(&self) -> bool {
+        self.require_encryption
+    }
+
+    /// Returns whether rejected logons are retried as the `Guest` account.
+    #[must_use]
+    pub fn guest_fallback(&self) -> bool {
+        self.guest_fallback
+    }
+
+    fn guest_fallback_config(&self, error: &CoreError) -> Option<Self> {
+        let logon_failed = matches!(
+            error,
+            CoreError::UnexpectedStatus {
+                command: Command::SessionSetup,
+                status,
+            } if *status == NtStatus::LOGON_FAILURE.to_u32()
+        );
+        if !self.guest_fallback || !logon_failed {
+            return None;
+        }
+        let mut guest = self.clone();
+        guest.auth = SessionAuth::Ntlm(NtlmCredentials::new("Guest", ""));
+        guest.guest_fallback = false;
+        Some(guest)
+    }
 }
 
 /// Access mask preset used when opening a named pipe.
@@ -299,9 +374,7 @@ impl NamedPipe<TokioTcpTransport> {
         pipe_name: &str,
         access: PipeAccess,
     ) -> Result<Self, CoreError> {
-        let transport =
-            TokioTcpTransport::connect((config.transport_target().connect_host(), config.port()))
-                .await?;
+        let transport = connect_tcp_transport(config).await?;
         Self::connect_with_transport(transport, config, share, pipe_name, access).await
     }
 }
@@ -505,28 +578,20 @@ where
     }
 
     /// Reads one length-delimited DCE/RPC PDU from the pipe.
+    ///
+    /// Bytes past the end of the PDU are kept for the next read.
     pub async fn read_pdu(&mut self) -> Result<Vec<u8>, CoreError> {
-        let mut buffer = Vec::new();
-        let expected_len = loop {
-            let file_id = self.file_id;
-            let fragment_size = self.fragment_size;
-            let response = self
-                .connection_mut()
-                .read(&ReadRequest::for_file(file_id, 0, fragment_size))
-                .await?;
-            if response.data.is_empty() {
-                return Err(CoreError::InvalidResponse(
-                    "named pipe read returned no data",
-                ));
-            }
-            buffer.extend_from_slice(&response.data);
+        let mut buffer = self.read_buffer.split().to_vec();
+        loop {
             if buffer.len() >= 10 {
                 let frag_len = u16::from_le_bytes([buffer[8], buffer[9]]) as usize;
-                break frag_len;
+                if buffer.len() >= frag_len {
+                    self.read_buffer.extend_from_slice(&buffer[frag_len..]);
+                    buffer.truncate(frag_len);
+                    return Ok(buffer);
+                }
             }
-        };
 
-        while buffer.len() < expected_len {
             let file_id = self.file_id;
             let fragment_size = self.fragment_size;
             let response = self
@@ -534,14 +599,14 @@ where
                 .read(&ReadRequest::for_file(file_id, 0, fragment_size))
                 .await?;
             if response.data.is_empty() {
-                return Err(CoreError::InvalidResponse(
-                    "named pipe response ended before rpc fragment was complete",
-                ));
+                return Err(CoreError::InvalidResponse(if buffer.len() < 10 {
+                    "named pipe read returned no data"
+                } else {
+                    "named pipe response ended before rpc fragment was complete"
+                }));
             }
             buffer.extend_from_slice(&response.data);
         }
-        buffer.truncate(expected_len);
-        Ok(buffer)
     }
 
     /// Writes one request PDU and then reads one response PDU.
@@ -550,6 +615,49 @@ where
         self.read_pdu().await
     }
 
+    /// Writes one message and reads the reply in a single `FSCTL_PIPE_TRANSCEIVE` round trip.
+    ///
+    /// Replies larger than the fragment size are completed with follow-up reads.
+    pub async fn transact(&mut self, request: &[u8]) -> Result<Vec<u8>, CoreError> {
+        if self.closed {
+            return Err(CoreError::InvalidInput("named pipe is closed"));
+        }
+        if self.pending_read.is_some()
+            || self.pending_write.is_some()
+            || self.pending_flush.is_some()
+        {
+            return Err(CoreError::InvalidInput(
+                "cannot transact on named pipe while async I/O is pending",
+            ));
+        }
+        if !self.read_buffer.is_empty() {
+            return Err(CoreError::InvalidInput(
+                "cannot transact on named pipe while buffered read data is pending",
+            ));
+        }
+
+        let file_id = self.file_id;
+        let fragment_size = self.fragment_size;
+        let (mut response, mut more) = self
+            .connection_mut()
+            .pipe_transceive(file_id, request.to_vec(), fragment_size)
+            .await?;
+        while more {
+            let (data, overflow) = self
+                .connection_mut()
+                .read_pipe_message(&ReadRequest::for_file(file_id, 0, fragment_size))
+                .await?;
+            if data.is_empty() {
+                return Err(CoreError::InvalidResponse(
+                    "named pipe transact reply ended before the message was complete",
+                ));
+            }
+            response.extend_from_slice(&data);
+            more = overflow;
+        }
+        Ok(response)
+    }
+
     /// Reads the next newline-terminated UTF-8 control line from the pipe.
     pub async fn read_line(&mut self, buffer: &mut Vec<u8>) -> Result<Option<String>, CoreError> {
         loop {
@@ -864,17 +972,18 @@ pub async fn connect_session(
     config: &SmbSessionConfig,
 ) -> Result<Connection<TokioTcpTransport, Authenticated>, CoreError> {
     match config.transport_protocol() {
-        TransportProtocol::Tcp => {
-            let transport = TokioTcpTransport::connect((
-                config.transport_target().connect_host(),
-                config.port(),
-            ))
-            .await?;
-            connect_session_with_transport(transport, config).await
-        }
-        TransportProtocol::Netbios => {
-            let transport = TokioTcpTransport::connect_netbios(config.transport_target()).await?;
-            connect_session_with_transport(transport, config).await
+        TransportProtocol::Tcp | TransportProtocol::Netbios => {
+            let transport = connect_tcp_transport(config).await?;
+            match connect_session_with_transport(transport, config).await {
+                Err(error) => match config.guest_fallback_config(&error) {
+                    Some(guest) => {
+                        let transport = connect_tcp_transport(&guest).await?;
+                        connect_session_with_transport(transport, &guest).await
+                    }
+                    None => Err(error),
+                },
+                result => result,
+            }
         }
         TransportProtocol::Quic => Err(CoreError::Unsupported(
             "SMB over QUIC requires connect_session_quic",
@@ -888,7 +997,16 @@ pub async fn connect_session_quic(
     config: &SmbSessionConfig,
 ) -> Result<Connection<QuicTransport, Authenticated>, CoreError> {
     let transport = QuicTransport::connect(config.transport_target()).await?;
-    connect_session_with_transport(transport, config).await
+    match connect_session_with_transport(transport, config).await {
+        Err(error) => match config.guest_fallback_config(&error) {
+            Some(guest) => {
+                let transport = QuicTransport::connect(guest.transport_target()).await?;
+                connect_session_with_transport(transport, &guest).await
+            }
+            None => Err(error),
+        },
+        result => result,
+    }
 }
 
 /// Authenticates a session over an already-created transport.
@@ -912,10 +1030,10 @@ where
         dialects: config.dialects.clone(),
     };
     let connection = Connection::new(transport).negotiate(&request).await?;
-    match config.auth.clone() {
+    let mut connection = match config.auth.clone() {
         SessionAuth::Ntlm(credentials) => {
             let mut auth = NtlmAuthenticator::new(credentials);
-            connection.authenticate(&mut auth).await
+            connection.authenticate(&mut auth).await?
         }
         #[cfg(feature = "kerberos-api")]
         SessionAuth::Kerberos {
@@ -923,9 +1041,13 @@ where
             target,
         } => {
             let mut auth = KerberosAuthenticator::new(credentials, target);
-            connection.authenticate(&mut auth).await
+            connection.authenticate(&mut auth).await?
         }
+    };
+    if config.require_encryption {
+        connection.require_encryption()?;
     }
+    Ok(connection)
 }
 
 /// Authenticates and tree-connects to the requested share.
@@ -934,17 +1056,9 @@ pub async fn connect_tree(
     share: &str,
 ) -> Result<Connection<TokioTcpTransport, TreeConnected>, CoreError> {
     match config.transport_protocol() {
-        TransportProtocol::Tcp => {
-            let transport = TokioTcpTransport::connect((
-                config.transport_target().connect_host(),
-                config.port(),
-            ))
-            .await?;
-            connect_tree_with_transport(transport, config, share).await
-        }
-        TransportProtocol::Netbios => {
-            let transport = TokioTcpTransport::connect_netbios(config.transport_target()).await?;
-            connect_tree_with_transport(transport, config, share).await
+        TransportProtocol::Tcp | TransportProtocol::Netbios => {
+            let connection = connect_session(config).await?;
+            tree_connect_share(connection, config, share).await
         }
         TransportProtocol::Quic => Err(CoreError::Unsupported(
             "SMB over QUIC requires connect_tree_quic",
@@ -958,8 +1072,8 @@ pub async fn connect_tree_quic(
     config: &SmbSessionConfig,
     share: &str,
 ) -> Result<Connection<QuicTransport, TreeConnected>, CoreError> {
-    let transport = QuicTransport::connect(config.transport_target()).await?;
-    connect_tree_with_transport(transport, config, share).await
+    let connection = connect_session_quic(config).await?;
+    tree_connect_share(connection, config, share).await
 }
 
 /// Authenticates and tree-connects to the requested share over an already-created transport.
@@ -972,12 +1086,32 @@ where
     T: SmbTransport + Send,
 {
     let connection = connect_session_with_transport(transport, config).await?;
+    tree_connect_share(connection, config, share).await
+}
+
+async fn tree_connect_share<T>(
+    connection: Connection<T, Authenticated>,
+    config: &SmbSessionConfig,
+    share: &str,
+) -> Result<Connection<T, TreeConnected>, CoreError>
+where
+    T: SmbTransport + Send,
+{
     let unc = format!(r"\\{}\{}", config.server(), normalize_share_name(share)?);
     connection
         .tree_connect(&TreeConnectRequest::from_unc(&unc))
         .await
 }
 
+async fn connect_tcp_transport(config: &SmbSessionConfig) -> Result<TokioTcpTransport, CoreError> {
+    TokioTcpTransport::connect_target(config.transport_target(), config.timeouts())
+        .await
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::TimedOut => CoreError::Timeout("connecting to the SMB server"),
+            _ => CoreError::Io(error),
+        })
+}
+
 fn default_negotiate_contexts(
     dialects: &[Dialect],
     capabilities: GlobalCapabilities,
@@ -1037,12 +1171,12 @@ mod tests {
     use smolder_proto::smb::compression::{CompressionAlgorithm, CompressionCapabilityFlags};
     use smolder_proto::smb::netbios::SessionMessage;
     use smolder_proto::smb::smb2::{
-        CipherId, CloseResponse, Command, CreateResponse, Dialect, FileAttributes, FileId,
-        FlushResponse, GlobalCapabilities, Header, MessageId, NegotiateRequest, NegotiateResponse,
-        OplockLevel, ReadResponse, ReadResponseFlags, SessionFlags, SessionSetupRequest,
-        SessionSetupResponse, SessionSetupSecurityMode, ShareFlags, ShareType, SigningMode,
-        TransportCapabilityFlags, TreeCapabilities, TreeConnectRequest, TreeConnectResponse,
-        TreeId, WriteRequest, WriteResponse,
+        CipherId, CloseResponse, Command, CreateResponse, CtlCode, Dialect, FileAttributes, FileId,
+        FlushResponse, GlobalCapabilities, Header, IoctlRequest, IoctlResponse, MessageId,
+        NegotiateRequest, NegotiateResponse, OplockLevel, ReadResponse, ReadResponseFlags,
+        SessionFlags, SessionSetupRequest, SessionSetupResponse, SessionSetupSecurityMode,
+        ShareFlags, ShareType, SigningMode, TransportCapabilityFlags, TreeCapabilities,
+        TreeConnectRequest, TreeConnectResponse, TreeId, WriteRequest, WriteResponse,
     };
     use smolder_proto::smb::status::NtStatus;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -1803,6 +1937,94 @@ mod tests {
         pipe.close().await.expect("pipe close should succeed");
     }
 
+    #[tokio::test]
+    async fn named_pipe_transact_completes_overflowing_replies_with_reads() {
+        let create_response = CreateResponse {
+            oplock_level: OplockLevel::None,
+            file_attributes: FileAttributes::NORMAL,
+            allocation_size: 0,
+            end_of_file: 0,
+            file_id: FileId {
+                persistent: 1,
+                volatile: 2,
+            },
+            create_contexts: Vec::new(),
+        };
+        let close_response = CloseResponse {
+            flags: 0,
+            allocation_size: 0,
+            end_of_file: 0,
+            file_attributes: FileAttributes::NORMAL,
+        };
+
+        let reads = vec![
+            response_frame(
+                Command::Create,
+                NtStatus::SUCCESS.to_u32(),
+                3,
+                11,
+                7,
+                create_response.encode(),
+            ),
+            response_frame(
+                Command::Ioctl,
+                NtStatus::BUFFER_OVERFLOW.to_u32(),
+                4,
+                11,
+                7,
+                IoctlResponse {
+                    ctl_code: CtlCode::FSCTL_PIPE_TRANSCEIVE,
+                    file_id: create_response.file_id,
+                    input: Vec::new(),
+                    output: b"hello ".to_vec(),
+                    flags: 0,
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::Read,
+                NtStatus::SUCCESS.to_u32(),
+                5,
+                11,
+                7,
+                ReadResponse {
+                    data_remaining: 0,
+                    flags: ReadResponseFlags::empty(),
+                    data: b"world".to_vec(),
+                }
+                .encode(),
+            ),
+            response_frame(
+                Command::Close,
+                NtStatus::SUCCESS.to_u32(),
+                6,
+                11,
+                7,
+                close_response.encode(),
+            ),
+        ];
+
+        let connection = build_tree_connection(reads).await;
+        let mut pipe = NamedPipe::open(connection, "svcctl", PipeAccess::ReadWrite)
+            .await
+            .expect("pipe open should succeed");
+
+        let response = pipe
+            .transact(b"ping")
+            .await
+            .expect("pipe transact should succeed");
+        assert_eq!(response, b"hello world");
+
+        let connection = pipe.close().await.expect("pipe close should succeed");
+        let writes = connection.into_transport().writes;
+        let frame = SessionMessage::decode(&writes[4]).expect("frame should decode");
+        let request = IoctlRequest::decode(&frame.payload[Header::LEN..])
+            .expect("ioctl request should decode");
+        assert_eq!(request.ctl_code, CtlCode::FSCTL_PIPE_TRANSCEIVE);
+        assert_eq!(request.file_id, create_response.file_id);
+        assert_eq!(request.input, b"ping");
+    }
+
     async fn build_tree_connection(
         reads: Vec<Vec<u8>>,
     ) -> Connection<ScriptedTransport, TreeConnected> {
@@ -11,12 +11,15 @@ use crate::pipe::NamedPipe;
 use crate::transport::TokioTcpTransport;
 
 const RPC_DEFAULT_FRAGMENT_SIZE: u16 = 4_280;
+const RPC_REQUEST_HEADER_LEN: usize = 24;
+const RPC_OBJECT_UUID_LEN: usize = 16;
 
 /// Reusable DCE/RPC client over a named pipe transport.
 #[derive(Debug)]
 pub struct PipeRpcClient<T = TokioTcpTransport> {
     pipe: NamedPipe<T>,
     next_call_id: u32,
+    max_xmit_frag: u16,
     ntlm_packet_integrity: Option<NtlmRpcPacketIntegrity>,
 }
 
@@ -27,6 +30,7 @@ impl<T> PipeRpcClient<T> {
         Self {
             pipe,
             next_call_id: 1,
+            max_xmit_frag: RPC_DEFAULT_FRAGMENT_SIZE,
             ntlm_packet_integrity: None,
         }
     }
@@ -124,6 +128,9 @@ where
         let Packet::BindAck(bind_ack) = packet else {
             return Err(CoreError::InvalidResponse("expected rpc bind ack"));
         };
+        if bind_ack.max_recv_frag != 0 {
+            self.max_xmit_frag = bind_ack.max_recv_frag.min(RPC_DEFAULT_FRAGMENT_SIZE);
+        }
         Ok(bind_ack)
     }
 
@@ -217,6 +224,10 @@ where
     }
 
     /// Sends a request PDU with an optional object UUID and authentication verifier.
+    ///
+    /// Unauthenticated requests larger than the bound transmit fragment size are
+    /// split into multiple request fragments. Multi-fragment responses are
+    /// reassembled into a single [`ResponsePdu`].
     pub async fn call_with_object_pdu(
         &mut self,
         context_id: u16,
@@ -231,6 +242,16 @@ where
             ));
         }
 
+        let header_len = RPC_REQUEST_HEADER_LEN + object_uuid.map_or(0, |_| RPC_OBJECT_UUID_LEN);
+        if auth_verifier.is_none()
+            && self.ntlm_packet_integrity.is_none()
+            && header_len + stub_data.len() > usize::from(self.max_xmit_frag)
+        {
+            self.write_request_fragments(context_id, opnum, object_uuid, &stub_data, header_len)
+                .await?;
+            return self.read_response().await;
+        }
+
         let request = RequestPdu {
             call_id: self.next_call_id(),
             flags: PacketFlags::FIRST_FRAGMENT | PacketFlags::LAST_FRAGMENT,
@@ -258,7 +279,64 @@ where
             } else {
                 request.encode()
             };
-        let response = self.pipe.call(request_packet).await?;
+        self.pipe.write_all(&request_packet).await?;
+        self.read_response().await
+    }
+
+    async fn write_request_fragments(
+        &mut self,
+        context_id: u16,
+        opnum: u16,
+        object_uuid: Option<Uuid>,
+        stub_data: &[u8],
+        header_len: usize,
+    ) -> Result<(), CoreError> {
+        // Keep fragment boundaries on NDR's largest primitive alignment.
+        let capacity = (usize::from(self.max_xmit_frag).saturating_sub(header_len) & !7).max(8);
+        let call_id = self.next_call_id();
+        let mut offset = 0;
+        for chunk in stub_data.chunks(capacity) {
+            let mut flags = PacketFlags::empty();
+            if offset == 0 {
+                flags |= PacketFlags::FIRST_FRAGMENT;
+            }
+            if offset + chunk.len() == stub_data.len() {
+                flags |= PacketFlags::LAST_FRAGMENT;
+            }
+            let fragment = RequestPdu {
+                call_id,
+                flags,
+                alloc_hint: (stub_data.len() - offset) as u32,
+                context_id,
+                opnum,
+                object_uuid,
+                stub_data: chunk.to_vec(),
+                auth_verifier: None,
+            };
+            self.pipe.write_all(&fragment.encode()).await?;
+            offset += chunk.len();
+        }
+        Ok(())
+    }
+
+    async fn read_response(&mut self) -> Result<ResponsePdu, CoreError> {
+        let mut response = self.read_response_fragment().await?;
+        while !response.flags.contains(PacketFlags::LAST_FRAGMENT) {
+            let fragment = self.read_response_fragment().await?;
+            if fragment.call_id != response.call_id {
+                return Err(CoreError::InvalidResponse(
+                    "rpc response fragment did not match the request call id",
+                ));
+            }
+            response.stub_data.extend_from_slice(&fragment.stub_data);
+            response.flags |= fragment.flags & PacketFlags::LAST_FRAGMENT;
+            response.auth_verifier = fragment.auth_verifier;
+        }
+        Ok(response)
+    }
+
+    async fn read_response_fragment(&mut self) -> Result<ResponsePdu, CoreError> {
+        let response = self.pipe.read_pdu().await?;
         let packet = Packet::decode(&response)?;
         if let Some(ntlm_packet_integrity) = self.ntlm_packet_integrity.as_mut() {
             let verifier = match &packet {
@@ -602,6 +680,148 @@ mod tests {
         );
     }
 
+    fn response_fragment(flags: PacketFlags, stub_data: Vec<u8>) -> Packet {
+        Packet::Response(ResponsePdu {
+            call_id: 1,
+            flags,
+            alloc_hint: 4,
+            context_id: 0,
+            cancel_count: 0,
+            stub_data,
+            auth_verifier: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn call_reassembles_multi_fragment_responses() {
+        let pipe = open_pipe(vec![
+            rpc_response_frame_with_message_id(
+                response_fragment(PacketFlags::FIRST_FRAGMENT, vec![1, 2]),
+                6,
+            ),
+            rpc_response_frame_with_message_id(
+                response_fragment(PacketFlags::LAST_FRAGMENT, vec![3, 4]),
+                7,
+            ),
+        ])
+        .await;
+        let mut rpc = PipeRpcClient::new(pipe);
+
+        let stub = rpc
+            .call(0, 15, vec![0xaa, 0xbb])
+            .await
+            .expect("fragmented response should reassemble");
+
+        assert_eq!(stub, vec![1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn call_keeps_trailing_fragment_from_a_single_pipe_read() {
+        let mut data = response_fragment(PacketFlags::FIRST_FRAGMENT, vec![1, 2]).encode();
+        data.extend_from_slice(&response_fragment(PacketFlags::LAST_FRAGMENT, vec![3, 4]).encode());
+        let pipe = open_pipe(vec![pipe_read_frame(data, 6)]).await;
+        let mut rpc = PipeRpcClient::new(pipe);
+
+        let stub = rpc
+            .call(0, 15, vec![0xaa, 0xbb])
+            .await
+            .expect("buffered fragments should reassemble");
+
+        assert_eq!(stub, vec![1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn call_fragments_requests_larger_than_the_bound_fragment_size() {
+        let mut reads = vec![rpc_response_frame(Packet::BindAck(BindAckPdu {
+            call_id: 1,
+            flags: PacketFlags::FIRST_FRAGMENT | PacketFlags::LAST_FRAGMENT,
+            max_xmit_frag: 4280,
+            max_recv_frag: 40,
+            assoc_group_id: 0,
+            secondary_address: b"\\PIPE\\svcctl\0".to_vec(),
+            result: BindAckResult {
+                result: 0,
+                reason: 0,
+                transfer_syntax: SyntaxId::NDR32,
+            },
+            auth_verifier: None,
+        }))];
+        for message_id in [7, 9, 11] {
+            reads.push(response_frame(
+                Command::Write,
+                NtStatus::SUCCESS.to_u32(),
+                message_id,
+                11,
+                7,
+                WriteResponse { count: 40 }.encode(),
+            ));
+            reads.push(response_frame(
+                Command::Flush,
+                NtStatus::SUCCESS.to_u32(),
+                message_id + 1,
+                11,
+                7,
+                smolder_proto::smb::smb2::FlushResponse.encode(),
+            ));
+        }
+        reads.push(rpc_response_frame_with_message_id(
+            Packet::Response(ResponsePdu {
+                call_id: 2,
+                flags: PacketFlags::FIRST_FRAGMENT | PacketFlags::LAST_FRAGMENT,
+                alloc_hint: 1,
+                context_id: 0,
+                cancel_count: 0,
+                stub_data: vec![0x01],
+                auth_verifier: None,
+            }),
+            13,
+        ));
+        let (pipe, writes) = open_pipe_with_writes(reads).await;
+        let mut rpc = PipeRpcClient::new(pipe);
+        rpc.bind_context(0, TEST_SYNTAX)
+            .await
+            .expect("bind should succeed");
+
+        let stub_data = (0u8..40).collect::<Vec<_>>();
+        let response = rpc
+            .call(0, 15, stub_data.clone())
+            .await
+            .expect("fragmented request should succeed");
+        assert_eq!(response, vec![0x01]);
+
+        let writes = writes.lock().expect("writes lock");
+        let fragments = (0..3)
+            .rev()
+            .map(|index| match decode_nth_rpc_write(&writes, index) {
+                Ok(Packet::Request(request)) => request,
+                other => panic!("expected request fragment, got {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fragments
+                .iter()
+                .map(|fragment| (
+                    fragment.flags,
+                    fragment.alloc_hint,
+                    fragment.stub_data.len()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (PacketFlags::FIRST_FRAGMENT, 40, 16),
+                (PacketFlags::empty(), 24, 16),
+                (PacketFlags::LAST_FRAGMENT, 8, 8),
+            ]
+        );
+        assert!(fragments.iter().all(|fragment| fragment.call_id == 2));
+        assert_eq!(
+            fragments
+                .iter()
+                .flat_map(|fragment| fragment.stub_data.iter().copied())
+                .collect::<Vec<_>>(),
+            stub_data
+        );
+    }
+
     #[tokio::test]
     async fn bind_ntlm_sends_auth3_and_enables_packet_integrity() {
         let bind_type2 = ntlm_type2_challenge();
@@ -725,7 +945,10 @@ mod tests {
     }
 
     fn rpc_response_frame_with_message_id(packet: Packet, message_id: u64) -> Vec<u8> {
-        let packet = packet.encode();
+        pipe_read_frame(packet.encode(), message_id)
+    }
+
+    fn pipe_read_frame(data: Vec<u8>, message_id: u64) -> Vec<u8> {
         let mut header = Header::new(Command::Read, MessageId(message_id));
         header.status = NtStatus::SUCCESS.to_u32();
         header.session_id = smolder_proto::smb::smb2::SessionId(11);
@@ -736,7 +959,7 @@ mod tests {
             &ReadResponse {
                 data_remaining: 0,
                 flags: ReadResponseFlags::empty(),
-                data: packet,
+                data,
             }
             .encode(),
         );
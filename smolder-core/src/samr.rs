@@ -1,8 +1,10 @@
 //! Typed `samr` DCE/RPC helpers built on top of named pipes.
 
 use smolder_proto::rpc::{SyntaxId, Uuid};
+use smolder_proto::smb::security::Sid;
 
 use crate::error::CoreError;
+use crate::ndr;
 use crate::rpc::PipeRpcClient;
 use crate::transport::TokioTcpTransport;
 
@@ -77,6 +79,16 @@ pub struct SamrSid {
     pub sub_authorities: Vec<u32>,
 }
 
+impl From<SamrSid> for Sid {
+    fn from(sid: SamrSid) -> Self {
+        Self {
+            revision: sid.revision,
+            identifier_authority: sid.identifier_authority,
+            sub_authorities: sid.sub_authorities,
+        }
+    }
+}
+
 /// Domain-scoped user enumeration entry returned by `SamrEnumerateUsersInDomain`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SamrUser {
@@ -914,6 +926,7 @@ fn parse_get_members_in_alias_response(response: &[u8]) -> Result<Vec<SamrSid>,
         ));
     }
 
+    let member_count = ndr::check_count(member_count, 4, reader.remaining(), "AliasMemberCount")?;
     let mut member_headers = Vec::with_capacity(member_count);
     for _ in 0..member_count {
         member_headers.push(reader.read_u32("AliasMemberSidReferent")?);
@@ -997,6 +1010,8 @@ fn parse_rid_enumeration_response(
                     "SamrEnumerateUsersInDomain returned fewer array slots than entries",
                 ));
             }
+            let entries_read =
+                ndr::check_count(entries_read, 12, reader.remaining(), "EntriesRead")?;
             let mut raw_entries = Vec::with_capacity(entries_read);
             let mut headers = Vec::with_capacity(entries_read);
             for _ in 0..entries_read {
@@ -1170,11 +1185,12 @@ impl<'a> NdrReader<'a> {
         let actual_count = self.read_u32(field)? as usize;
         if offset != 0
             || actual_count > max_count
-            || actual_count * 2 < header.length
+            || actual_count.saturating_mul(2) < header.length
             || header.maximum_length < header.length
         {
             return Err(CoreError::InvalidResponse(field));
         }
+        let actual_count = ndr::check_count(actual_count, 2, self.remaining(), field)?;
         let mut code_units = Vec::with_capacity(actual_count);
         for _ in 0..actual_count {
             code_units.push(self.read_u16(field)?);
@@ -1198,11 +1214,12 @@ impl<'a> NdrReader<'a> {
         let actual_count = self.read_u32(field)? as usize;
         if offset != 0
             || actual_count > max_count
-            || actual_count * 2 < length
+            || actual_count.saturating_mul(2) < length
             || maximum_length < length
         {
             return Err(CoreError::InvalidResponse(field));
         }
+        let actual_count = ndr::check_count(actual_count, 2, self.remaining(), field)?;
         let mut code_units = Vec::with_capacity(actual_count);
         for _ in 0..actual_count {
             code_units.push(self.read_u16(field)?);
@@ -1806,9 +1823,7 @@ mod tests {
             encode_query_user_request([0x22; 20], USER_ACCOUNT_NAME_INFORMATION_CLASS),
             [
                 [0x22; 20].to_vec(),
-                (USER_ACCOUNT_NAME_INFORMATION_CLASS as u32)
-                    .to_le_bytes()
-                    .to_vec(),
+                USER_ACCOUNT_NAME_INFORMATION_CLASS.to_le_bytes().to_vec(),
             ]
             .concat()
         );
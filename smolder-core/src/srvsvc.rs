@@ -3,6 +3,7 @@
 use smolder_proto::rpc::{SyntaxId, Uuid};
 
 use crate::error::CoreError;
+use crate::ndr;
 use crate::rpc::PipeRpcClient;
 use crate::transport::TokioTcpTransport;
 
@@ -383,7 +384,7 @@ fn parse_share_enum_level1_response(response: &[u8]) -> Result<Vec<ShareInfo1>,
 
     let entries_read = reader.read_u32("EntriesRead")? as usize;
     let buffer_referent = reader.read_u32("BufferReferent")?;
-    let mut entries = Vec::with_capacity(entries_read);
+    let mut entries = Vec::new();
     if buffer_referent != 0 {
         let max_count = reader.read_u32("BufferMaxCount")? as usize;
         if max_count < entries_read {
@@ -391,6 +392,12 @@ fn parse_share_enum_level1_response(response: &[u8]) -> Result<Vec<ShareInfo1>,
                 "NetrShareEnum buffer count was smaller than entries read",
             ));
         }
+        entries.reserve(ndr::check_count(
+            entries_read,
+            12,
+            reader.remaining(),
+            "EntriesRead",
+        )?);
 
         for _ in 0..entries_read {
             entries.push(ShareInfo1Stub {
@@ -461,7 +468,7 @@ fn parse_session_enum_level10_response(response: &[u8]) -> Result<Vec<SessionInf
 
     let entries_read = reader.read_u32("EntriesRead")? as usize;
     let buffer_referent = reader.read_u32("BufferReferent")?;
-    let mut entries = Vec::with_capacity(entries_read);
+    let mut entries = Vec::new();
     if buffer_referent != 0 {
         let max_count = reader.read_u32("BufferMaxCount")? as usize;
         if max_count < entries_read {
@@ -469,6 +476,12 @@ fn parse_session_enum_level10_response(response: &[u8]) -> Result<Vec<SessionInf
                 "NetrSessionEnum buffer count was smaller than entries read",
             ));
         }
+        entries.reserve(ndr::check_count(
+            entries_read,
+            16,
+            reader.remaining(),
+            "EntriesRead",
+        )?);
 
         for _ in 0..entries_read {
             entries.push(SessionInfo10Stub {
@@ -804,6 +817,7 @@ impl<'a> NdrReader<'a> {
             return Err(CoreError::InvalidResponse(field));
         }
 
+        let actual_count = ndr::check_count(actual_count, 2, self.remaining(), field)?;
         let mut code_units = Vec::with_capacity(actual_count);
         for _ in 0..actual_count {
             code_units.push(self.read_u16(field)?);
@@ -1075,6 +1089,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_share_enum_level1_response_rejects_oversized_counts() {
+        let mut writer = ResponseWriter::new();
+        writer.write_u32(1);
+        writer.write_u32(1);
+        writer.write_u32(u32::MAX);
+        let array_referent = writer.next_referent();
+        writer.write_u32(array_referent);
+        writer.write_u32(u32::MAX);
+        writer.write_u32(0);
+
+        assert!(matches!(
+            parse_share_enum_level1_response(&writer.into_bytes()),
+            Err(CoreError::InvalidResponse("EntriesRead"))
+        ));
+
+        let mut writer = ResponseWriter::new();
+        writer.write_u32(1);
+        writer.write_u32(1);
+        writer.write_u32(1);
+        let array_referent = writer.next_referent();
+        writer.write_u32(array_referent);
+        writer.write_u32(1);
+        let name = writer.next_referent();
+        writer.write_u32(name);
+        writer.write_u32(0);
+        writer.write_u32(0);
+        writer.write_u32(u32::MAX);
+        writer.write_u32(0);
+        writer.write_u32(u32::MAX);
+
+        assert!(matches!(
+            parse_share_enum_level1_response(&writer.into_bytes()),
+            Err(CoreError::InvalidResponse("shi1_netname"))
+        ));
+    }
+
     #[test]
     fn parse_session_enum_level10_response_decodes_entries() {
         let mut writer = ResponseWriter::new();
@@ -5,15 +5,17 @@ use smolder_proto::smb::netbios::{
     SessionMessage, NEGATIVE_SESSION_RESPONSE, POSITIVE_SESSION_RESPONSE, SESSION_KEEP_ALIVE,
     SESSION_MESSAGE,
 };
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv6Addr};
 #[cfg(feature = "quic")]
-use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::net::{Ipv4Addr, SocketAddr};
 #[cfg(feature = "quic")]
 use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 #[cfg(feature = "quic")]
 use tokio::net::lookup_host;
 use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::time::timeout;
 
 #[cfg(feature = "quic")]
 use quinn::{
@@ -27,6 +29,14 @@ use rustls::RootCertStore;
 
 const NETBIOS_WILDCARD_SERVER_NAME: &str = "*SMBSERVER";
 const NETBIOS_CALLING_NAME: &str = "SMOLDER";
+const SOCKS5_VERSION: u8 = 0x05;
+const SOCKS5_AUTH_NONE: u8 = 0x00;
+const SOCKS5_AUTH_USERNAME_PASSWORD: u8 = 0x02;
+const SOCKS5_AUTH_NO_ACCEPTABLE: u8 = 0xff;
+const SOCKS5_COMMAND_CONNECT: u8 = 0x01;
+const SOCKS5_ADDRESS_IPV4: u8 = 0x01;
+const SOCKS5_ADDRESS_DOMAIN: u8 = 0x03;
+const SOCKS5_ADDRESS_IPV6: u8 = 0x04;
 
 /// The network transport protocol used to carry SMB session traffic.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -140,6 +150,64 @@ impl TransportTarget {
     }
 }
 
+/// Optional deadlines for establishing and driving a TCP transport.
+///
+/// Every limit defaults to `None`, which leaves the corresponding operation
+/// unbounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransportTimeouts {
+    connect: Option<Duration>,
+    read: Option<Duration>,
+    write: Option<Duration>,
+}
+
+impl TransportTimeouts {
+    /// Creates an unbounded timeout set.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bounds how long TCP connect and any NetBIOS session request may take.
+    #[must_use]
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect = Some(timeout);
+        self
+    }
+
+    /// Bounds how long receiving one SMB frame may take.
+    #[must_use]
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read = Some(timeout);
+        self
+    }
+
+    /// Bounds how long sending one SMB frame may take.
+    #[must_use]
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.write = Some(timeout);
+        self
+    }
+
+    /// Returns the configured connect timeout, if any.
+    #[must_use]
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect
+    }
+
+    /// Returns the configured read timeout, if any.
+    #[must_use]
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read
+    }
+
+    /// Returns the configured write timeout, if any.
+    #[must_use]
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write
+    }
+}
+
 /// Abstracts framed SMB request and response transport.
 ///
 /// This is the compatibility layer for transports that carry RFC1002 session
@@ -151,6 +219,13 @@ pub trait Transport {
 
     /// Reads a fully framed RFC1002 session message.
     async fn recv(&mut self) -> std::io::Result<Vec<u8>>;
+
+    /// Closes the sending side of the transport.
+    ///
+    /// The default does nothing, leaving the transport to close on drop.
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 /// Abstracts raw SMB message transport independent of RFC1002 framing.
@@ -165,6 +240,13 @@ pub trait SmbTransport {
 
     /// Reads a raw SMB message or transform payload.
     async fn recv_message(&mut self) -> std::io::Result<Vec<u8>>;
+
+    /// Closes the sending side of the transport.
+    ///
+    /// The default does nothing, leaving the transport to close on drop.
+    async fn shutdown_transport(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -195,6 +277,10 @@ where
         }
         Ok(message.payload)
     }
+
+    async fn shutdown_transport(&mut self) -> std::io::Result<()> {
+        self.shutdown().await
+    }
 }
 
 /// `tokio` TCP transport for SMB over port 445.
@@ -202,6 +288,7 @@ where
 pub struct TokioTcpTransport {
     stream: TcpStream,
     mode: TcpTransportMode,
+    timeouts: TransportTimeouts,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -220,6 +307,7 @@ impl TokioTcpTransport {
         Ok(Self {
             stream,
             mode: TcpTransportMode::DirectTcp,
+            timeouts: TransportTimeouts::default(),
         })
     }
 
@@ -277,32 +365,92 @@ impl TokioTcpTransport {
         Ok(Self {
             stream,
             mode: TcpTransportMode::NetbiosSession,
+            timeouts: TransportTimeouts::default(),
         })
     }
-}
 
-#[async_trait]
-impl Transport for TokioTcpTransport {
-    async fn send(&mut self, frame: &[u8]) -> std::io::Result<()> {
-        self.stream.write_all(frame).await
+    /// Connects to an SMB endpoint through a SOCKS5 proxy.
+    ///
+    /// Performs the RFC 1928 handshake with the proxy, using RFC 1929
+    /// username/password authentication when `credentials` is set, and asks it
+    /// to open a TCP stream to `target_host:target_port`. Host names are
+    /// resolved by the proxy; IP literals, including bracketed IPv6 literals
+    /// such as `[::1]`, are sent as addresses. The connect timeout bounds the
+    /// proxy connection and the whole handshake, and the read and write
+    /// timeouts apply to the returned transport, which speaks Direct TCP over
+    /// the proxied stream so the SMB negotiate flow runs unchanged on top of it.
+    pub async fn connect_via_socks5<A>(
+        proxy: A,
+        target_host: &str,
+        target_port: u16,
+        credentials: Option<(&str, &str)>,
+        timeouts: TransportTimeouts,
+    ) -> std::io::Result<Self>
+    where
+        A: ToSocketAddrs,
+    {
+        let connect = async {
+            let mut stream = TcpStream::connect(proxy).await?;
+            socks5_handshake(&mut stream, target_host, target_port, credentials).await?;
+            Ok(stream)
+        };
+        let stream = with_deadline(timeouts.connect, "SOCKS5 connect timed out", connect).await?;
+        Ok(Self {
+            stream,
+            mode: TcpTransportMode::DirectTcp,
+            timeouts,
+        })
     }
 
-    async fn recv(&mut self) -> std::io::Result<Vec<u8>> {
-        match self.mode {
-            TcpTransportMode::DirectTcp => {
-                let mut header = [0_u8; 4];
-                self.stream.read_exact(&mut header).await?;
-                let payload_len = (usize::from(header[1]) << 16)
-                    | (usize::from(header[2]) << 8)
-                    | usize::from(header[3]);
-                let mut payload = vec![0; payload_len];
-                self.stream.read_exact(&mut payload).await?;
-
-                let mut frame = Vec::with_capacity(header.len() + payload_len);
-                frame.extend_from_slice(&header);
-                frame.extend_from_slice(&payload);
-                Ok(frame)
+    /// Connects to a TCP or NetBIOS target, bounding the connect phase by
+    /// the configured connect timeout and applying the read and write
+    /// timeouts to the returned transport.
+    pub async fn connect_target(
+        target: &TransportTarget,
+        timeouts: TransportTimeouts,
+    ) -> std::io::Result<Self> {
+        let connect = async {
+            match target.protocol() {
+                TransportProtocol::Netbios => Self::connect_netbios(target).await,
+                TransportProtocol::Tcp => {
+                    Self::connect((target.connect_host(), target.port())).await
+                }
+                TransportProtocol::Quic => Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "TCP transport cannot connect to a QUIC transport target",
+                )),
             }
+        };
+        let transport = with_deadline(timeouts.connect, "SMB connect timed out", connect).await?;
+        Ok(transport.with_timeouts(timeouts))
+    }
+
+    /// Applies read and write timeouts to subsequent frame I/O.
+    #[must_use]
+    pub fn with_timeouts(mut self, timeouts: TransportTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Returns the timeouts applied to this transport.
+    #[must_use]
+    pub fn timeouts(&self) -> TransportTimeouts {
+        self.timeouts
+    }
+
+    /// Replaces the read timeout for subsequent frames. `None` leaves reads unbounded.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeouts.read = timeout;
+    }
+
+    /// Replaces the write timeout for subsequent frames. `None` leaves writes unbounded.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeouts.write = timeout;
+    }
+
+    async fn recv_frame(&mut self) -> std::io::Result<Vec<u8>> {
+        match self.mode {
+            TcpTransportMode::DirectTcp => read_direct_tcp_frame(&mut self.stream).await,
             TcpTransportMode::NetbiosSession => loop {
                 let (message_type, payload) = read_netbios_packet(&mut self.stream).await?;
                 match message_type {
@@ -330,6 +478,120 @@ impl Transport for TokioTcpTransport {
     }
 }
 
+#[async_trait]
+impl Transport for TokioTcpTransport {
+    async fn send(&mut self, frame: &[u8]) -> std::io::Result<()> {
+        let write_timeout = self.timeouts.write;
+        with_deadline(
+            write_timeout,
+            "SMB write timed out",
+            self.stream.write_all(frame),
+        )
+        .await
+    }
+
+    async fn recv(&mut self) -> std::io::Result<Vec<u8>> {
+        let read_timeout = self.timeouts.read;
+        with_deadline(read_timeout, "SMB read timed out", self.recv_frame()).await
+    }
+
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        let write_timeout = self.timeouts.write;
+        with_deadline(
+            write_timeout,
+            "SMB shutdown timed out",
+            self.stream.shutdown(),
+        )
+        .await
+    }
+}
+
+/// Direct TCP framed transport over any `tokio` byte stream.
+///
+/// Use this to carry SMB over streams the crate does not open itself, such as
+/// TLS-wrapped or tunnelled connections, or an in-memory
+/// [`tokio::io::duplex`] pair in tests. Frames use the same 1-byte zero +
+/// 3-byte length layout as [`TokioTcpTransport`] in Direct TCP mode.
+#[derive(Debug)]
+pub struct StreamTransport<S> {
+    stream: S,
+}
+
+impl<S> StreamTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    /// Wraps an already-connected byte stream.
+    #[must_use]
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+
+    /// Returns the wrapped stream.
+    #[must_use]
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Consumes the transport and returns the wrapped stream.
+    #[must_use]
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+#[async_trait]
+impl<S> Transport for StreamTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn send(&mut self, frame: &[u8]) -> std::io::Result<()> {
+        self.stream.write_all(frame).await?;
+        self.stream.flush().await
+    }
+
+    async fn recv(&mut self) -> std::io::Result<Vec<u8>> {
+        read_direct_tcp_frame(&mut self.stream).await
+    }
+
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        self.stream.shutdown().await
+    }
+}
+
+async fn read_direct_tcp_frame<S>(stream: &mut S) -> std::io::Result<Vec<u8>>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut header = [0_u8; 4];
+    stream.read_exact(&mut header).await?;
+    let payload_len =
+        (usize::from(header[1]) << 16) | (usize::from(header[2]) << 8) | usize::from(header[3]);
+    let mut payload = vec![0; payload_len];
+    stream.read_exact(&mut payload).await?;
+
+    let mut frame = Vec::with_capacity(header.len() + payload_len);
+    frame.extend_from_slice(&header);
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+async fn with_deadline<F, R>(
+    limit: Option<Duration>,
+    message: &'static str,
+    operation: F,
+) -> std::io::Result<R>
+where
+    F: std::future::Future<Output = std::io::Result<R>>,
+{
+    match limit {
+        Some(limit) => timeout(limit, operation)
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, message))?,
+        None => operation.await,
+    }
+}
+
 async fn read_netbios_packet(stream: &mut TcpStream) -> std::io::Result<(u8, Vec<u8>)> {
     let mut header = [0_u8; 4];
     stream.read_exact(&mut header).await?;
@@ -340,6 +602,162 @@ async fn read_netbios_packet(stream: &mut TcpStream) -> std::io::Result<(u8, Vec
     Ok((header[0], payload))
 }
 
+async fn socks5_handshake(
+    stream: &mut TcpStream,
+    target_host: &str,
+    target_port: u16,
+    credentials: Option<(&str, &str)>,
+) -> std::io::Result<()> {
+    let greeting: &[u8] = if credentials.is_some() {
+        &[
+            SOCKS5_VERSION,
+            2,
+            SOCKS5_AUTH_NONE,
+            SOCKS5_AUTH_USERNAME_PASSWORD,
+        ]
+    } else {
+        &[SOCKS5_VERSION, 1, SOCKS5_AUTH_NONE]
+    };
+    stream.write_all(greeting).await?;
+
+    let mut choice = [0_u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice[0] != SOCKS5_VERSION {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "SOCKS5 proxy replied with an unexpected protocol version",
+        ));
+    }
+    match (choice[1], credentials) {
+        (SOCKS5_AUTH_NONE, _) => {}
+        (SOCKS5_AUTH_USERNAME_PASSWORD, Some((username, password))) => {
+            let username_len = socks5_field_len(username.len(), "SOCKS5 username")?;
+            let password_len = socks5_field_len(password.len(), "SOCKS5 password")?;
+            let mut request = Vec::with_capacity(3 + username.len() + password.len());
+            request.push(0x01);
+            request.push(username_len);
+            request.extend_from_slice(username.as_bytes());
+            request.push(password_len);
+            request.extend_from_slice(password.as_bytes());
+            stream.write_all(&request).await?;
+
+            let mut status = [0_u8; 2];
+            stream.read_exact(&mut status).await?;
+            if status[1] != 0x00 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    "SOCKS5 proxy rejected the username and password",
+                ));
+            }
+        }
+        (SOCKS5_AUTH_NO_ACCEPTABLE, _) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "SOCKS5 proxy accepted none of the offered authentication methods",
+            ));
+        }
+        (other, _) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("SOCKS5 proxy selected unsupported authentication method 0x{other:02x}"),
+            ));
+        }
+    }
+
+    let address = match target_host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+    {
+        Some(literal) => Some(literal.parse::<Ipv6Addr>().map(IpAddr::V6).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "SOCKS5 target host is not a valid bracketed IPv6 literal",
+            )
+        })?),
+        None => target_host.parse::<IpAddr>().ok(),
+    };
+    let mut request = vec![SOCKS5_VERSION, SOCKS5_COMMAND_CONNECT, 0x00];
+    match address {
+        Some(IpAddr::V4(address)) => {
+            request.push(SOCKS5_ADDRESS_IPV4);
+            request.extend_from_slice(&address.octets());
+        }
+        Some(IpAddr::V6(address)) => {
+            request.push(SOCKS5_ADDRESS_IPV6);
+            request.extend_from_slice(&address.octets());
+        }
+        None => {
+            request.push(SOCKS5_ADDRESS_DOMAIN);
+            request.push(socks5_field_len(target_host.len(), "SOCKS5 target host")?);
+            request.extend_from_slice(target_host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&target_port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0_u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS5_VERSION {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "SOCKS5 proxy replied with an unexpected protocol version",
+        ));
+    }
+    if reply[1] != 0x00 {
+        return Err(socks5_reply_error(reply[1]));
+    }
+    let bound_address_len = match reply[3] {
+        SOCKS5_ADDRESS_IPV4 => 4,
+        SOCKS5_ADDRESS_IPV6 => 16,
+        SOCKS5_ADDRESS_DOMAIN => {
+            let mut len = [0_u8; 1];
+            stream.read_exact(&mut len).await?;
+            usize::from(len[0])
+        }
+        other => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("SOCKS5 proxy replied with unknown address type 0x{other:02x}"),
+            ));
+        }
+    };
+    let mut bound = vec![0_u8; bound_address_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+fn socks5_field_len(len: usize, field: &str) -> std::io::Result<u8> {
+    u8::try_from(len).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{field} must be at most 255 bytes"),
+        )
+    })
+}
+
+fn socks5_reply_error(code: u8) -> std::io::Error {
+    let (kind, reason) = match code {
+        0x02 => (
+            std::io::ErrorKind::PermissionDenied,
+            "connection not allowed by ruleset",
+        ),
+        0x03 => (std::io::ErrorKind::Other, "network unreachable"),
+        0x04 => (std::io::ErrorKind::Other, "host unreachable"),
+        0x05 => (std::io::ErrorKind::ConnectionRefused, "connection refused"),
+        0x06 => (std::io::ErrorKind::TimedOut, "TTL expired"),
+        0x07 => (std::io::ErrorKind::Unsupported, "command not supported"),
+        0x08 => (
+            std::io::ErrorKind::Unsupported,
+            "address type not supported",
+        ),
+        _ => (std::io::ErrorKind::Other, "general failure"),
+    };
+    std::io::Error::new(
+        kind,
+        format!("SOCKS5 proxy could not connect to the target: {reason} (0x{code:02x})"),
+    )
+}
+
 fn default_netbios_called_name(server: &str) -> &str {
     if server.parse::<IpAddr>().is_ok() {
         return NETBIOS_WILDCARD_SERVER_NAME;
@@ -502,11 +920,19 @@ impl Transport for QuicTransport {
         frame.extend_from_slice(&payload);
         Ok(frame)
     }
+
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        self.send
+            .finish()
+            .map_err(|error| std::io::Error::other(error.to_string()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use tokio::io::AsyncWriteExt;
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::net::TcpListener;
 
     use smolder_proto::smb::netbios::{
@@ -515,8 +941,9 @@ mod tests {
     };
 
     use super::{
-        default_netbios_called_name, read_netbios_packet, SmbTransport, TokioTcpTransport,
-        TransportProtocol, TransportTarget, NETBIOS_CALLING_NAME,
+        default_netbios_called_name, read_netbios_packet, SmbTransport, StreamTransport,
+        TokioTcpTransport, TransportProtocol, TransportTarget, TransportTimeouts,
+        NETBIOS_CALLING_NAME,
     };
 
     #[cfg(feature = "quic")]
@@ -613,6 +1040,78 @@ mod tests {
         server.await.expect("server task should finish cleanly");
     }
 
+    #[tokio::test]
+    async fn read_timeout_bounds_a_silent_server() {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("listener should bind");
+        let port = listener
+            .local_addr()
+            .expect("listener should expose a local address")
+            .port();
+
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.expect("server should accept");
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            drop(socket);
+        });
+
+        let target = TransportTarget::tcp("127.0.0.1").with_port(port);
+        let timeouts = TransportTimeouts::new()
+            .with_connect_timeout(Duration::from_secs(5))
+            .with_read_timeout(Duration::from_millis(50));
+        let mut transport = TokioTcpTransport::connect_target(&target, timeouts)
+            .await
+            .expect("TCP transport should connect");
+        assert_eq!(transport.timeouts(), timeouts);
+
+        let error = transport
+            .recv_message()
+            .await
+            .expect_err("silent server should time out");
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+
+        server.await.expect("server task should finish cleanly");
+    }
+
+    #[tokio::test]
+    async fn read_timeout_can_be_tightened_after_connect() {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should expose a local address");
+
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.expect("server should accept");
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            drop(socket);
+        });
+
+        let mut transport = TokioTcpTransport::connect(addr)
+            .await
+            .expect("client should connect");
+        assert_eq!(transport.timeouts().read_timeout(), None);
+
+        transport.set_read_timeout(Some(Duration::from_millis(50)));
+        transport.set_write_timeout(Some(Duration::from_secs(1)));
+        assert_eq!(
+            transport.timeouts().write_timeout(),
+            Some(Duration::from_secs(1))
+        );
+        let error = transport
+            .recv_message()
+            .await
+            .expect_err("silent server should time out");
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+
+        transport.set_read_timeout(None);
+        assert_eq!(transport.timeouts().read_timeout(), None);
+
+        server.await.expect("server task should finish cleanly");
+    }
+
     #[tokio::test]
     async fn direct_tcp_transport_rejects_non_session_messages() {
         let listener = TcpListener::bind(("127.0.0.1", 0))
@@ -642,6 +1141,339 @@ mod tests {
         server.await.expect("server task should finish cleanly");
     }
 
+    #[tokio::test]
+    async fn direct_tcp_transport_reassembles_frames_split_across_reads() {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should expose a local address");
+        let payload: Vec<u8> = (0..=255).collect();
+        let frame = SessionMessage::encode_payload(&payload).expect("payload should encode");
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.expect("server should accept");
+            for byte in frame {
+                socket.write_all(&[byte]).await.expect("byte should write");
+                socket.flush().await.expect("byte should flush");
+                tokio::task::yield_now().await;
+            }
+        });
+
+        let mut transport = TokioTcpTransport::connect(addr)
+            .await
+            .expect("client should connect");
+        let message = transport
+            .recv_message()
+            .await
+            .expect("split frame should be reassembled");
+        assert_eq!(message, payload);
+
+        server.await.expect("server task should finish cleanly");
+    }
+
+    #[tokio::test]
+    async fn direct_tcp_transport_reports_truncated_frames_as_eof() {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should expose a local address");
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.expect("server should accept");
+            socket
+                .write_all(&[SESSION_MESSAGE, 0x00, 0x00, 0x40, 0xfe, b'S', b'M', b'B'])
+                .await
+                .expect("partial frame should write");
+        });
+
+        let mut transport = TokioTcpTransport::connect(addr)
+            .await
+            .expect("client should connect");
+        let error = transport
+            .recv_message()
+            .await
+            .expect_err("truncated frame should fail");
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        server.await.expect("server task should finish cleanly");
+    }
+
+    #[tokio::test]
+    async fn stream_transport_frames_messages_over_in_memory_streams() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut transport = StreamTransport::new(client);
+
+        transport
+            .send_message(b"PING")
+            .await
+            .expect("stream transport should send");
+        let mut frame = [0_u8; 8];
+        server
+            .read_exact(&mut frame)
+            .await
+            .expect("server should read the frame");
+        assert_eq!(&frame, b"\x00\x00\x00\x04PING");
+
+        server
+            .write_all(
+                &SessionMessage::encode_payload(b"\xfeSMB").expect("session message should encode"),
+            )
+            .await
+            .expect("server should write the reply");
+        let message = transport
+            .recv_message()
+            .await
+            .expect("stream transport should receive");
+        assert_eq!(message, b"\xfeSMB");
+    }
+
+    #[tokio::test]
+    async fn stream_transport_shutdown_signals_end_of_stream() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut transport = StreamTransport::new(client);
+
+        transport
+            .shutdown_transport()
+            .await
+            .expect("stream transport should shut down");
+        let mut buffer = Vec::new();
+        let read = server
+            .read_to_end(&mut buffer)
+            .await
+            .expect("server should observe end of stream");
+        assert_eq!(read, 0);
+    }
+
+    #[tokio::test]
+    async fn socks5_transport_authenticates_and_connects_by_host_name() {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should expose a local address");
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.expect("server should accept");
+            let mut greeting = [0_u8; 4];
+            socket
+                .read_exact(&mut greeting)
+                .await
+                .expect("greeting should read");
+            assert_eq!(greeting, [0x05, 0x02, 0x00, 0x02]);
+            socket
+                .write_all(&[0x05, 0x02])
+                .await
+                .expect("method choice should write");
+
+            let mut auth = [0_u8; 12];
+            socket
+                .read_exact(&mut auth)
+                .await
+                .expect("auth should read");
+            assert_eq!(&auth, b"\x01\x05alice\x04pass");
+            socket
+                .write_all(&[0x01, 0x00])
+                .await
+                .expect("auth status should write");
+
+            let mut connect = [0_u8; 22];
+            socket
+                .read_exact(&mut connect)
+                .await
+                .expect("connect request should read");
+            assert_eq!(&connect[..5], &[0x05, 0x01, 0x00, 0x03, 15]);
+            assert_eq!(&connect[5..20], b"files.lab.local");
+            assert_eq!(&connect[20..], &445_u16.to_be_bytes());
+            socket
+                .write_all(&[0x05, 0x00, 0x00, 0x01, 10, 0, 0, 5, 0x01, 0xbd])
+                .await
+                .expect("connect reply should write");
+
+            socket
+                .write_all(
+                    &SessionMessage::encode_payload(b"\xfeSMB")
+                        .expect("session message should encode"),
+                )
+                .await
+                .expect("session payload should write");
+        });
+
+        let timeouts = TransportTimeouts::new()
+            .with_connect_timeout(Duration::from_secs(5))
+            .with_read_timeout(Duration::from_secs(5));
+        let mut transport = TokioTcpTransport::connect_via_socks5(
+            addr,
+            "files.lab.local",
+            445,
+            Some(("alice", "pass")),
+            timeouts,
+        )
+        .await
+        .expect("SOCKS5 transport should connect");
+        assert_eq!(transport.timeouts(), timeouts);
+        let message = transport
+            .recv_message()
+            .await
+            .expect("proxied transport should read the SMB payload");
+        assert_eq!(message, b"\xfeSMB");
+
+        server.await.expect("server task should finish cleanly");
+    }
+
+    #[tokio::test]
+    async fn socks5_transport_reports_refused_targets() {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should expose a local address");
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.expect("server should accept");
+            let mut greeting = [0_u8; 3];
+            socket
+                .read_exact(&mut greeting)
+                .await
+                .expect("greeting should read");
+            assert_eq!(greeting, [0x05, 0x01, 0x00]);
+            socket
+                .write_all(&[0x05, 0x00])
+                .await
+                .expect("method choice should write");
+
+            let mut connect = [0_u8; 10];
+            socket
+                .read_exact(&mut connect)
+                .await
+                .expect("connect request should read");
+            assert_eq!(&connect[..8], &[0x05, 0x01, 0x00, 0x01, 192, 0, 2, 10]);
+            socket
+                .write_all(&[0x05, 0x05, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                .await
+                .expect("connect reply should write");
+        });
+
+        let error = TokioTcpTransport::connect_via_socks5(
+            addr,
+            "192.0.2.10",
+            445,
+            None,
+            TransportTimeouts::default(),
+        )
+        .await
+        .expect_err("refused target should fail");
+        assert_eq!(error.kind(), std::io::ErrorKind::ConnectionRefused);
+
+        server.await.expect("server task should finish cleanly");
+    }
+
+    #[tokio::test]
+    async fn socks5_transport_sends_bracketed_ipv6_literals_as_addresses() {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should expose a local address");
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.expect("server should accept");
+            let mut greeting = [0_u8; 3];
+            socket
+                .read_exact(&mut greeting)
+                .await
+                .expect("greeting should read");
+            socket
+                .write_all(&[0x05, 0x00])
+                .await
+                .expect("method choice should write");
+
+            let mut connect = [0_u8; 22];
+            socket
+                .read_exact(&mut connect)
+                .await
+                .expect("connect request should read");
+            assert_eq!(&connect[..4], &[0x05, 0x01, 0x00, 0x04]);
+            assert_eq!(&connect[4..20], &std::net::Ipv6Addr::LOCALHOST.octets());
+            assert_eq!(&connect[20..], &445_u16.to_be_bytes());
+            socket
+                .write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                .await
+                .expect("connect reply should write");
+
+            let (mut socket, _) = listener.accept().await.expect("server should accept");
+            socket
+                .read_exact(&mut greeting)
+                .await
+                .expect("greeting should read");
+            socket
+                .write_all(&[0x05, 0x00])
+                .await
+                .expect("method choice should write");
+            let mut rest = Vec::new();
+            socket
+                .read_to_end(&mut rest)
+                .await
+                .expect("client should hang up");
+            assert!(rest.is_empty(), "no connect request should be sent");
+        });
+
+        TokioTcpTransport::connect_via_socks5(
+            addr,
+            "[::1]",
+            445,
+            None,
+            TransportTimeouts::default(),
+        )
+        .await
+        .expect("a bracketed IPv6 target should connect");
+
+        let error = TokioTcpTransport::connect_via_socks5(
+            addr,
+            "[files.lab.local]",
+            445,
+            None,
+            TransportTimeouts::default(),
+        )
+        .await
+        .expect_err("a bracketed host name should be rejected");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        server.await.expect("server task should finish cleanly");
+    }
+
+    #[tokio::test]
+    async fn socks5_transport_times_out_a_silent_proxy() {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should expose a local address");
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.expect("server should accept");
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            drop(socket);
+        });
+
+        let error = TokioTcpTransport::connect_via_socks5(
+            addr,
+            "files.lab.local",
+            445,
+            None,
+            TransportTimeouts::new().with_connect_timeout(Duration::from_millis(50)),
+        )
+        .await
+        .expect_err("a proxy that never answers should time out");
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+        server.abort();
+    }
+
     #[cfg(feature = "quic")]
     #[test]
     fn quic_client_config_sets_smb_alpn() {
@@ -934,10 +934,10 @@ fn append_auth_verifier(body: &mut Vec<u8>, auth_verifier: Option<&AuthVerifier>
     auth_verifier.auth_value.len() as u16
 }
 
-fn split_auth_verifier<'a>(
-    body: &'a [u8],
+fn split_auth_verifier(
+    body: &[u8],
     auth_length: u16,
-) -> Result<(&'a [u8], Option<AuthVerifier>), ProtocolError> {
+) -> Result<(&[u8], Option<AuthVerifier>), ProtocolError> {
     if auth_length == 0 {
         return Ok((body, None));
     }
@@ -0,0 +1,96 @@
+//! Conversions between Windows `FILETIME` values and [`SystemTime`].
+//!
+//! A `FILETIME` counts 100-nanosecond ticks since 1601-01-01 UTC. SMB carries
+//! it as a little-endian `u64` in file-information and directory records.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number of `FILETIME` ticks per second.
+pub const TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// Seconds between the `FILETIME` epoch (1601) and the Unix epoch (1970).
+pub const SECONDS_TO_UNIX_EPOCH: u64 = 11_644_473_600;
+
+const UNIX_EPOCH_TICKS: u64 = SECONDS_TO_UNIX_EPOCH * TICKS_PER_SECOND;
+
+/// Converts a `FILETIME` tick count into a [`SystemTime`].
+///
+/// Values before 1970 map to times before [`UNIX_EPOCH`]. If the platform
+/// cannot represent the instant, the result saturates to [`UNIX_EPOCH`].
+#[must_use]
+pub fn filetime_to_system_time(filetime: u64) -> SystemTime {
+    if filetime >= UNIX_EPOCH_TICKS {
+        UNIX_EPOCH.checked_add(ticks_to_duration(filetime - UNIX_EPOCH_TICKS))
+    } else {
+        UNIX_EPOCH.checked_sub(ticks_to_duration(UNIX_EPOCH_TICKS - filetime))
+    }
+    .unwrap_or(UNIX_EPOCH)
+}
+
+/// Converts a [`SystemTime`] into a `FILETIME` tick count.
+///
+/// Times before 1601 saturate to `0` and times past the `u64` range saturate
+/// to `u64::MAX`. Sub-tick precision is truncated.
+#[must_use]
+pub fn system_time_to_filetime(time: SystemTime) -> u64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => UNIX_EPOCH_TICKS.saturating_add(duration_to_ticks(after)),
+        Err(before) => UNIX_EPOCH_TICKS.saturating_sub(duration_to_ticks(before.duration())),
+    }
+}
+
+fn ticks_to_duration(ticks: u64) -> Duration {
+    Duration::new(
+        ticks / TICKS_PER_SECOND,
+        ((ticks % TICKS_PER_SECOND) * 100) as u32,
+    )
+}
+
+fn duration_to_ticks(duration: Duration) -> u64 {
+    let ticks = duration.as_nanos() / 100;
+    u64::try_from(ticks).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{filetime_to_system_time, system_time_to_filetime, UNIX_EPOCH_TICKS};
+
+    #[test]
+    fn unix_epoch_matches_known_filetime() {
+        assert_eq!(UNIX_EPOCH_TICKS, 116_444_736_000_000_000);
+        assert_eq!(filetime_to_system_time(UNIX_EPOCH_TICKS), UNIX_EPOCH);
+        assert_eq!(system_time_to_filetime(UNIX_EPOCH), UNIX_EPOCH_TICKS);
+    }
+
+    #[test]
+    fn known_timestamp_roundtrips() {
+        // 2021-01-01T00:00:00.1234567Z
+        let filetime = 132_539_328_001_234_567;
+        let time = UNIX_EPOCH + Duration::new(1_609_459_200, 123_456_700);
+
+        assert_eq!(filetime_to_system_time(filetime), time);
+        assert_eq!(system_time_to_filetime(time), filetime);
+    }
+
+    #[test]
+    fn pre_unix_epoch_values_are_preserved() {
+        let time = filetime_to_system_time(0);
+
+        assert_eq!(
+            UNIX_EPOCH.duration_since(time).expect("1601 precedes 1970"),
+            Duration::from_secs(11_644_473_600)
+        );
+        assert_eq!(system_time_to_filetime(time), 0);
+    }
+
+    #[test]
+    fn out_of_range_times_saturate() {
+        let before_1601 = UNIX_EPOCH - Duration::from_secs(11_644_473_601);
+        assert_eq!(system_time_to_filetime(before_1601), 0);
+
+        let far_future = filetime_to_system_time(u64::MAX) + Duration::from_secs(1);
+        assert_eq!(system_time_to_filetime(far_future), u64::MAX);
+    }
+}
@@ -2,7 +2,9 @@
 
 mod error;
 pub mod compression;
+pub mod filetime;
 pub mod netbios;
+pub mod security;
 pub mod smb2;
 pub mod status;
 pub mod transform;
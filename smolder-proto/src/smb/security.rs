@@ -0,0 +1,946 @@
+//! Windows security descriptors returned by security `QUERY_INFO` requests.
+//!
+//! The layouts follow MS-DTYP: a self-relative `SECURITY_DESCRIPTOR` points at
+//! optional owner and group SIDs and optional system and discretionary ACLs.
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitflags::bitflags;
+use bytes::{BufMut, BytesMut};
+
+use super::ProtocolError;
+
+const SECURITY_DESCRIPTOR_HEADER_LEN: usize = 20;
+const ACL_HEADER_LEN: usize = 8;
+const ACE_HEADER_LEN: usize = 4;
+const ACE_OBJECT_TYPE_PRESENT: u32 = 0x0000_0001;
+const ACE_INHERITED_OBJECT_TYPE_PRESENT: u32 = 0x0000_0002;
+
+bitflags! {
+    /// Parts of a security descriptor requested from or applied to a handle.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct SecurityInformation: u32 {
+        /// The owner SID.
+        const OWNER = 0x0000_0001;
+        /// The primary group SID.
+        const GROUP = 0x0000_0002;
+        /// The discretionary ACL.
+        const DACL = 0x0000_0004;
+        /// The system ACL. Reading it requires `ACCESS_SYSTEM_SECURITY`.
+        const SACL = 0x0000_0008;
+        /// The mandatory integrity label.
+        const LABEL = 0x0000_0010;
+    }
+}
+
+bitflags! {
+    /// `SECURITY_DESCRIPTOR` control bits.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct SecurityDescriptorControl: u16 {
+        /// The owner was set by a default mechanism.
+        const OWNER_DEFAULTED = 0x0001;
+        /// The group was set by a default mechanism.
+        const GROUP_DEFAULTED = 0x0002;
+        /// A DACL is present.
+        const DACL_PRESENT = 0x0004;
+        /// The DACL was set by a default mechanism.
+        const DACL_DEFAULTED = 0x0008;
+        /// A SACL is present.
+        const SACL_PRESENT = 0x0010;
+        /// The SACL was set by a default mechanism.
+        const SACL_DEFAULTED = 0x0020;
+        /// The DACL is trusted.
+        const DACL_TRUSTED = 0x0040;
+        /// Server-side security is in effect.
+        const SERVER_SECURITY = 0x0080;
+        /// DACL auto-inheritance was requested.
+        const DACL_AUTO_INHERIT_REQ = 0x0100;
+        /// SACL auto-inheritance was requested.
+        const SACL_AUTO_INHERIT_REQ = 0x0200;
+        /// The DACL was created through auto-inheritance.
+        const DACL_AUTO_INHERITED = 0x0400;
+        /// The SACL was created through auto-inheritance.
+        const SACL_AUTO_INHERITED = 0x0800;
+        /// The DACL does not inherit from its parent.
+        const DACL_PROTECTED = 0x1000;
+        /// The SACL does not inherit from its parent.
+        const SACL_PROTECTED = 0x2000;
+        /// The resource-manager control byte is valid.
+        const RM_CONTROL_VALID = 0x4000;
+        /// The descriptor is in self-relative form.
+        const SELF_RELATIVE = 0x8000;
+    }
+}
+
+bitflags! {
+    /// `ACE_HEADER` inheritance and audit flags.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct AceFlags: u8 {
+        /// Non-container children inherit the ACE.
+        const OBJECT_INHERIT = 0x01;
+        /// Container children inherit the ACE.
+        const CONTAINER_INHERIT = 0x02;
+        /// Inheritance stops at the immediate children.
+        const NO_PROPAGATE_INHERIT = 0x04;
+        /// The ACE only applies to children, not to this object.
+        const INHERIT_ONLY = 0x08;
+        /// The ACE was inherited from a parent.
+        const INHERITED = 0x10;
+        /// Audit successful access attempts.
+        const SUCCESSFUL_ACCESS = 0x40;
+        /// Audit failed access attempts.
+        const FAILED_ACCESS = 0x80;
+    }
+}
+
+/// A binary security identifier.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sid {
+    /// SID revision, always `1` for SIDs in the wild.
+    pub revision: u8,
+    /// Big-endian identifier authority.
+    pub identifier_authority: [u8; 6],
+    /// Subauthority values, including the trailing RID.
+    pub sub_authorities: Vec<u32>,
+}
+
+impl Sid {
+    /// Builds a revision-1 SID from a numeric authority and subauthorities.
+    ///
+    /// Only the low 48 bits of `authority` are representable.
+    #[must_use]
+    pub fn new(authority: u64, sub_authorities: &[u32]) -> Self {
+        let bytes = authority.to_be_bytes();
+        Self {
+            revision: 1,
+            identifier_authority: bytes[2..].try_into().expect("slice len"),
+            sub_authorities: sub_authorities.to_vec(),
+        }
+    }
+
+    /// `S-1-1-0`, the Everyone group.
+    #[must_use]
+    pub fn everyone() -> Self {
+        Self::new(1, &[0])
+    }
+
+    /// `S-1-3-0`, the Creator Owner placeholder used in inheritable ACEs.
+    #[must_use]
+    pub fn creator_owner() -> Self {
+        Self::new(3, &[0])
+    }
+
+    /// `S-1-5-11`, the Authenticated Users group.
+    #[must_use]
+    pub fn authenticated_users() -> Self {
+        Self::new(5, &[11])
+    }
+
+    /// `S-1-5-18`, the Local System account.
+    #[must_use]
+    pub fn local_system() -> Self {
+        Self::new(5, &[18])
+    }
+
+    /// `S-1-5-32-544`, the built-in Administrators alias.
+    #[must_use]
+    pub fn builtin_administrators() -> Self {
+        Self::new(5, &[32, 544])
+    }
+
+    /// `S-1-5-32-545`, the built-in Users alias.
+    #[must_use]
+    pub fn builtin_users() -> Self {
+        Self::new(5, &[32, 545])
+    }
+
+    /// Returns the identifier authority as a number.
+    #[must_use]
+    pub fn authority(&self) -> u64 {
+        let mut bytes = [0u8; 8];
+        bytes[2..].copy_from_slice(&self.identifier_authority);
+        u64::from_be_bytes(bytes)
+    }
+
+    /// Returns the relative identifier, the last subauthority, if any.
+    #[must_use]
+    pub fn rid(&self) -> Option<u32> {
+        self.sub_authorities.last().copied()
+    }
+
+    /// Returns the account name of a well-known SID, if this is one.
+    #[must_use]
+    pub fn well_known_name(&self) -> Option<&'static str> {
+        let name = match (self.authority(), self.sub_authorities.as_slice()) {
+            (1, [0]) => "Everyone",
+            (3, [0]) => "CREATOR OWNER",
+            (3, [1]) => "CREATOR GROUP",
+            (5, [7]) => "NT AUTHORITY\\ANONYMOUS LOGON",
+            (5, [11]) => "NT AUTHORITY\\Authenticated Users",
+            (5, [18]) => "NT AUTHORITY\\SYSTEM",
+            (5, [19]) => "NT AUTHORITY\\LOCAL SERVICE",
+            (5, [20]) => "NT AUTHORITY\\NETWORK SERVICE",
+            (5, [32, 544]) => "BUILTIN\\Administrators",
+            (5, [32, 545]) => "BUILTIN\\Users",
+            (5, [32, 546]) => "BUILTIN\\Guests",
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    /// Returns the encoded size of the SID in bytes.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        8 + 4 * self.sub_authorities.len()
+    }
+
+    /// Serializes the SID.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = BytesMut::with_capacity(self.encoded_len());
+        out.put_u8(self.revision);
+        out.put_u8(self.sub_authorities.len() as u8);
+        out.extend_from_slice(&self.identifier_authority);
+        for sub_authority in &self.sub_authorities {
+            out.put_u32_le(*sub_authority);
+        }
+        out.to_vec()
+    }
+
+    /// Parses a SID from the start of `input`, ignoring any trailing bytes.
+    pub fn decode(input: &[u8]) -> Result<Self, ProtocolError> {
+        if input.len() < 8 {
+            return Err(ProtocolError::UnexpectedEof { field: "sid" });
+        }
+        let revision = input[0];
+        let sub_authority_count = usize::from(input[1]);
+        let identifier_authority = input[2..8].try_into().expect("slice len");
+        let end = 8 + 4 * sub_authority_count;
+        if input.len() < end {
+            return Err(ProtocolError::UnexpectedEof {
+                field: "sid_sub_authorities",
+            });
+        }
+        let sub_authorities = input[8..end]
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().expect("chunk len")))
+            .collect();
+        Ok(Self {
+            revision,
+            identifier_authority,
+            sub_authorities,
+        })
+    }
+}
+
+impl fmt::Display for Sid {
+    /// Formats the SID in the canonical `S-1-5-21-...` string form.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let authority = self.authority();
+        if authority >> 32 == 0 {
+            write!(f, "S-{}-{authority}", self.revision)?;
+        } else {
+            write!(f, "S-{}-0x{authority:012X}", self.revision)?;
+        }
+        for sub_authority in &self.sub_authorities {
+            write!(f, "-{sub_authority}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Sid {
+    type Err = ProtocolError;
+
+    /// Parses the canonical `S-R-I-S...` string form.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = ProtocolError::InvalidField {
+            field: "sid",
+            reason: "malformed SID string",
+        };
+        let mut parts = value.split('-');
+        if !parts
+            .next()
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("S"))
+        {
+            return Err(invalid);
+        }
+        let revision = parts
+            .next()
+            .and_then(|part| part.parse::<u8>().ok())
+            .ok_or(invalid.clone())?;
+        let authority = parts
+            .next()
+            .and_then(
+                |part| match part.strip_prefix("0x").or(part.strip_prefix("0X")) {
+                    Some(hex) => u64::from_str_radix(hex, 16).ok(),
+                    None => part.parse::<u64>().ok(),
+                },
+            )
+            .filter(|authority| authority >> 48 == 0)
+            .ok_or(invalid.clone())?;
+        let sub_authorities = parts
+            .map(|part| part.parse::<u32>().map_err(|_| invalid.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if sub_authorities.len() > usize::from(u8::MAX) {
+            return Err(invalid);
+        }
+        let mut sid = Self::new(authority, &sub_authorities);
+        sid.revision = revision;
+        Ok(sid)
+    }
+}
+
+/// ACE kinds defined by MS-DTYP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AceType {
+    /// `ACCESS_ALLOWED_ACE_TYPE`
+    AccessAllowed,
+    /// `ACCESS_DENIED_ACE_TYPE`
+    AccessDenied,
+    /// `SYSTEM_AUDIT_ACE_TYPE`
+    SystemAudit,
+    /// `ACCESS_ALLOWED_OBJECT_ACE_TYPE`
+    AccessAllowedObject,
+    /// `ACCESS_DENIED_OBJECT_ACE_TYPE`
+    AccessDeniedObject,
+    /// `SYSTEM_AUDIT_OBJECT_ACE_TYPE`
+    SystemAuditObject,
+    /// `ACCESS_ALLOWED_CALLBACK_ACE_TYPE`
+    AccessAllowedCallback,
+    /// `ACCESS_DENIED_CALLBACK_ACE_TYPE`
+    AccessDeniedCallback,
+    /// `SYSTEM_MANDATORY_LABEL_ACE_TYPE`
+    SystemMandatoryLabel,
+    /// Another ACE type that carries its SID after the access mask.
+    Other(u8),
+}
+
+impl AceType {
+    fn from_raw(value: u8) -> Self {
+        match value {
+            0x00 => Self::AccessAllowed,
+            0x01 => Self::AccessDenied,
+            0x02 => Self::SystemAudit,
+            0x05 => Self::AccessAllowedObject,
+            0x06 => Self::AccessDeniedObject,
+            0x07 => Self::SystemAuditObject,
+            0x09 => Self::AccessAllowedCallback,
+            0x0a => Self::AccessDeniedCallback,
+            0x11 => Self::SystemMandatoryLabel,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Returns the raw `AceType` byte.
+    #[must_use]
+    pub fn to_raw(self) -> u8 {
+        match self {
+            Self::AccessAllowed => 0x00,
+            Self::AccessDenied => 0x01,
+            Self::SystemAudit => 0x02,
+            Self::AccessAllowedObject => 0x05,
+            Self::AccessDeniedObject => 0x06,
+            Self::SystemAuditObject => 0x07,
+            Self::AccessAllowedCallback => 0x09,
+            Self::AccessDeniedCallback => 0x0a,
+            Self::SystemMandatoryLabel => 0x11,
+            Self::Other(other) => other,
+        }
+    }
+
+    /// Returns the SDDL abbreviation of the ACE type, if it has one.
+    #[must_use]
+    pub fn sddl(self) -> Option<&'static str> {
+        let abbreviation = match self {
+            Self::AccessAllowed => "A",
+            Self::AccessDenied => "D",
+            Self::SystemAudit => "AU",
+            Self::AccessAllowedObject => "OA",
+            Self::AccessDeniedObject => "OD",
+            Self::SystemAuditObject => "OU",
+            Self::AccessAllowedCallback => "XA",
+            Self::AccessDeniedCallback => "XD",
+            Self::SystemMandatoryLabel => "ML",
+            Self::Other(_) => return None,
+        };
+        Some(abbreviation)
+    }
+
+    /// Returns true for object ACEs, which carry GUIDs before the SID.
+    #[must_use]
+    pub fn is_object(self) -> bool {
+        matches!(self.to_raw(), 0x05..=0x08 | 0x0b | 0x0c | 0x0f | 0x10)
+    }
+}
+
+/// One access control entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ace {
+    /// The ACE kind.
+    pub ace_type: AceType,
+    /// Inheritance and audit flags.
+    pub flags: AceFlags,
+    /// Access rights granted, denied, or audited.
+    pub access_mask: u32,
+    /// The trustee the ACE applies to.
+    pub sid: Sid,
+    /// The object type GUID of an object ACE, in wire byte order.
+    pub object_type: Option<[u8; 16]>,
+    /// The inherited object type GUID of an object ACE, in wire byte order.
+    pub inherited_object_type: Option<[u8; 16]>,
+    /// Bytes that follow the SID, such as the application data of a callback
+    /// ACE or a conditional expression. They are re-encoded verbatim.
+    pub application_data: Vec<u8>,
+}
+
+impl fmt::Display for Ace {
+    /// Formats the ACE as an SDDL ACE string such as `(A;OICI;0x001f01ff;;;S-1-5-18)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ace_type.sddl() {
+            Some(abbreviation) => write!(f, "({abbreviation};")?,
+            None => write!(f, "(0x{:02x};", self.ace_type.to_raw())?,
+        }
+        for (flag, abbreviation) in [
+            (AceFlags::OBJECT_INHERIT, "OI"),
+            (AceFlags::CONTAINER_INHERIT, "CI"),
+            (AceFlags::NO_PROPAGATE_INHERIT, "NP"),
+            (AceFlags::INHERIT_ONLY, "IO"),
+            (AceFlags::INHERITED, "ID"),
+            (AceFlags::SUCCESSFUL_ACCESS, "SA"),
+            (AceFlags::FAILED_ACCESS, "FA"),
+        ] {
+            if self.flags.contains(flag) {
+                f.write_str(abbreviation)?;
+            }
+        }
+        write!(f, ";0x{:08x};;;{})", self.access_mask, self.sid)
+    }
+}
+
+impl Ace {
+    /// Creates an ACE without object GUIDs or application data.
+    #[must_use]
+    pub fn new(ace_type: AceType, flags: AceFlags, access_mask: u32, sid: Sid) -> Self {
+        Self {
+            ace_type,
+            flags,
+            access_mask,
+            sid,
+            object_type: None,
+            inherited_object_type: None,
+            application_data: Vec::new(),
+        }
+    }
+
+    fn encode_into(&self, out: &mut BytesMut) -> Result<(), ProtocolError> {
+        let is_object = self.ace_type.is_object();
+        if !is_object && (self.object_type.is_some() || self.inherited_object_type.is_some()) {
+            return Err(ProtocolError::InvalidField {
+                field: "ace_object_type",
+                reason: "only object ACEs can carry object type GUIDs",
+            });
+        }
+        let guids = [self.object_type, self.inherited_object_type];
+        let object_len = if is_object {
+            4 + 16 * guids.iter().flatten().count()
+        } else {
+            0
+        };
+        let size =
+            ACE_HEADER_LEN + 4 + object_len + self.sid.encoded_len() + self.application_data.len();
+        let size = u16::try_from(size).map_err(|_| ProtocolError::InvalidField {
+            field: "ace_size",
+            reason: "ace exceeds 65535 bytes",
+        })?;
+        out.put_u8(self.ace_type.to_raw());
+        out.put_u8(self.flags.bits());
+        out.put_u16_le(size);
+        out.put_u32_le(self.access_mask);
+        if is_object {
+            let mut object_flags = 0;
+            if self.object_type.is_some() {
+                object_flags |= ACE_OBJECT_TYPE_PRESENT;
+            }
+            if self.inherited_object_type.is_some() {
+                object_flags |= ACE_INHERITED_OBJECT_TYPE_PRESENT;
+            }
+            out.put_u32_le(object_flags);
+            for guid in guids.iter().flatten() {
+                out.extend_from_slice(guid);
+            }
+        }
+        out.extend_from_slice(&self.sid.encode());
+        out.extend_from_slice(&self.application_data);
+        Ok(())
+    }
+
+    fn decode(input: &[u8]) -> Result<(Self, usize), ProtocolError> {
+        if input.len() < ACE_HEADER_LEN + 4 {
+            return Err(ProtocolError::UnexpectedEof { field: "ace" });
+        }
+        let ace_type = AceType::from_raw(input[0]);
+        let flags = AceFlags::from_bits_retain(input[1]);
+        let size = usize::from(u16::from_le_bytes([input[2], input[3]]));
+        if size < ACE_HEADER_LEN + 4 || size > input.len() {
+            return Err(ProtocolError::InvalidField {
+                field: "ace_size",
+                reason: "ace extends past acl",
+            });
+        }
+        let body = &input[..size];
+        let access_mask = u32::from_le_bytes(body[4..8].try_into().expect("slice len"));
+        let mut sid_offset = ACE_HEADER_LEN + 4;
+        let mut object_type = None;
+        let mut inherited_object_type = None;
+        if ace_type.is_object() {
+            let object_flags = body
+                .get(sid_offset..sid_offset + 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().expect("slice len")))
+                .ok_or(ProtocolError::UnexpectedEof {
+                    field: "ace_object_flags",
+                })?;
+            sid_offset += 4;
+            let mut read_guid = |field| {
+                let guid: [u8; 16] = body
+                    .get(sid_offset..sid_offset + 16)
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or(ProtocolError::UnexpectedEof { field })?;
+                sid_offset += 16;
+                Ok::<_, ProtocolError>(guid)
+            };
+            if object_flags & ACE_OBJECT_TYPE_PRESENT != 0 {
+                object_type = Some(read_guid("ace_object_type")?);
+            }
+            if object_flags & ACE_INHERITED_OBJECT_TYPE_PRESENT != 0 {
+                inherited_object_type = Some(read_guid("ace_inherited_object_type")?);
+            }
+        }
+        let sid = Sid::decode(body.get(sid_offset..).unwrap_or_default())?;
+        let application_data = body[sid_offset + sid.encoded_len()..].to_vec();
+        Ok((
+            Self {
+                ace_type,
+                flags,
+                access_mask,
+                sid,
+                object_type,
+                inherited_object_type,
+                application_data,
+            },
+            size,
+        ))
+    }
+}
+
+/// An access control list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Acl {
+    /// ACL revision: `2`, or `4` when object ACEs are present.
+    pub revision: u8,
+    /// Entries in evaluation order.
+    pub aces: Vec<Ace>,
+}
+
+impl Acl {
+    fn encode_into(&self, out: &mut BytesMut) -> Result<(), ProtocolError> {
+        let mut aces = BytesMut::new();
+        for ace in &self.aces {
+            ace.encode_into(&mut aces)?;
+        }
+        let size = u16::try_from(ACL_HEADER_LEN + aces.len()).map_err(|_| {
+            ProtocolError::InvalidField {
+                field: "acl_size",
+                reason: "acl exceeds 65535 bytes",
+            }
+        })?;
+        let ace_count =
+            u16::try_from(self.aces.len()).map_err(|_| ProtocolError::InvalidField {
+                field: "acl_ace_count",
+                reason: "acl holds more than 65535 aces",
+            })?;
+        out.put_u8(self.revision);
+        out.put_u8(0);
+        out.put_u16_le(size);
+        out.put_u16_le(ace_count);
+        out.put_u16_le(0);
+        out.extend_from_slice(&aces);
+        Ok(())
+    }
+
+    fn decode(input: &[u8]) -> Result<Self, ProtocolError> {
+        if input.len() < ACL_HEADER_LEN {
+            return Err(ProtocolError::UnexpectedEof { field: "acl" });
+        }
+        let revision = input[0];
+        let size = usize::from(u16::from_le_bytes([input[2], input[3]]));
+        let ace_count = u16::from_le_bytes([input[4], input[5]]);
+        if size < ACL_HEADER_LEN || size > input.len() {
+            return Err(ProtocolError::InvalidField {
+                field: "acl_size",
+                reason: "acl extends past security descriptor",
+            });
+        }
+        let mut cursor = &input[ACL_HEADER_LEN..size];
+        let mut aces = Vec::with_capacity(usize::from(ace_count));
+        for _ in 0..ace_count {
+            let (ace, consumed) = Ace::decode(cursor)?;
+            aces.push(ace);
+            cursor = &cursor[consumed..];
+        }
+        Ok(Self { revision, aces })
+    }
+}
+
+/// A decoded self-relative security descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityDescriptor {
+    /// Descriptor control bits.
+    pub control: SecurityDescriptorControl,
+    /// The owner SID, when requested and present.
+    pub owner: Option<Sid>,
+    /// The primary group SID, when requested and present.
+    pub group: Option<Sid>,
+    /// The system ACL, when requested and present.
+    pub sacl: Option<Acl>,
+    /// The discretionary ACL, when requested and present.
+    ///
+    /// `None` with `DACL_PRESENT` clear grants everyone full access, while an
+    /// empty DACL denies everyone.
+    pub dacl: Option<Acl>,
+}
+
+impl SecurityDescriptor {
+    /// Serializes the descriptor in self-relative form.
+    ///
+    /// Fails when an ACL or ACE does not fit its 16-bit size field, or when a
+    /// non-object ACE carries object type GUIDs, rather than writing a
+    /// descriptor that would not decode back to `self`.
+    pub fn encode(&self) -> Result<Vec<u8>, ProtocolError> {
+        let mut control = self.control | SecurityDescriptorControl::SELF_RELATIVE;
+        control.set(SecurityDescriptorControl::SACL_PRESENT, self.sacl.is_some());
+        control.set(SecurityDescriptorControl::DACL_PRESENT, self.dacl.is_some());
+
+        let mut body = BytesMut::new();
+        let offset_of = |present: bool, body: &BytesMut| {
+            if present {
+                (SECURITY_DESCRIPTOR_HEADER_LEN + body.len()) as u32
+            } else {
+                0
+            }
+        };
+        let owner_offset = offset_of(self.owner.is_some(), &body);
+        if let Some(owner) = &self.owner {
+            body.extend_from_slice(&owner.encode());
+        }
+        let group_offset = offset_of(self.group.is_some(), &body);
+        if let Some(group) = &self.group {
+            body.extend_from_slice(&group.encode());
+        }
+        let sacl_offset = offset_of(self.sacl.is_some(), &body);
+        if let Some(sacl) = &self.sacl {
+            sacl.encode_into(&mut body)?;
+        }
+        let dacl_offset = offset_of(self.dacl.is_some(), &body);
+        if let Some(dacl) = &self.dacl {
+            dacl.encode_into(&mut body)?;
+        }
+
+        let mut out = BytesMut::with_capacity(SECURITY_DESCRIPTOR_HEADER_LEN + body.len());
+        out.put_u8(1);
+        out.put_u8(0);
+        out.put_u16_le(control.bits());
+        out.put_u32_le(owner_offset);
+        out.put_u32_le(group_offset);
+        out.put_u32_le(sacl_offset);
+        out.put_u32_le(dacl_offset);
+        out.extend_from_slice(&body);
+        Ok(out.to_vec())
+    }
+
+    /// Parses a self-relative security descriptor.
+    pub fn decode(input: &[u8]) -> Result<Self, ProtocolError> {
+        if input.len() < SECURITY_DESCRIPTOR_HEADER_LEN {
+            return Err(ProtocolError::UnexpectedEof {
+                field: "security_descriptor",
+            });
+        }
+        if input[0] != 1 {
+            return Err(ProtocolError::InvalidField {
+                field: "security_descriptor_revision",
+                reason: "unsupported security descriptor revision",
+            });
+        }
+        let control =
+            SecurityDescriptorControl::from_bits_retain(u16::from_le_bytes([input[2], input[3]]));
+        if !control.contains(SecurityDescriptorControl::SELF_RELATIVE) {
+            return Err(ProtocolError::InvalidField {
+                field: "security_descriptor_control",
+                reason: "security descriptor is not self-relative",
+            });
+        }
+        let offset = |index: usize| {
+            let start = 4 + index * 4;
+            u32::from_le_bytes(input[start..start + 4].try_into().expect("slice len")) as usize
+        };
+        let section = |offset: usize, field: &'static str| {
+            input
+                .get(offset..)
+                .ok_or(ProtocolError::UnexpectedEof { field })
+        };
+
+        let owner = match offset(0) {
+            0 => None,
+            start => Some(Sid::decode(section(start, "owner_sid")?)?),
+        };
+        let group = match offset(1) {
+            0 => None,
+            start => Some(Sid::decode(section(start, "group_sid")?)?),
+        };
+        let sacl = match offset(2) {
+            start if start != 0 && control.contains(SecurityDescriptorControl::SACL_PRESENT) => {
+                Some(Acl::decode(section(start, "sacl")?)?)
+            }
+            _ => None,
+        };
+        let dacl = match offset(3) {
+            start if start != 0 && control.contains(SecurityDescriptorControl::DACL_PRESENT) => {
+                Some(Acl::decode(section(start, "dacl")?)?)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            control,
+            owner,
+            group,
+            sacl,
+            dacl,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ace, AceFlags, AceType, Acl, SecurityDescriptor, SecurityDescriptorControl, Sid};
+    use crate::smb::ProtocolError;
+
+    fn sid(authority: u8, sub_authorities: &[u32]) -> Sid {
+        Sid {
+            revision: 1,
+            identifier_authority: [0, 0, 0, 0, 0, authority],
+            sub_authorities: sub_authorities.to_vec(),
+        }
+    }
+
+    #[test]
+    fn security_descriptor_roundtrips() {
+        let descriptor = SecurityDescriptor {
+            control: SecurityDescriptorControl::SELF_RELATIVE
+                | SecurityDescriptorControl::DACL_PRESENT
+                | SecurityDescriptorControl::DACL_AUTO_INHERITED,
+            owner: Some(sid(5, &[32, 544])),
+            group: Some(sid(5, &[18])),
+            sacl: None,
+            dacl: Some(Acl {
+                revision: 2,
+                aces: vec![
+                    Ace::new(
+                        AceType::AccessAllowed,
+                        AceFlags::OBJECT_INHERIT | AceFlags::CONTAINER_INHERIT,
+                        0x001f_01ff,
+                        sid(5, &[18]),
+                    ),
+                    Ace::new(
+                        AceType::AccessDenied,
+                        AceFlags::INHERITED,
+                        0x0001_0000,
+                        sid(1, &[0]),
+                    ),
+                ],
+            }),
+        };
+
+        let encoded = descriptor.encode().expect("descriptor should encode");
+        let decoded = SecurityDescriptor::decode(&encoded).expect("descriptor should decode");
+        assert_eq!(decoded, descriptor);
+    }
+
+    #[test]
+    fn object_and_callback_aces_roundtrip_losslessly() {
+        let mut object_ace = Ace::new(
+            AceType::AccessAllowedObject,
+            AceFlags::CONTAINER_INHERIT,
+            0x0000_0100,
+            sid(5, &[11]),
+        );
+        object_ace.object_type = Some([0x11; 16]);
+        object_ace.inherited_object_type = Some([0x22; 16]);
+        let mut inherited_only = Ace::new(
+            AceType::AccessDeniedObject,
+            AceFlags::empty(),
+            0x0000_0010,
+            sid(1, &[0]),
+        );
+        inherited_only.inherited_object_type = Some([0x33; 16]);
+        let mut callback_ace = Ace::new(
+            AceType::AccessAllowedCallback,
+            AceFlags::empty(),
+            0x0012_0089,
+            sid(5, &[18]),
+        );
+        callback_ace.application_data = b"artx\x00\x00\x00\x00".to_vec();
+        let descriptor = SecurityDescriptor {
+            control: SecurityDescriptorControl::SELF_RELATIVE
+                | SecurityDescriptorControl::DACL_PRESENT,
+            owner: None,
+            group: None,
+            sacl: None,
+            dacl: Some(Acl {
+                revision: 4,
+                aces: vec![object_ace, inherited_only, callback_ace],
+            }),
+        };
+
+        let encoded = descriptor.encode().expect("descriptor should encode");
+        // Object flags advertise both GUIDs on the first ACE.
+        assert_eq!(&encoded[20 + 8 + 8..20 + 8 + 12], &[3, 0, 0, 0]);
+        let decoded = SecurityDescriptor::decode(&encoded).expect("descriptor should decode");
+        assert_eq!(decoded, descriptor);
+        assert_eq!(
+            decoded.encode().expect("descriptor should re-encode"),
+            encoded
+        );
+
+        let mut invalid = Ace::new(AceType::AccessAllowed, AceFlags::empty(), 1, sid(1, &[0]));
+        invalid.object_type = Some([0x44; 16]);
+        let rejected = SecurityDescriptor {
+            control: SecurityDescriptorControl::SELF_RELATIVE,
+            owner: None,
+            group: None,
+            sacl: None,
+            dacl: Some(Acl {
+                revision: 2,
+                aces: vec![invalid],
+            }),
+        };
+        assert!(matches!(
+            rejected.encode(),
+            Err(ProtocolError::InvalidField {
+                field: "ace_object_type",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn security_descriptor_decodes_windows_layout() {
+        // Owner BUILTIN\Administrators, DACL granting SYSTEM full control, with
+        // the DACL placed before the owner as Windows servers do.
+        let bytes = [
+            0x01, 0x00, 0x04, 0x84, 0x30, 0x00, 0x00, 0x00, // revision, control, owner
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // group, sacl
+            0x14, 0x00, 0x00, 0x00, // dacl
+            0x02, 0x00, 0x1c, 0x00, 0x01, 0x00, 0x00, 0x00, // acl header
+            0x00, 0x13, 0x14, 0x00, 0xff, 0x01, 0x1f, 0x00, // ace header, mask
+            0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x12, 0x00, 0x00,
+            0x00, // S-1-5-18
+            0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, // S-1-5-32-544
+            0x20, 0x00, 0x00, 0x00, 0x20, 0x02, 0x00, 0x00,
+        ];
+
+        let descriptor = SecurityDescriptor::decode(&bytes).expect("descriptor should decode");
+        assert_eq!(descriptor.owner, Some(sid(5, &[32, 544])));
+        assert_eq!(descriptor.group, None);
+        let dacl = descriptor.dacl.expect("dacl should be present");
+        assert_eq!(dacl.aces.len(), 1);
+        assert_eq!(dacl.aces[0].ace_type, AceType::AccessAllowed);
+        assert_eq!(
+            dacl.aces[0].flags,
+            AceFlags::OBJECT_INHERIT | AceFlags::CONTAINER_INHERIT | AceFlags::INHERITED
+        );
+        assert_eq!(dacl.aces[0].access_mask, 0x001f_01ff);
+        assert_eq!(dacl.aces[0].sid, sid(5, &[18]));
+    }
+
+    #[test]
+    fn security_descriptor_rejects_truncated_acls() {
+        let mut encoded = SecurityDescriptor {
+            control: SecurityDescriptorControl::SELF_RELATIVE,
+            owner: None,
+            group: None,
+            sacl: None,
+            dacl: Some(Acl {
+                revision: 2,
+                aces: vec![Ace::new(
+                    AceType::AccessAllowed,
+                    AceFlags::empty(),
+                    1,
+                    sid(1, &[0]),
+                )],
+            }),
+        }
+        .encode()
+        .expect("descriptor should encode");
+        encoded.truncate(encoded.len() - 2);
+
+        assert!(SecurityDescriptor::decode(&encoded).is_err());
+    }
+
+    #[test]
+    fn sid_formats_and_parses_canonical_strings() {
+        let domain_user = Sid::new(5, &[21, 1_004_336_348, 1_177_238_915, 682_003_330, 1_001]);
+        assert_eq!(
+            domain_user.to_string(),
+            "S-1-5-21-1004336348-1177238915-682003330-1001"
+        );
+        assert_eq!(
+            "S-1-5-21-1004336348-1177238915-682003330-1001"
+                .parse::<Sid>()
+                .expect("sid should parse"),
+            domain_user
+        );
+        assert_eq!(domain_user.rid(), Some(1_001));
+
+        let wide = Sid::new(0x0102_0304_0506, &[7]);
+        assert_eq!(wide.to_string(), "S-1-0x010203040506-7");
+        assert_eq!(wide.to_string().parse::<Sid>().expect("hex sid"), wide);
+
+        for invalid in ["", "S-1", "X-1-5-18", "S-1-5-x", "S-1-0x1000000000000-1"] {
+            assert!(matches!(
+                invalid.parse::<Sid>(),
+                Err(ProtocolError::InvalidField { field: "sid", .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn well_known_sids_match_their_canonical_strings() {
+        assert_eq!(Sid::everyone().to_string(), "S-1-1-0");
+        assert_eq!(Sid::creator_owner().to_string(), "S-1-3-0");
+        assert_eq!(Sid::authenticated_users().to_string(), "S-1-5-11");
+        assert_eq!(Sid::local_system().to_string(), "S-1-5-18");
+        assert_eq!(Sid::builtin_administrators().to_string(), "S-1-5-32-544");
+        assert_eq!(Sid::builtin_users().to_string(), "S-1-5-32-545");
+        assert_eq!(
+            Sid::builtin_administrators().well_known_name(),
+            Some("BUILTIN\\Administrators")
+        );
+        assert_eq!(Sid::new(5, &[21, 1, 2, 3, 500]).well_known_name(), None);
+    }
+
+    #[test]
+    fn ace_formats_as_sddl() {
+        let ace = Ace::new(
+            AceType::AccessAllowed,
+            AceFlags::OBJECT_INHERIT | AceFlags::CONTAINER_INHERIT | AceFlags::INHERITED,
+            0x001f_01ff,
+            Sid::local_system(),
+        );
+        assert_eq!(ace.to_string(), "(A;OICIID;0x001f01ff;;;S-1-5-18)");
+
+        let ace = Ace::new(AceType::Other(0x13), AceFlags::empty(), 0, Sid::everyone());
+        assert_eq!(ace.to_string(), "(0x13;;0x00000000;;;S-1-1-0)");
+    }
+}
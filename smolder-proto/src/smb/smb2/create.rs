@@ -22,6 +22,56 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Access rights requested by a create request.
+    ///
+    /// Servers may grant rights not listed here, so decoded masks should be
+    /// built with [`DesiredAccess::from_bits_retain`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct DesiredAccess: u32 {
+        /// Read file data, or list a directory.
+        const FILE_READ_DATA = 0x0000_0001;
+        /// Write file data, or add a file to a directory.
+        const FILE_WRITE_DATA = 0x0000_0002;
+        /// Append file data, or add a subdirectory.
+        const FILE_APPEND_DATA = 0x0000_0004;
+        /// Read extended attributes.
+        const FILE_READ_EA = 0x0000_0008;
+        /// Write extended attributes.
+        const FILE_WRITE_EA = 0x0000_0010;
+        /// Execute a file, or traverse a directory.
+        const FILE_EXECUTE = 0x0000_0020;
+        /// Delete entries from a directory.
+        const FILE_DELETE_CHILD = 0x0000_0040;
+        /// Read file attributes.
+        const FILE_READ_ATTRIBUTES = 0x0000_0080;
+        /// Write file attributes.
+        const FILE_WRITE_ATTRIBUTES = 0x0000_0100;
+        /// Delete the object.
+        const DELETE = 0x0001_0000;
+        /// Read the security descriptor, excluding the SACL.
+        const READ_CONTROL = 0x0002_0000;
+        /// Modify the DACL.
+        const WRITE_DAC = 0x0004_0000;
+        /// Change the owner.
+        const WRITE_OWNER = 0x0008_0000;
+        /// Use the handle for synchronization.
+        const SYNCHRONIZE = 0x0010_0000;
+        /// Read or modify the SACL.
+        const ACCESS_SYSTEM_SECURITY = 0x0100_0000;
+        /// Request the maximum access the server allows.
+        const MAXIMUM_ALLOWED = 0x0200_0000;
+        /// Generic all access.
+        const GENERIC_ALL = 0x1000_0000;
+        /// Generic execute access.
+        const GENERIC_EXECUTE = 0x2000_0000;
+        /// Generic write access.
+        const GENERIC_WRITE = 0x4000_0000;
+        /// Generic read access.
+        const GENERIC_READ = 0x8000_0000;
+    }
+}
+
 bitflags! {
     /// Durable handle flags used by SMB 3.x durable-handle-v2 contexts.
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -58,7 +108,7 @@ bitflags! {
 }
 
 bitflags! {
-    /// File attributes in a create request.
+    /// File attributes carried by create requests, directory listings, and file information.
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct FileAttributes: u32 {
         /// Read-only file.
@@ -73,6 +123,32 @@ bitflags! {
         const ARCHIVE = 0x0000_0020;
         /// Normal file.
         const NORMAL = 0x0000_0080;
+        /// Temporary file.
+        const TEMPORARY = 0x0000_0100;
+        /// Sparse file.
+        const SPARSE_FILE = 0x0000_0200;
+        /// Reparse point, such as a symbolic link or mount point.
+        const REPARSE_POINT = 0x0000_0400;
+        /// Compressed file or directory.
+        const COMPRESSED = 0x0000_0800;
+        /// File data is not immediately available.
+        const OFFLINE = 0x0000_1000;
+        /// Excluded from content indexing.
+        const NOT_CONTENT_INDEXED = 0x0000_2000;
+        /// Encrypted file or directory.
+        const ENCRYPTED = 0x0000_4000;
+        /// Directory or user data stream configured with integrity support.
+        const INTEGRITY_STREAM = 0x0000_8000;
+        /// Excluded from data integrity scans.
+        const NO_SCRUB_DATA = 0x0002_0000;
+        /// Data should be recalled from remote storage when opened.
+        const RECALL_ON_OPEN = 0x0004_0000;
+        /// Pinned for local availability.
+        const PINNED = 0x0008_0000;
+        /// Allowed to be removed from local storage.
+        const UNPINNED = 0x0010_0000;
+        /// Data should be recalled from remote storage on access.
+        const RECALL_ON_DATA_ACCESS = 0x0040_0000;
     }
 }
 
@@ -127,6 +203,24 @@ pub enum OplockLevel {
     Lease = 0xff,
 }
 
+impl TryFrom<u8> for OplockLevel {
+    type Error = ProtocolError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(Self::None),
+            0x01 => Ok(Self::II),
+            0x08 => Ok(Self::Exclusive),
+            0x09 => Ok(Self::Batch),
+            0xff => Ok(Self::Lease),
+            _ => Err(ProtocolError::InvalidField {
+                field: "oplock_level",
+                reason: "unknown oplock level",
+            }),
+        }
+    }
+}
+
 /// Create disposition value.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u32)]
@@ -145,6 +239,14 @@ pub enum CreateDisposition {
     OverwriteIf = 5,
 }
 
+impl CreateDisposition {
+    /// Returns the wire value of the disposition.
+    #[must_use]
+    pub const fn to_u32(self) -> u32 {
+        self as u32
+    }
+}
+
 /// A 128-bit SMB file identifier.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct FileId {
@@ -721,6 +823,19 @@ impl CreateRequest {
         }
     }
 
+    /// Replaces the requested access mask.
+    #[must_use]
+    pub fn with_desired_access(mut self, access: DesiredAccess) -> Self {
+        self.desired_access = access.bits();
+        self
+    }
+
+    /// Returns the requested access mask as typed flags.
+    #[must_use]
+    pub fn access(&self) -> DesiredAccess {
+        DesiredAccess::from_bits_retain(self.desired_access)
+    }
+
     /// Serializes the request body.
     #[must_use]
     pub fn encode(&self) -> Vec<u8> {
@@ -736,7 +851,7 @@ impl CreateRequest {
         out.put_u32_le(self.desired_access);
         out.put_u32_le(self.file_attributes.bits());
         out.put_u32_le(self.share_access.bits());
-        out.put_u32_le(self.create_disposition as u32);
+        out.put_u32_le(self.create_disposition.to_u32());
         out.put_u32_le(self.create_options.bits());
         out.put_u16_le((HEADER_LEN + 56) as u16);
         out.put_u16_le(self.name.len() as u16);
@@ -903,19 +1018,7 @@ impl CreateResponse {
     pub fn decode(body: &[u8]) -> Result<Self, ProtocolError> {
         let mut input = body;
         check_fixed_structure_size(get_u16(&mut input, "structure_size")?, 89, "structure_size")?;
-        let oplock_level = match super::get_u8(&mut input, "oplock_level")? {
-            0x00 => OplockLevel::None,
-            0x01 => OplockLevel::II,
-            0x08 => OplockLevel::Exclusive,
-            0x09 => OplockLevel::Batch,
-            0xff => OplockLevel::Lease,
-            _ => {
-                return Err(ProtocolError::InvalidField {
-                    field: "oplock_level",
-                    reason: "unknown oplock level",
-                })
-            }
-        };
+        let oplock_level = OplockLevel::try_from(super::get_u8(&mut input, "oplock_level")?)?;
         let _flags = super::get_u8(&mut input, "flags")?;
         let _create_action = get_u32(&mut input, "create_action")?;
         let _creation_time = get_u64(&mut input, "creation_time")?;
@@ -1180,7 +1283,7 @@ mod tests {
         DurableHandleRequestV2, DurableHandleResponse, DurableHandleResponseV2, FileAttributes,
         FileId, LeaseFlags, LeaseState, LeaseV2, OplockLevel, ShareAccess,
     };
-    use super::{CreateDisposition, CreateOptions, RequestedOplockLevel};
+    use super::{CreateDisposition, CreateOptions, DesiredAccess, RequestedOplockLevel};
 
     #[test]
     fn create_request_roundtrips() {
@@ -1223,6 +1326,27 @@ mod tests {
         assert_eq!(encoded.len(), 57);
     }
 
+    #[test]
+    fn typed_access_and_disposition_encode_at_their_wire_offsets() {
+        let request = CreateRequest::from_path("notes.txt")
+            .with_desired_access(DesiredAccess::GENERIC_READ | DesiredAccess::SYNCHRONIZE);
+        let request = CreateRequest {
+            create_disposition: CreateDisposition::OverwriteIf,
+            ..request
+        };
+
+        let encoded = request.encode();
+
+        assert_eq!(&encoded[24..28], &0x8010_0000_u32.to_le_bytes());
+        assert_eq!(&encoded[36..40], &5_u32.to_le_bytes());
+        assert_eq!(CreateDisposition::Supersede.to_u32(), 0);
+        let decoded = CreateRequest::decode(&encoded).expect("request should decode");
+        assert_eq!(
+            decoded.access(),
+            DesiredAccess::GENERIC_READ | DesiredAccess::SYNCHRONIZE
+        );
+    }
+
     #[test]
     fn create_response_roundtrips() {
         let response = CreateResponse {
@@ -7,7 +7,7 @@ use bytes::{BufMut, BytesMut};
 
 use super::{
     get_array, get_u16, get_u32, get_u64, AsyncId, CreditCharge, MessageId, SessionId, TreeId,
-    PROTOCOL_ID,
+    PROTOCOL_ID, SMB1_PROTOCOL_ID,
 };
 use crate::smb::ProtocolError;
 
@@ -68,6 +68,8 @@ pub enum Command {
     QueryInfo = 0x0010,
     /// `SET_INFO`
     SetInfo = 0x0011,
+    /// `OPLOCK_BREAK`
+    OplockBreak = 0x0012,
 }
 
 impl TryFrom<u16> for Command {
@@ -93,6 +95,7 @@ impl TryFrom<u16> for Command {
             0x000f => Ok(Self::ChangeNotify),
             0x0010 => Ok(Self::QueryInfo),
             0x0011 => Ok(Self::SetInfo),
+            0x0012 => Ok(Self::OplockBreak),
             _ => Err(ProtocolError::InvalidField {
                 field: "command",
                 reason: "unknown SMB2 command",
@@ -120,6 +123,11 @@ pub struct Header {
     pub message_id: MessageId,
     /// Asynchronous identifier present on async headers.
     pub async_id: Option<AsyncId>,
+    /// Process identifier carried in the sync header's reserved field.
+    ///
+    /// Servers ignore it, but keeping it stable makes captured traffic
+    /// reproducible. Async headers reuse these bytes for `async_id`.
+    pub process_id: u32,
     /// Tree identifier.
     pub tree_id: TreeId,
     /// Session identifier.
@@ -146,6 +154,7 @@ impl Header {
             next_command: 0,
             message_id,
             async_id: None,
+            process_id: 0,
             tree_id: TreeId(0),
             session_id: SessionId(0),
             signature: [0; 16],
@@ -168,7 +177,7 @@ impl Header {
         if self.flags.contains(HeaderFlags::ASYNC_COMMAND) {
             out.put_u64_le(self.async_id.unwrap_or(AsyncId(0)).0);
         } else {
-            out.put_u32_le(0);
+            out.put_u32_le(self.process_id);
             out.put_u32_le(self.tree_id.0);
         }
         out.put_u64_le(self.session_id.0);
@@ -178,6 +187,12 @@ impl Header {
 
     /// Parses an SMB2 header.
     pub fn decode(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        if bytes.starts_with(&SMB1_PROTOCOL_ID) {
+            return Err(ProtocolError::InvalidField {
+                field: "protocol_id",
+                reason: "SMB1 packets are not supported; the peer must speak SMB2 or later",
+            });
+        }
         if bytes.len() < Self::LEN {
             return Err(ProtocolError::UnexpectedEof { field: "header" });
         }
@@ -211,11 +226,15 @@ impl Header {
         )?;
         let next_command = get_u32(&mut input, "next_command")?;
         let message_id = MessageId(get_u64(&mut input, "message_id")?);
-        let (async_id, tree_id) = if flags.contains(HeaderFlags::ASYNC_COMMAND) {
-            (Some(AsyncId(get_u64(&mut input, "async_id")?)), TreeId(0))
+        let (async_id, process_id, tree_id) = if flags.contains(HeaderFlags::ASYNC_COMMAND) {
+            (
+                Some(AsyncId(get_u64(&mut input, "async_id")?)),
+                0,
+                TreeId(0),
+            )
         } else {
-            let _reserved = get_u32(&mut input, "reserved")?;
-            (None, TreeId(get_u32(&mut input, "tree_id")?))
+            let process_id = get_u32(&mut input, "process_id")?;
+            (None, process_id, TreeId(get_u32(&mut input, "tree_id")?))
         };
         let session_id = SessionId(get_u64(&mut input, "session_id")?);
         let signature = get_array::<16>(&mut input, "signature")?;
@@ -229,6 +248,7 @@ impl Header {
             next_command,
             message_id,
             async_id,
+            process_id,
             tree_id,
             session_id,
             signature,
@@ -239,7 +259,7 @@ impl Header {
 #[cfg(test)]
 mod tests {
     use super::{Command, Header, HeaderFlags};
-    use crate::smb::smb2::{AsyncId, MessageId, SessionId, TreeId};
+    use crate::smb::smb2::{AsyncId, CreditCharge, MessageId, SessionId, TreeId};
 
     #[test]
     fn header_roundtrips() {
@@ -255,6 +275,46 @@ mod tests {
         assert_eq!(decoded, header);
     }
 
+    #[test]
+    fn header_encodes_the_fixed_64_byte_layout() {
+        let mut header = Header::new(Command::Read, MessageId(0x0102_0304_0506_0708));
+        header.credit_charge = CreditCharge(3);
+        header.status = 0x1122_3344;
+        header.credit_request_response = 0x0a0b;
+        header.flags = HeaderFlags::SERVER_TO_REDIR | HeaderFlags::SIGNED;
+        header.next_command = 0x0000_0080;
+        header.process_id = 0x0000_feff;
+        header.tree_id = TreeId(0x5566_7788);
+        header.session_id = SessionId(0x99aa_bbcc_ddee_ff00);
+        header.signature = [0x5a; 16];
+
+        let encoded = header.encode();
+
+        assert_eq!(encoded.len(), Header::LEN);
+        assert_eq!(&encoded[0..4], b"\xfeSMB");
+        assert_eq!(&encoded[4..6], &64u16.to_le_bytes());
+        assert_eq!(&encoded[6..8], &3u16.to_le_bytes());
+        assert_eq!(&encoded[8..12], &0x1122_3344u32.to_le_bytes());
+        assert_eq!(&encoded[12..14], &(Command::Read as u16).to_le_bytes());
+        assert_eq!(&encoded[14..16], &0x0a0bu16.to_le_bytes());
+        assert_eq!(&encoded[16..20], &0x0000_0009u32.to_le_bytes());
+        assert_eq!(&encoded[20..24], &0x0000_0080u32.to_le_bytes());
+        assert_eq!(&encoded[24..32], &0x0102_0304_0506_0708u64.to_le_bytes());
+        assert_eq!(&encoded[32..36], &0x0000_feffu32.to_le_bytes());
+        assert_eq!(&encoded[36..40], &0x5566_7788u32.to_le_bytes());
+        assert_eq!(&encoded[40..48], &0x99aa_bbcc_ddee_ff00u64.to_le_bytes());
+        assert_eq!(&encoded[Header::SIGNATURE_RANGE], &[0x5a; 16]);
+    }
+
+    #[test]
+    fn header_decode_rejects_smb1_packets_with_a_clear_reason() {
+        let mut packet = vec![0xff, b'S', b'M', b'B'];
+        packet.resize(Header::LEN, 0);
+
+        let error = Header::decode(&packet).expect_err("SMB1 header should be rejected");
+        assert!(error.to_string().contains("SMB1 packets are not supported"));
+    }
+
     #[test]
     fn async_header_roundtrips() {
         let mut header = Header::new(Command::Write, MessageId(7));
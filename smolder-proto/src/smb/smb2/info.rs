@@ -8,6 +8,7 @@ use super::{
     check_fixed_structure_size, get_u16, get_u32, get_u64, slice_from_offset, utf16le,
     utf16le_string, HEADER_LEN,
 };
+use crate::smb::security::SecurityInformation;
 use crate::smb::ProtocolError;
 
 bitflags! {
@@ -25,6 +26,43 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Filesystem capability flags reported by `FILE_FS_ATTRIBUTE_INFORMATION`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct FileSystemAttributes: u32 {
+        /// Name lookups are case-sensitive.
+        const CASE_SENSITIVE_SEARCH = 0x0000_0001;
+        /// The filesystem preserves the case of names.
+        const CASE_PRESERVED_NAMES = 0x0000_0002;
+        /// Names are stored as Unicode on disk.
+        const UNICODE_ON_DISK = 0x0000_0004;
+        /// The filesystem enforces ACLs.
+        const PERSISTENT_ACLS = 0x0000_0008;
+        /// Per-file compression is supported.
+        const FILE_COMPRESSION = 0x0000_0010;
+        /// Disk quotas are supported.
+        const VOLUME_QUOTAS = 0x0000_0020;
+        /// Sparse files are supported.
+        const SUPPORTS_SPARSE_FILES = 0x0000_0040;
+        /// Reparse points are supported.
+        const SUPPORTS_REPARSE_POINTS = 0x0000_0080;
+        /// The volume is compressed.
+        const VOLUME_IS_COMPRESSED = 0x0000_8000;
+        /// Object identifiers are supported.
+        const SUPPORTS_OBJECT_IDS = 0x0001_0000;
+        /// Encrypted files are supported.
+        const SUPPORTS_ENCRYPTION = 0x0002_0000;
+        /// Named streams are supported.
+        const NAMED_STREAMS = 0x0004_0000;
+        /// The volume is read-only.
+        const READ_ONLY_VOLUME = 0x0008_0000;
+        /// Hard links are supported.
+        const SUPPORTS_HARD_LINKS = 0x0040_0000;
+        /// Extended attributes are supported.
+        const SUPPORTS_EXTENDED_ATTRIBUTES = 0x0080_0000;
+    }
+}
+
 /// File information classes used for directory enumeration.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -301,15 +339,98 @@ pub enum FileInfoClass {
     RenameInformation = 0x0a,
     /// `FileDispositionInformation`
     DispositionInformation = 0x0d,
+    /// `FileAllocationInformation`
+    AllocationInformation = 0x13,
+    /// `FileEndOfFileInformation`
+    EndOfFileInformation = 0x14,
+}
+
+impl TryFrom<u8> for FileInfoClass {
+    type Error = ProtocolError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x04 => Ok(Self::BasicInformation),
+            0x05 => Ok(Self::StandardInformation),
+            0x0a => Ok(Self::RenameInformation),
+            0x0d => Ok(Self::DispositionInformation),
+            0x13 => Ok(Self::AllocationInformation),
+            0x14 => Ok(Self::EndOfFileInformation),
+            _ => Err(ProtocolError::InvalidField {
+                field: "file_info_class",
+                reason: "unknown file info class",
+            }),
+        }
+    }
+}
+
+/// Filesystem information classes used by `QUERY_INFO`.
+///
+/// These share a numeric space with [`FileInfoClass`] but are only valid
+/// together with [`InfoType::FileSystem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum FileSystemInfoClass {
+    /// `FileFsVolumeInformation`
+    VolumeInformation = 0x01,
+    /// `FileFsAttributeInformation`
+    AttributeInformation = 0x05,
+    /// `FileFsFullSizeInformation`
+    FullSizeInformation = 0x07,
+}
+
+impl TryFrom<u8> for FileSystemInfoClass {
+    type Error = ProtocolError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(Self::VolumeInformation),
+            0x05 => Ok(Self::AttributeInformation),
+            0x07 => Ok(Self::FullSizeInformation),
+            _ => Err(ProtocolError::InvalidField {
+                field: "file_info_class",
+                reason: "unknown filesystem info class",
+            }),
+        }
+    }
+}
+
+/// Information class queried by `QUERY_INFO`, tagged with its info type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InfoClass {
+    /// A file information class, sent with [`InfoType::File`].
+    File(FileInfoClass),
+    /// A filesystem information class, sent with [`InfoType::FileSystem`].
+    FileSystem(FileSystemInfoClass),
+    /// A security descriptor, sent with [`InfoType::Security`] and class zero.
+    Security,
+}
+
+impl InfoClass {
+    /// Returns the info type this class is sent with.
+    #[must_use]
+    pub fn info_type(self) -> InfoType {
+        match self {
+            Self::File(_) => InfoType::File,
+            Self::FileSystem(_) => InfoType::FileSystem,
+            Self::Security => InfoType::Security,
+        }
+    }
+
+    fn class_byte(self) -> u8 {
+        match self {
+            Self::File(class) => class as u8,
+            Self::FileSystem(class) => class as u8,
+            Self::Security => 0,
+        }
+    }
 }
 
 /// SMB2 query-info request body.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct QueryInfoRequest {
-    /// The info type being queried.
-    pub info_type: InfoType,
-    /// The file information class.
-    pub file_info_class: FileInfoClass,
+    /// The information class being queried, which also selects the info type.
+    pub info_class: InfoClass,
     /// Maximum response size.
     pub output_buffer_length: u32,
     /// Optional input buffer.
@@ -327,8 +448,33 @@ impl QueryInfoRequest {
     #[must_use]
     pub fn for_file_info(file_id: FileId, file_info_class: FileInfoClass) -> Self {
         Self {
-            info_type: InfoType::File,
-            file_info_class,
+            info_class: InfoClass::File(file_info_class),
+            output_buffer_length: 4096,
+            input_buffer: Vec::new(),
+            additional_information: 0,
+            flags: 0,
+            file_id,
+        }
+    }
+
+    /// Creates a security-descriptor query for the requested parts.
+    #[must_use]
+    pub fn for_security(file_id: FileId, security_information: SecurityInformation) -> Self {
+        Self {
+            info_class: InfoClass::Security,
+            output_buffer_length: 4096,
+            input_buffer: Vec::new(),
+            additional_information: security_information.bits(),
+            flags: 0,
+            file_id,
+        }
+    }
+
+    /// Creates a filesystem-info query against any open handle on the volume.
+    #[must_use]
+    pub fn for_fs_info(file_id: FileId, fs_info_class: FileSystemInfoClass) -> Self {
+        Self {
+            info_class: InfoClass::FileSystem(fs_info_class),
             output_buffer_length: 4096,
             input_buffer: Vec::new(),
             additional_information: 0,
@@ -342,8 +488,8 @@ impl QueryInfoRequest {
     pub fn encode(&self) -> Vec<u8> {
         let mut out = BytesMut::with_capacity(40 + self.input_buffer.len());
         out.put_u16_le(41);
-        out.put_u8(self.info_type as u8);
-        out.put_u8(self.file_info_class as u8);
+        out.put_u8(self.info_class.info_type() as u8);
+        out.put_u8(self.info_class.class_byte());
         out.put_u32_le(self.output_buffer_length);
         let input_buffer_offset = if self.input_buffer.is_empty() {
             0
@@ -365,27 +511,28 @@ impl QueryInfoRequest {
     pub fn decode(body: &[u8]) -> Result<Self, ProtocolError> {
         let mut input = body;
         check_fixed_structure_size(get_u16(&mut input, "structure_size")?, 41, "structure_size")?;
-        let info_type = match super::get_u8(&mut input, "info_type")? {
-            0x01 => InfoType::File,
-            0x02 => InfoType::FileSystem,
-            0x03 => InfoType::Security,
-            0x04 => InfoType::Quota,
-            _ => {
+        let info_type = super::get_u8(&mut input, "info_type")?;
+        let class = super::get_u8(&mut input, "file_info_class")?;
+        let info_class = match info_type {
+            0x01 => InfoClass::File(FileInfoClass::try_from(class)?),
+            0x02 => InfoClass::FileSystem(FileSystemInfoClass::try_from(class)?),
+            0x03 if class == 0 => InfoClass::Security,
+            0x03 => {
+                return Err(ProtocolError::InvalidField {
+                    field: "file_info_class",
+                    reason: "security queries must use class zero",
+                })
+            }
+            0x04 => {
                 return Err(ProtocolError::InvalidField {
                     field: "info_type",
-                    reason: "unknown info type",
+                    reason: "quota queries are not supported",
                 })
             }
-        };
-        let file_info_class = match super::get_u8(&mut input, "file_info_class")? {
-            0x04 => FileInfoClass::BasicInformation,
-            0x05 => FileInfoClass::StandardInformation,
-            0x0a => FileInfoClass::RenameInformation,
-            0x0d => FileInfoClass::DispositionInformation,
             _ => {
                 return Err(ProtocolError::InvalidField {
-                    field: "file_info_class",
-                    reason: "unknown file info class",
+                    field: "info_type",
+                    reason: "unknown info type",
                 })
             }
         };
@@ -412,8 +559,7 @@ impl QueryInfoRequest {
         };
 
         Ok(Self {
-            info_type,
-            file_info_class,
+            info_class,
             output_buffer_length,
             input_buffer,
             additional_information,
@@ -485,6 +631,22 @@ pub struct FileBasicInformation {
 }
 
 impl FileBasicInformation {
+    /// Serializes `FILE_BASIC_INFORMATION` for a set-info request.
+    ///
+    /// In a set-info request, a zero timestamp or empty attribute set leaves
+    /// that field unchanged on the server.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = BytesMut::with_capacity(40);
+        out.put_u64_le(self.creation_time);
+        out.put_u64_le(self.last_access_time);
+        out.put_u64_le(self.last_write_time);
+        out.put_u64_le(self.change_time);
+        out.put_u32_le(self.file_attributes.bits());
+        out.put_u32_le(0);
+        out.to_vec()
+    }
+
     /// Parses `FILE_BASIC_INFORMATION`.
     pub fn decode(buffer: &[u8]) -> Result<Self, ProtocolError> {
         let mut input = buffer;
@@ -592,6 +754,185 @@ impl DispositionInformation {
     }
 }
 
+/// `FILE_ALLOCATION_INFORMATION`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocationInformation {
+    /// Bytes the server should reserve for the file.
+    pub allocation_size: u64,
+}
+
+impl AllocationInformation {
+    /// Serializes the payload.
+    #[must_use]
+    pub fn encode(self) -> Vec<u8> {
+        self.allocation_size.to_le_bytes().to_vec()
+    }
+}
+
+/// `FILE_END_OF_FILE_INFORMATION`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndOfFileInformation {
+    /// New file size in bytes.
+    pub end_of_file: u64,
+}
+
+impl EndOfFileInformation {
+    /// Serializes the payload.
+    #[must_use]
+    pub fn encode(self) -> Vec<u8> {
+        self.end_of_file.to_le_bytes().to_vec()
+    }
+}
+
+/// Parsed `FILE_FS_FULL_SIZE_INFORMATION`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFsFullSizeInformation {
+    /// Total allocation units on the volume.
+    pub total_allocation_units: u64,
+    /// Free allocation units available to the calling user.
+    pub caller_available_allocation_units: u64,
+    /// Free allocation units on the volume, ignoring per-user quotas.
+    pub actual_available_allocation_units: u64,
+    /// Sectors in each allocation unit.
+    pub sectors_per_allocation_unit: u32,
+    /// Bytes in each sector.
+    pub bytes_per_sector: u32,
+}
+
+impl FileFsFullSizeInformation {
+    /// Serializes `FILE_FS_FULL_SIZE_INFORMATION`.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = BytesMut::with_capacity(32);
+        out.put_u64_le(self.total_allocation_units);
+        out.put_u64_le(self.caller_available_allocation_units);
+        out.put_u64_le(self.actual_available_allocation_units);
+        out.put_u32_le(self.sectors_per_allocation_unit);
+        out.put_u32_le(self.bytes_per_sector);
+        out.to_vec()
+    }
+
+    /// Parses `FILE_FS_FULL_SIZE_INFORMATION`.
+    pub fn decode(buffer: &[u8]) -> Result<Self, ProtocolError> {
+        let mut input = buffer;
+        Ok(Self {
+            total_allocation_units: get_u64(&mut input, "total_allocation_units")?,
+            caller_available_allocation_units: get_u64(
+                &mut input,
+                "caller_available_allocation_units",
+            )?,
+            actual_available_allocation_units: get_u64(
+                &mut input,
+                "actual_available_allocation_units",
+            )?,
+            sectors_per_allocation_unit: get_u32(&mut input, "sectors_per_allocation_unit")?,
+            bytes_per_sector: get_u32(&mut input, "bytes_per_sector")?,
+        })
+    }
+
+    /// Returns the size of one allocation unit in bytes.
+    #[must_use]
+    pub fn bytes_per_allocation_unit(&self) -> u64 {
+        u64::from(self.sectors_per_allocation_unit) * u64::from(self.bytes_per_sector)
+    }
+}
+
+/// Parsed `FILE_FS_VOLUME_INFORMATION`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFsVolumeInformation {
+    /// Volume creation time in Windows ticks.
+    pub volume_creation_time: u64,
+    /// Volume serial number.
+    pub volume_serial_number: u32,
+    /// Whether the filesystem supports object identifiers.
+    pub supports_objects: bool,
+    /// Volume label.
+    pub volume_label: String,
+}
+
+impl FileFsVolumeInformation {
+    /// Serializes `FILE_FS_VOLUME_INFORMATION`.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let volume_label = utf16le(&self.volume_label);
+        let mut out = BytesMut::with_capacity(18 + volume_label.len());
+        out.put_u64_le(self.volume_creation_time);
+        out.put_u32_le(self.volume_serial_number);
+        out.put_u32_le(volume_label.len() as u32);
+        out.put_u8(u8::from(self.supports_objects));
+        out.put_u8(0);
+        out.extend_from_slice(&volume_label);
+        out.to_vec()
+    }
+
+    /// Parses `FILE_FS_VOLUME_INFORMATION`.
+    pub fn decode(buffer: &[u8]) -> Result<Self, ProtocolError> {
+        let mut input = buffer;
+        let volume_creation_time = get_u64(&mut input, "volume_creation_time")?;
+        let volume_serial_number = get_u32(&mut input, "volume_serial_number")?;
+        let volume_label_length = get_u32(&mut input, "volume_label_length")? as usize;
+        let supports_objects = super::get_u8(&mut input, "supports_objects")? != 0;
+        let _reserved = super::get_u8(&mut input, "reserved")?;
+        if volume_label_length > input.len() {
+            return Err(ProtocolError::UnexpectedEof {
+                field: "volume_label",
+            });
+        }
+        let volume_label = utf16le_string(&input[..volume_label_length])?;
+        Ok(Self {
+            volume_creation_time,
+            volume_serial_number,
+            supports_objects,
+            volume_label,
+        })
+    }
+}
+
+/// Parsed `FILE_FS_ATTRIBUTE_INFORMATION`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFsAttributeInformation {
+    /// Filesystem capability flags. Unknown bits are preserved.
+    pub file_system_attributes: FileSystemAttributes,
+    /// Longest file name component the filesystem accepts, in characters.
+    pub maximum_component_name_length: u32,
+    /// Filesystem type name, such as `NTFS` or `FAT32`.
+    pub file_system_name: String,
+}
+
+impl FileFsAttributeInformation {
+    /// Serializes `FILE_FS_ATTRIBUTE_INFORMATION`.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let file_system_name = utf16le(&self.file_system_name);
+        let mut out = BytesMut::with_capacity(12 + file_system_name.len());
+        out.put_u32_le(self.file_system_attributes.bits());
+        out.put_u32_le(self.maximum_component_name_length);
+        out.put_u32_le(file_system_name.len() as u32);
+        out.extend_from_slice(&file_system_name);
+        out.to_vec()
+    }
+
+    /// Parses `FILE_FS_ATTRIBUTE_INFORMATION`.
+    pub fn decode(buffer: &[u8]) -> Result<Self, ProtocolError> {
+        let mut input = buffer;
+        let file_system_attributes =
+            FileSystemAttributes::from_bits_retain(get_u32(&mut input, "file_system_attributes")?);
+        let maximum_component_name_length = get_u32(&mut input, "maximum_component_name_length")?;
+        let file_system_name_length = get_u32(&mut input, "file_system_name_length")? as usize;
+        if file_system_name_length > input.len() {
+            return Err(ProtocolError::UnexpectedEof {
+                field: "file_system_name",
+            });
+        }
+        let file_system_name = utf16le_string(&input[..file_system_name_length])?;
+        Ok(Self {
+            file_system_attributes,
+            maximum_component_name_length,
+            file_system_name,
+        })
+    }
+}
+
 /// SMB2 set-info request body.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SetInfoRequest {
@@ -658,18 +999,8 @@ impl SetInfoRequest {
                 })
             }
         };
-        let file_info_class = match super::get_u8(&mut input, "file_info_class")? {
-            0x04 => FileInfoClass::BasicInformation,
-            0x05 => FileInfoClass::StandardInformation,
-            0x0a => FileInfoClass::RenameInformation,
-            0x0d => FileInfoClass::DispositionInformation,
-            _ => {
-                return Err(ProtocolError::InvalidField {
-                    field: "file_info_class",
-                    reason: "unknown file info class",
-                })
-            }
-        };
+        let file_info_class =
+            FileInfoClass::try_from(super::get_u8(&mut input, "file_info_class")?)?;
         let buffer_length = get_u32(&mut input, "buffer_length")? as usize;
         let buffer_offset = get_u16(&mut input, "buffer_offset")?;
         let _reserved = get_u16(&mut input, "reserved")?;
@@ -718,10 +1049,14 @@ impl SetInfoResponse {
 #[cfg(test)]
 mod tests {
     use super::{
-        DirectoryInformationEntry, DispositionInformation, FileBasicInformation, FileInfoClass,
-        FileStandardInformation, InfoType, QueryDirectoryRequest, QueryDirectoryResponse,
-        QueryInfoRequest, QueryInfoResponse, RenameInformation, SetInfoRequest, SetInfoResponse,
+        AllocationInformation, DirectoryInformationEntry, DispositionInformation,
+        EndOfFileInformation, FileBasicInformation, FileFsAttributeInformation,
+        FileFsFullSizeInformation, FileFsVolumeInformation, FileInfoClass, FileStandardInformation,
+        FileSystemAttributes, FileSystemInfoClass, InfoClass, InfoType, QueryDirectoryRequest,
+        QueryDirectoryResponse, QueryInfoRequest, QueryInfoResponse, RenameInformation,
+        SetInfoRequest, SetInfoResponse,
     };
+    use crate::smb::security::SecurityInformation;
     use crate::smb::smb2::{FileAttributes, FileId};
 
     #[test]
@@ -810,8 +1145,7 @@ mod tests {
     #[test]
     fn query_info_roundtrips() {
         let request = QueryInfoRequest {
-            info_type: InfoType::File,
-            file_info_class: FileInfoClass::BasicInformation,
+            info_class: InfoClass::File(FileInfoClass::BasicInformation),
             output_buffer_length: 4096,
             input_buffer: vec![0xaa, 0xbb],
             additional_information: 0x11,
@@ -833,6 +1167,32 @@ mod tests {
         assert_eq!(decoded, response);
     }
 
+    #[test]
+    fn query_info_decode_validates_the_class_for_its_info_type() {
+        let file_id = FileId {
+            persistent: 1,
+            volatile: 2,
+        };
+        let security = QueryInfoRequest::for_security(file_id, SecurityInformation::DACL);
+        let decoded =
+            QueryInfoRequest::decode(&security.encode()).expect("security query should decode");
+        assert_eq!(decoded.info_class, InfoClass::Security);
+
+        let mut unknown_file_class =
+            QueryInfoRequest::for_file_info(file_id, FileInfoClass::BasicInformation).encode();
+        unknown_file_class[3] = 0x7f;
+        assert!(QueryInfoRequest::decode(&unknown_file_class).is_err());
+
+        let mut file_class_on_fs_query =
+            QueryInfoRequest::for_fs_info(file_id, FileSystemInfoClass::VolumeInformation).encode();
+        file_class_on_fs_query[3] = FileInfoClass::BasicInformation as u8;
+        assert!(QueryInfoRequest::decode(&file_class_on_fs_query).is_err());
+
+        let mut classed_security = security.encode();
+        classed_security[3] = 0x04;
+        assert!(QueryInfoRequest::decode(&classed_security).is_err());
+    }
+
     #[test]
     fn file_info_decoders_parse_basic_and_standard_information() {
         let mut basic = Vec::new();
@@ -851,17 +1211,86 @@ mod tests {
         standard.push(0);
         standard.extend_from_slice(&0_u16.to_le_bytes());
 
+        let basic_bytes = basic.clone();
         let basic = FileBasicInformation::decode(&basic).expect("basic info should decode");
         let standard =
             FileStandardInformation::decode(&standard).expect("standard info should decode");
 
         assert_eq!(basic.creation_time, 1);
         assert_eq!(basic.file_attributes, FileAttributes::ARCHIVE);
+        assert_eq!(basic.encode(), basic_bytes);
         assert_eq!(standard.end_of_file, 5);
         assert!(standard.delete_pending);
         assert!(!standard.directory);
     }
 
+    #[test]
+    fn fs_full_size_information_roundtrips_and_reports_unit_size() {
+        let request = QueryInfoRequest::for_fs_info(
+            FileId {
+                persistent: 5,
+                volatile: 6,
+            },
+            FileSystemInfoClass::FullSizeInformation,
+        );
+        let decoded = QueryInfoRequest::decode(&request.encode()).expect("request should decode");
+        assert_eq!(
+            decoded.info_class,
+            InfoClass::FileSystem(FileSystemInfoClass::FullSizeInformation)
+        );
+        assert_eq!(decoded.info_class.info_type(), InfoType::FileSystem);
+
+        let info = FileFsFullSizeInformation {
+            total_allocation_units: 1_000,
+            caller_available_allocation_units: 250,
+            actual_available_allocation_units: 300,
+            sectors_per_allocation_unit: 8,
+            bytes_per_sector: 512,
+        };
+        let encoded = info.encode();
+        assert_eq!(encoded.len(), 32);
+        assert_eq!(
+            FileFsFullSizeInformation::decode(&encoded).expect("info should decode"),
+            info
+        );
+        assert_eq!(info.bytes_per_allocation_unit(), 4096);
+        assert!(FileFsFullSizeInformation::decode(&encoded[..31]).is_err());
+    }
+
+    #[test]
+    fn fs_volume_and_attribute_information_roundtrip() {
+        let volume = FileFsVolumeInformation {
+            volume_creation_time: 132_539_328_000_000_000,
+            volume_serial_number: 0x1234_abcd,
+            supports_objects: true,
+            volume_label: "Backup".to_string(),
+        };
+        let encoded = volume.encode();
+        assert_eq!(encoded.len(), 18 + 12);
+        assert_eq!(
+            FileFsVolumeInformation::decode(&encoded).expect("volume info should decode"),
+            volume
+        );
+        assert!(FileFsVolumeInformation::decode(&encoded[..encoded.len() - 1]).is_err());
+
+        let mut attribute_bytes = FileFsAttributeInformation {
+            file_system_attributes: FileSystemAttributes::CASE_PRESERVED_NAMES
+                | FileSystemAttributes::UNICODE_ON_DISK,
+            maximum_component_name_length: 255,
+            file_system_name: "NTFS".to_string(),
+        }
+        .encode();
+        attribute_bytes[3] |= 0x40;
+        let attributes = FileFsAttributeInformation::decode(&attribute_bytes)
+            .expect("attribute info should decode");
+        assert_eq!(attributes.file_system_name, "NTFS");
+        assert_eq!(attributes.maximum_component_name_length, 255);
+        assert!(attributes
+            .file_system_attributes
+            .contains(FileSystemAttributes::CASE_PRESERVED_NAMES));
+        assert_eq!(attributes.file_system_attributes.bits(), 0x4000_0006);
+    }
+
     #[test]
     fn set_info_roundtrips() {
         let rename = RenameInformation::from_path("notes-renamed.txt", false);
@@ -882,6 +1311,26 @@ mod tests {
         };
         assert_eq!(disposition.encode(), vec![1]);
 
+        let end_of_file = EndOfFileInformation {
+            end_of_file: 0x0102,
+        };
+        assert_eq!(end_of_file.encode(), vec![0x02, 0x01, 0, 0, 0, 0, 0, 0]);
+        let request = SetInfoRequest::for_file_info(
+            FileId {
+                persistent: 5,
+                volatile: 6,
+            },
+            FileInfoClass::EndOfFileInformation,
+            end_of_file.encode(),
+        );
+        let decoded = SetInfoRequest::decode(&request.encode()).expect("request should decode");
+        assert_eq!(decoded, request);
+
+        let allocation = AllocationInformation {
+            allocation_size: 4096,
+        };
+        assert_eq!(allocation.encode(), 4096u64.to_le_bytes().to_vec());
+
         let encoded = SetInfoResponse.encode();
         let decoded = SetInfoResponse::decode(&encoded).expect("response should decode");
         assert_eq!(decoded, SetInfoResponse);
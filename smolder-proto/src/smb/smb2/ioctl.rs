@@ -32,6 +32,8 @@ impl CtlCode {
     pub const FSCTL_QUERY_NETWORK_INTERFACE_INFO: Self = Self(0x0014_01fc);
     /// `FSCTL_VALIDATE_NEGOTIATE_INFO`
     pub const FSCTL_VALIDATE_NEGOTIATE_INFO: Self = Self(0x0014_0204);
+    /// `FSCTL_PIPE_TRANSCEIVE`
+    pub const FSCTL_PIPE_TRANSCEIVE: Self = Self(0x0011_c017);
 }
 
 bitflags! {
@@ -311,6 +313,18 @@ impl IoctlRequest {
         )
     }
 
+    /// Builds `FSCTL_PIPE_TRANSCEIVE`, which writes `input` to a named pipe and
+    /// reads the reply message in one round trip.
+    #[must_use]
+    pub fn pipe_transceive(file_id: FileId, max_output_response: u32, input: Vec<u8>) -> Self {
+        Self::fsctl(
+            CtlCode::FSCTL_PIPE_TRANSCEIVE,
+            file_id,
+            max_output_response,
+            input,
+        )
+    }
+
     /// Builds `FSCTL_LMR_REQUEST_RESILIENCY`.
     #[must_use]
     pub fn request_resiliency(file_id: FileId, timeout: u32) -> Self {
@@ -10,6 +10,7 @@ mod ioctl;
 mod lock;
 mod negotiate;
 mod notify;
+mod oplock;
 mod session;
 mod tree;
 
@@ -18,18 +19,20 @@ use bytes::Buf;
 pub use cancel::CancelRequest;
 pub use create::{
     CloseRequest, CloseResponse, CreateContext, CreateDisposition, CreateOptions, CreateRequest,
-    CreateResponse, DurableHandleFlags, DurableHandleReconnect, DurableHandleReconnectV2,
-    DurableHandleRequest, DurableHandleRequestV2, DurableHandleResponse, DurableHandleResponseV2,
-    FileAttributes, FileId, LeaseFlags, LeaseState, LeaseV2, OplockLevel, RequestedOplockLevel,
-    ShareAccess,
+    CreateResponse, DesiredAccess, DurableHandleFlags, DurableHandleReconnect,
+    DurableHandleReconnectV2, DurableHandleRequest, DurableHandleRequestV2, DurableHandleResponse,
+    DurableHandleResponseV2, FileAttributes, FileId, LeaseFlags, LeaseState, LeaseV2, OplockLevel,
+    RequestedOplockLevel, ShareAccess,
 };
 pub use echo::{EchoRequest, EchoResponse};
 pub use header::{Command, Header, HeaderFlags};
 pub use info::{
-    DirectoryInformationEntry, DispositionInformation, FileBasicInformation, FileInfoClass,
-    FileStandardInformation, InfoType, QueryDirectoryFileInformationClass, QueryDirectoryFlags,
-    QueryDirectoryRequest, QueryDirectoryResponse, QueryInfoRequest, QueryInfoResponse,
-    RenameInformation, SetInfoRequest, SetInfoResponse,
+    AllocationInformation, DirectoryInformationEntry, DispositionInformation, EndOfFileInformation,
+    FileBasicInformation, FileFsAttributeInformation, FileFsFullSizeInformation,
+    FileFsVolumeInformation, FileInfoClass, FileStandardInformation, FileSystemAttributes,
+    FileSystemInfoClass, InfoClass, InfoType, QueryDirectoryFileInformationClass,
+    QueryDirectoryFlags, QueryDirectoryRequest, QueryDirectoryResponse, QueryInfoRequest,
+    QueryInfoResponse, RenameInformation, SetInfoRequest, SetInfoResponse,
 };
 pub use io::{
     FlushRequest, FlushResponse, ReadFlags, ReadRequest, ReadResponse, ReadResponseFlags,
@@ -48,7 +51,11 @@ pub use negotiate::{
     PreauthIntegrityCapabilities, PreauthIntegrityHashId, SigningMode, TransportCapabilities,
     TransportCapabilityFlags,
 };
-pub use notify::{ChangeNotifyFlags, ChangeNotifyRequest, ChangeNotifyResponse, CompletionFilter};
+pub use notify::{
+    ChangeNotifyFlags, ChangeNotifyRequest, ChangeNotifyResponse, CompletionFilter,
+    FileNotifyAction, FileNotifyInformation,
+};
+pub use oplock::{OplockBreak, OPLOCK_BREAK_MESSAGE_ID};
 pub use session::{
     LogoffRequest, LogoffResponse, SessionFlags, SessionSetupRequest, SessionSetupResponse,
     SessionSetupSecurityMode,
@@ -66,6 +73,9 @@ pub const HEADER_LEN: usize = 64;
 /// Protocol identifier for SMB2+ packets.
 pub const PROTOCOL_ID: [u8; 4] = [0xfe, b'S', b'M', b'B'];
 
+/// Protocol identifier for legacy SMB1 packets, which this crate does not decode.
+pub const SMB1_PROTOCOL_ID: [u8; 4] = [0xff, b'S', b'M', b'B'];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 /// A message identifier assigned by the client.
 pub struct MessageId(pub u64);
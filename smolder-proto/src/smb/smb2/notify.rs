@@ -4,7 +4,10 @@ use bitflags::bitflags;
 use bytes::{BufMut, BytesMut};
 
 use super::create::FileId;
-use super::{check_fixed_structure_size, get_u16, get_u32, get_u64, slice_from_offset, HEADER_LEN};
+use super::{
+    check_fixed_structure_size, get_u16, get_u32, get_u64, slice_from_offset, utf16le,
+    utf16le_string, HEADER_LEN,
+};
 use crate::smb::ProtocolError;
 
 bitflags! {
@@ -47,6 +50,58 @@ bitflags! {
     }
 }
 
+/// Change reported by one `FILE_NOTIFY_INFORMATION` record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum FileNotifyAction {
+    /// The file or directory was added.
+    Added = 0x0000_0001,
+    /// The file or directory was removed.
+    Removed = 0x0000_0002,
+    /// The file or directory was modified.
+    Modified = 0x0000_0003,
+    /// The entry was renamed and this is its old name.
+    RenamedOldName = 0x0000_0004,
+    /// The entry was renamed and this is its new name.
+    RenamedNewName = 0x0000_0005,
+    /// A named stream was added.
+    AddedStream = 0x0000_0006,
+    /// A named stream was removed.
+    RemovedStream = 0x0000_0007,
+    /// A named stream was modified.
+    ModifiedStream = 0x0000_0008,
+}
+
+impl TryFrom<u32> for FileNotifyAction {
+    type Error = ProtocolError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0x0000_0001 => Ok(Self::Added),
+            0x0000_0002 => Ok(Self::Removed),
+            0x0000_0003 => Ok(Self::Modified),
+            0x0000_0004 => Ok(Self::RenamedOldName),
+            0x0000_0005 => Ok(Self::RenamedNewName),
+            0x0000_0006 => Ok(Self::AddedStream),
+            0x0000_0007 => Ok(Self::RemovedStream),
+            0x0000_0008 => Ok(Self::ModifiedStream),
+            _ => Err(ProtocolError::InvalidField {
+                field: "action",
+                reason: "unknown file notify action",
+            }),
+        }
+    }
+}
+
+/// One `FILE_NOTIFY_INFORMATION` record returned by `CHANGE_NOTIFY`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileNotifyInformation {
+    /// Kind of change.
+    pub action: FileNotifyAction,
+    /// Path of the changed entry relative to the watched directory.
+    pub file_name: String,
+}
+
 /// SMB2 change-notify request body.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChangeNotifyRequest {
@@ -130,6 +185,32 @@ pub struct ChangeNotifyResponse {
 }
 
 impl ChangeNotifyResponse {
+    /// Builds a response carrying the given `FILE_NOTIFY_INFORMATION` records.
+    #[must_use]
+    pub fn from_notifications(notifications: &[FileNotifyInformation]) -> Self {
+        let mut out = BytesMut::new();
+        for (index, notification) in notifications.iter().enumerate() {
+            let file_name = utf16le(&notification.file_name);
+            let entry_len = 12 + file_name.len();
+            let padded_len = (entry_len + 3) & !3;
+            let next_entry_offset = if index + 1 == notifications.len() {
+                0
+            } else {
+                padded_len as u32
+            };
+            out.put_u32_le(next_entry_offset);
+            out.put_u32_le(notification.action as u32);
+            out.put_u32_le(file_name.len() as u32);
+            out.extend_from_slice(&file_name);
+            if next_entry_offset != 0 {
+                out.put_bytes(0, padded_len - entry_len);
+            }
+        }
+        Self {
+            output_buffer: out.to_vec(),
+        }
+    }
+
     /// Serializes the response body.
     #[must_use]
     pub fn encode(&self) -> Vec<u8> {
@@ -165,11 +246,48 @@ impl ChangeNotifyResponse {
         };
         Ok(Self { output_buffer })
     }
+
+    /// Decodes all returned `FILE_NOTIFY_INFORMATION` records.
+    ///
+    /// An empty buffer means the server overflowed its change buffer; the
+    /// caller should rescan the directory.
+    pub fn notifications(&self) -> Result<Vec<FileNotifyInformation>, ProtocolError> {
+        let mut notifications = Vec::new();
+        let mut cursor = self.output_buffer.as_slice();
+
+        while !cursor.is_empty() {
+            let mut input = cursor;
+            let next_entry_offset = get_u32(&mut input, "next_entry_offset")? as usize;
+            let action = FileNotifyAction::try_from(get_u32(&mut input, "action")?)?;
+            let file_name_length = get_u32(&mut input, "file_name_length")? as usize;
+            if file_name_length > input.len() {
+                return Err(ProtocolError::UnexpectedEof { field: "file_name" });
+            }
+            let file_name = utf16le_string(&input[..file_name_length])?;
+            notifications.push(FileNotifyInformation { action, file_name });
+
+            if next_entry_offset == 0 {
+                break;
+            }
+            if next_entry_offset < 12 + file_name_length || next_entry_offset > cursor.len() {
+                return Err(ProtocolError::InvalidField {
+                    field: "next_entry_offset",
+                    reason: "notify record extends past buffer",
+                });
+            }
+            cursor = &cursor[next_entry_offset..];
+        }
+
+        Ok(notifications)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{ChangeNotifyFlags, ChangeNotifyRequest, ChangeNotifyResponse, CompletionFilter};
+    use super::{
+        ChangeNotifyFlags, ChangeNotifyRequest, ChangeNotifyResponse, CompletionFilter,
+        FileNotifyAction, FileNotifyInformation,
+    };
     use crate::smb::smb2::FileId;
 
     #[test]
@@ -203,4 +321,48 @@ mod tests {
             ChangeNotifyResponse::decode(&encoded).expect("change notify response should decode");
         assert_eq!(decoded, response);
     }
+
+    #[test]
+    fn change_notify_response_decodes_notify_records() {
+        let notifications = vec![
+            FileNotifyInformation {
+                action: FileNotifyAction::Added,
+                file_name: "new.txt".to_owned(),
+            },
+            FileNotifyInformation {
+                action: FileNotifyAction::RenamedOldName,
+                file_name: "docs\\a.txt".to_owned(),
+            },
+            FileNotifyInformation {
+                action: FileNotifyAction::RenamedNewName,
+                file_name: "docs\\b.txt".to_owned(),
+            },
+        ];
+
+        let response = ChangeNotifyResponse::from_notifications(&notifications);
+        assert_eq!(response.output_buffer[..4], 28u32.to_le_bytes());
+        assert_eq!(
+            response
+                .notifications()
+                .expect("notify records should decode"),
+            notifications
+        );
+    }
+
+    #[test]
+    fn change_notify_response_rejects_truncated_records() {
+        let mut response = ChangeNotifyResponse::from_notifications(&[FileNotifyInformation {
+            action: FileNotifyAction::Modified,
+            file_name: "report.txt".to_owned(),
+        }]);
+        response.output_buffer.truncate(16);
+
+        assert!(response.notifications().is_err());
+        assert!(ChangeNotifyResponse {
+            output_buffer: Vec::new(),
+        }
+        .notifications()
+        .expect("empty buffer should decode")
+        .is_empty());
+    }
 }
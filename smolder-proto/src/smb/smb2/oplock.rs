@@ -0,0 +1,90 @@
+//! SMB2 oplock break notification and acknowledgment bodies.
+
+use bytes::{BufMut, BytesMut};
+
+use super::create::{FileId, OplockLevel};
+use super::{check_fixed_structure_size, get_u16, get_u32, get_u64, get_u8};
+use crate::smb::ProtocolError;
+
+/// Message identifier the server uses for unsolicited oplock break notifications.
+pub const OPLOCK_BREAK_MESSAGE_ID: u64 = u64::MAX;
+
+/// `OPLOCK_BREAK` body shared by the server notification, the client
+/// acknowledgment, and the server's response to that acknowledgment.
+///
+/// In a notification `oplock_level` is the level the server is breaking the
+/// oplock to; in an acknowledgment it is the level the client accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OplockBreak {
+    /// Oplock level after the break.
+    pub oplock_level: OplockLevel,
+    /// File whose oplock is being broken.
+    pub file_id: FileId,
+}
+
+impl OplockBreak {
+    /// Serializes the body.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = BytesMut::with_capacity(24);
+        out.put_u16_le(24);
+        out.put_u8(self.oplock_level as u8);
+        out.put_u8(0);
+        out.put_u32_le(0);
+        out.put_u64_le(self.file_id.persistent);
+        out.put_u64_le(self.file_id.volatile);
+        out.to_vec()
+    }
+
+    /// Parses the body.
+    pub fn decode(body: &[u8]) -> Result<Self, ProtocolError> {
+        let mut input = body;
+        check_fixed_structure_size(get_u16(&mut input, "structure_size")?, 24, "structure_size")?;
+        let oplock_level = OplockLevel::try_from(get_u8(&mut input, "oplock_level")?)?;
+        let _reserved = get_u8(&mut input, "reserved")?;
+        let _reserved2 = get_u32(&mut input, "reserved2")?;
+        let file_id = FileId {
+            persistent: get_u64(&mut input, "persistent_file_id")?,
+            volatile: get_u64(&mut input, "volatile_file_id")?,
+        };
+        Ok(Self {
+            oplock_level,
+            file_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OplockBreak;
+    use crate::smb::smb2::{FileId, OplockLevel};
+
+    #[test]
+    fn oplock_break_roundtrips() {
+        let notification = OplockBreak {
+            oplock_level: OplockLevel::II,
+            file_id: FileId {
+                persistent: 0x1122_3344_5566_7788,
+                volatile: 0x8877_6655_4433_2211,
+            },
+        };
+
+        let encoded = notification.encode();
+        assert_eq!(encoded.len(), 24);
+        assert_eq!(encoded[2], 0x01);
+        let decoded = OplockBreak::decode(&encoded).expect("oplock break should decode");
+        assert_eq!(decoded, notification);
+    }
+
+    #[test]
+    fn oplock_break_rejects_unknown_levels() {
+        let mut encoded = OplockBreak {
+            oplock_level: OplockLevel::None,
+            file_id: FileId::NONE,
+        }
+        .encode();
+        encoded[2] = 0x42;
+
+        OplockBreak::decode(&encoded).expect_err("unknown level should be rejected");
+    }
+}
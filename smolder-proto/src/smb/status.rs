@@ -1,5 +1,7 @@
 //! NTSTATUS values used by the SMB client.
 
+use std::fmt;
+
 /// A 32-bit NTSTATUS code carried in SMB headers.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct NtStatus(pub u32);
@@ -11,12 +13,22 @@ impl NtStatus {
     pub const NOT_IMPLEMENTED: Self = Self(0xc000_0002);
     /// `STATUS_PENDING`
     pub const PENDING: Self = Self(0x0000_0103);
+    /// `STATUS_NOTIFY_ENUM_DIR`
+    pub const NOTIFY_ENUM_DIR: Self = Self(0x0000_010c);
     /// `STATUS_MORE_PROCESSING_REQUIRED`
     pub const MORE_PROCESSING_REQUIRED: Self = Self(0xc000_0016);
     /// `STATUS_NO_MORE_FILES`
     pub const NO_MORE_FILES: Self = Self(0x8000_0006);
+    /// `STATUS_BUFFER_OVERFLOW`
+    pub const BUFFER_OVERFLOW: Self = Self(0x8000_0005);
+    /// `STATUS_NO_SUCH_FILE`
+    pub const NO_SUCH_FILE: Self = Self(0xc000_000f);
     /// `STATUS_END_OF_FILE`
     pub const END_OF_FILE: Self = Self(0xc000_0011);
+    /// `STATUS_INVALID_HANDLE`
+    pub const INVALID_HANDLE: Self = Self(0xc000_0008);
+    /// `STATUS_INVALID_PARAMETER`
+    pub const INVALID_PARAMETER: Self = Self(0xc000_000d);
     /// `STATUS_OBJECT_NAME_NOT_FOUND`
     pub const OBJECT_NAME_NOT_FOUND: Self = Self(0xc000_0034);
     /// `STATUS_OBJECT_PATH_NOT_FOUND`
@@ -25,10 +37,24 @@ impl NtStatus {
     pub const PATH_NOT_COVERED: Self = Self(0xc000_0257);
     /// `STATUS_NOT_FOUND`
     pub const NOT_FOUND: Self = Self(0xc000_0225);
+    /// `STATUS_SHARING_VIOLATION`
+    pub const SHARING_VIOLATION: Self = Self(0xc000_0043);
     /// `STATUS_OBJECT_NAME_COLLISION`
     pub const OBJECT_NAME_COLLISION: Self = Self(0xc000_0035);
     /// `STATUS_ACCESS_DENIED`
     pub const ACCESS_DENIED: Self = Self(0xc000_0022);
+    /// `STATUS_CANNOT_DELETE`
+    pub const CANNOT_DELETE: Self = Self(0xc000_0121);
+    /// `STATUS_DIRECTORY_NOT_EMPTY`
+    pub const DIRECTORY_NOT_EMPTY: Self = Self(0xc000_0101);
+    /// `STATUS_FILE_LOCK_CONFLICT`
+    pub const FILE_LOCK_CONFLICT: Self = Self(0xc000_0054);
+    /// `STATUS_LOCK_NOT_GRANTED`
+    pub const LOCK_NOT_GRANTED: Self = Self(0xc000_0055);
+    /// `STATUS_RANGE_NOT_LOCKED`
+    pub const RANGE_NOT_LOCKED: Self = Self(0xc000_007e);
+    /// `STATUS_CANCELLED`
+    pub const CANCELLED: Self = Self(0xc000_0120);
     /// `STATUS_LOGON_FAILURE`
     pub const LOGON_FAILURE: Self = Self(0xc000_006d);
     /// `STATUS_INVALID_NETWORK_RESPONSE`
@@ -51,6 +77,53 @@ impl NtStatus {
     pub const fn is_success(self) -> bool {
         self.0 == Self::SUCCESS.0
     }
+
+    /// Returns the symbolic `STATUS_*` name for codes this crate knows about.
+    #[must_use]
+    pub const fn name(self) -> Option<&'static str> {
+        let name = match self {
+            Self::SUCCESS => "STATUS_SUCCESS",
+            Self::NOT_IMPLEMENTED => "STATUS_NOT_IMPLEMENTED",
+            Self::PENDING => "STATUS_PENDING",
+            Self::NOTIFY_ENUM_DIR => "STATUS_NOTIFY_ENUM_DIR",
+            Self::MORE_PROCESSING_REQUIRED => "STATUS_MORE_PROCESSING_REQUIRED",
+            Self::NO_MORE_FILES => "STATUS_NO_MORE_FILES",
+            Self::BUFFER_OVERFLOW => "STATUS_BUFFER_OVERFLOW",
+            Self::NO_SUCH_FILE => "STATUS_NO_SUCH_FILE",
+            Self::END_OF_FILE => "STATUS_END_OF_FILE",
+            Self::INVALID_HANDLE => "STATUS_INVALID_HANDLE",
+            Self::INVALID_PARAMETER => "STATUS_INVALID_PARAMETER",
+            Self::OBJECT_NAME_NOT_FOUND => "STATUS_OBJECT_NAME_NOT_FOUND",
+            Self::OBJECT_PATH_NOT_FOUND => "STATUS_OBJECT_PATH_NOT_FOUND",
+            Self::PATH_NOT_COVERED => "STATUS_PATH_NOT_COVERED",
+            Self::NOT_FOUND => "STATUS_NOT_FOUND",
+            Self::SHARING_VIOLATION => "STATUS_SHARING_VIOLATION",
+            Self::OBJECT_NAME_COLLISION => "STATUS_OBJECT_NAME_COLLISION",
+            Self::ACCESS_DENIED => "STATUS_ACCESS_DENIED",
+            Self::CANNOT_DELETE => "STATUS_CANNOT_DELETE",
+            Self::DIRECTORY_NOT_EMPTY => "STATUS_DIRECTORY_NOT_EMPTY",
+            Self::FILE_LOCK_CONFLICT => "STATUS_FILE_LOCK_CONFLICT",
+            Self::LOCK_NOT_GRANTED => "STATUS_LOCK_NOT_GRANTED",
+            Self::RANGE_NOT_LOCKED => "STATUS_RANGE_NOT_LOCKED",
+            Self::CANCELLED => "STATUS_CANCELLED",
+            Self::LOGON_FAILURE => "STATUS_LOGON_FAILURE",
+            Self::INVALID_NETWORK_RESPONSE => "STATUS_INVALID_NETWORK_RESPONSE",
+            Self::PIPE_NOT_AVAILABLE => "STATUS_PIPE_NOT_AVAILABLE",
+            Self::PIPE_BROKEN => "STATUS_PIPE_BROKEN",
+            Self::FS_DRIVER_REQUIRED => "STATUS_FS_DRIVER_REQUIRED",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+impl fmt::Display for NtStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name} (0x{:08x})", self.0),
+            None => write!(f, "0x{:08x}", self.0),
+        }
+    }
 }
 
 impl From<u32> for NtStatus {
@@ -64,3 +137,27 @@ impl From<NtStatus> for u32 {
         value.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::NtStatus;
+
+    #[test]
+    fn known_statuses_display_with_their_symbolic_name() {
+        assert_eq!(
+            NtStatus::SHARING_VIOLATION.name(),
+            Some("STATUS_SHARING_VIOLATION")
+        );
+        assert_eq!(
+            NtStatus::ACCESS_DENIED.to_string(),
+            "STATUS_ACCESS_DENIED (0xc0000022)"
+        );
+    }
+
+    #[test]
+    fn unknown_statuses_keep_the_raw_code() {
+        let status = NtStatus::from(0xc000_9999);
+        assert_eq!(status.name(), None);
+        assert_eq!(status.to_string(), "0xc0009999");
+    }
+}
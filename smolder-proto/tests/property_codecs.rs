@@ -5,9 +5,13 @@ use smolder_proto::rpc::{
 };
 use smolder_proto::smb::netbios::SessionMessage;
 use smolder_proto::smb::smb2::{
-    ChangeNotifyRequest, Command, CreateRequest, Header, HeaderFlags, IoctlRequest, LockRequest,
-    MessageId, NegotiateRequest, ReadRequest, SessionId, SessionSetupRequest, TreeConnectRequest,
-    TreeId, WriteRequest, utf16le, utf16le_string,
+    ChangeNotifyRequest, ChangeNotifyResponse, CloseResponse, Command, CreateRequest,
+    CreateResponse, Dialect, GlobalCapabilities, Header, HeaderFlags, IoctlRequest, IoctlResponse,
+    LockRequest, MessageId, NegotiateContext, NegotiateRequest, NegotiateResponse,
+    PreauthIntegrityCapabilities, PreauthIntegrityHashId, QueryDirectoryResponse,
+    QueryInfoResponse, ReadRequest, ReadResponse, SessionId, SessionSetupRequest,
+    SessionSetupResponse, SigningMode, TreeConnectRequest, TreeConnectResponse, TreeId,
+    WriteRequest, WriteResponse, utf16le, utf16le_string,
 };
 use smolder_proto::smb::transform::{TransformHeader, TransformValue};
 use smolder_proto::smb::ProtocolError;
@@ -171,6 +175,7 @@ proptest! {
         raw_flags in any::<u8>(),
         next_command in any::<u32>(),
         async_id in any::<u64>(),
+        process_id in any::<u32>(),
         tree_id in any::<u32>(),
         session_id in any::<u64>(),
         signature in prop::array::uniform16(any::<u8>()),
@@ -186,6 +191,7 @@ proptest! {
             next_command,
             message_id: MessageId(message_id),
             async_id: is_async.then_some(smolder_proto::smb::smb2::AsyncId(async_id)),
+            process_id: if is_async { 0 } else { process_id },
             tree_id: if is_async { TreeId(0) } else { TreeId(tree_id) },
             session_id: SessionId(session_id),
             signature,
@@ -325,6 +331,155 @@ proptest! {
         let _ = ChangeNotifyRequest::decode(&bytes);
         let _ = LockRequest::decode(&bytes);
     }
+
+    #[test]
+    fn malformed_bytes_do_not_panic_across_response_decoders(
+        bytes in prop::collection::vec(any::<u8>(), 0..1024),
+    ) {
+        let _ = NegotiateResponse::decode(&bytes);
+        let _ = SessionSetupResponse::decode(&bytes);
+        let _ = TreeConnectResponse::decode(&bytes);
+        let _ = CreateResponse::decode(&bytes);
+        let _ = CloseResponse::decode(&bytes);
+        let _ = ReadResponse::decode(&bytes);
+        let _ = WriteResponse::decode(&bytes);
+        let _ = QueryInfoResponse::decode(&bytes);
+        let _ = ChangeNotifyResponse::decode(&bytes);
+        if let Ok(response) = QueryDirectoryResponse::decode(&bytes) {
+            let _ = response.directory_entries();
+        }
+        if let Ok(response) = IoctlResponse::decode(&bytes) {
+            let _ = response.dfs_referral_response();
+        }
+    }
+
+    #[test]
+    fn corrupted_negotiate_responses_do_not_panic(
+        edits in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+        truncate in any::<prop::sample::Index>(),
+    ) {
+        let mut bytes = negotiate_response_bytes();
+        for (index, value) in edits {
+            let offset = index.index(bytes.len());
+            bytes[offset] = value;
+        }
+        let _ = NegotiateResponse::decode(&bytes);
+        let _ = NegotiateResponse::decode(&bytes[..truncate.index(bytes.len())]);
+    }
+
+    #[test]
+    fn corrupted_directory_listings_do_not_panic(
+        edits in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+        truncate in any::<prop::sample::Index>(),
+    ) {
+        let mut bytes = query_directory_response_bytes();
+        for (index, value) in edits {
+            let offset = index.index(bytes.len());
+            bytes[offset] = value;
+        }
+        let _ = directory_listing(&bytes);
+        let _ = directory_listing(&bytes[..truncate.index(bytes.len())]);
+    }
+}
+
+fn negotiate_response_bytes() -> Vec<u8> {
+    NegotiateResponse {
+        security_mode: SigningMode::ENABLED,
+        dialect_revision: Dialect::Smb311,
+        negotiate_contexts: vec![NegotiateContext::preauth_integrity(
+            PreauthIntegrityCapabilities {
+                hash_algorithms: vec![PreauthIntegrityHashId::Sha512],
+                salt: vec![0xaa; 34],
+            },
+        )],
+        server_guid: [0x11; 16],
+        capabilities: GlobalCapabilities::LARGE_MTU,
+        max_transact_size: 65_536,
+        max_read_size: 65_536,
+        max_write_size: 65_536,
+        system_time: 1,
+        server_start_time: 0,
+        security_buffer: vec![0x60; 12],
+    }
+    .encode()
+}
+
+fn directory_entry_bytes(name: &str, next_entry_offset: u32) -> Vec<u8> {
+    let file_name = utf16le(name);
+    let mut entry = Vec::new();
+    entry.extend_from_slice(&next_entry_offset.to_le_bytes());
+    entry.extend_from_slice(&[0; 52]);
+    entry.extend_from_slice(&0x20u32.to_le_bytes());
+    entry.extend_from_slice(&(file_name.len() as u32).to_le_bytes());
+    entry.extend_from_slice(&file_name);
+    entry
+}
+
+fn query_directory_response_bytes() -> Vec<u8> {
+    let mut output_buffer = directory_entry_bytes("a.txt", 80);
+    output_buffer.resize(80, 0);
+    output_buffer.extend_from_slice(&directory_entry_bytes("b.txt", 0));
+    QueryDirectoryResponse { output_buffer }.encode()
+}
+
+fn directory_listing(bytes: &[u8]) -> Result<usize, ProtocolError> {
+    Ok(QueryDirectoryResponse::decode(bytes)?
+        .directory_entries()?
+        .len())
+}
+
+#[test]
+fn negotiate_response_rejects_truncated_and_oversized_lengths() {
+    let valid = negotiate_response_bytes();
+    let decoded = NegotiateResponse::decode(&valid).expect("valid response should decode");
+    assert_eq!(decoded.negotiate_contexts.len(), 1);
+
+    for len in 0..valid.len() {
+        assert!(
+            NegotiateResponse::decode(&valid[..len]).is_err(),
+            "truncation to {len} bytes should be rejected"
+        );
+    }
+
+    let mut oversized_security = valid.clone();
+    oversized_security[58..60].copy_from_slice(&u16::MAX.to_le_bytes());
+    assert!(NegotiateResponse::decode(&oversized_security).is_err());
+
+    let mut oversized_context_count = valid.clone();
+    oversized_context_count[6..8].copy_from_slice(&u16::MAX.to_le_bytes());
+    assert!(NegotiateResponse::decode(&oversized_context_count).is_err());
+
+    let mut oversized_context_offset = valid;
+    oversized_context_offset[60..64].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(NegotiateResponse::decode(&oversized_context_offset).is_err());
+}
+
+#[test]
+fn directory_listing_rejects_truncated_and_oversized_lengths() {
+    let valid = query_directory_response_bytes();
+    assert_eq!(
+        directory_listing(&valid).expect("valid listing should decode"),
+        2
+    );
+
+    for len in 0..valid.len() {
+        assert!(
+            directory_listing(&valid[..len]).is_err(),
+            "truncation to {len} bytes should be rejected"
+        );
+    }
+
+    let mut oversized_buffer = valid.clone();
+    oversized_buffer[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(directory_listing(&oversized_buffer).is_err());
+
+    let mut oversized_next_entry = valid.clone();
+    oversized_next_entry[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(directory_listing(&oversized_next_entry).is_err());
+
+    let mut oversized_name = valid;
+    oversized_name[68..72].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(directory_listing(&oversized_name).is_err());
 }
 
 #[test]
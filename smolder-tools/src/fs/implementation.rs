@@ -57,8 +57,6 @@ const DELETE: u32 = 0x0001_0000;
 const READ_CONTROL: u32 = 0x0002_0000;
 const SYNCHRONIZE: u32 = 0x0010_0000;
 const FILE_LIST_DIRECTORY: u32 = 0x0000_0001;
-const WINDOWS_TICK: u64 = 10_000_000;
-const SEC_TO_UNIX_EPOCH: u64 = 11_644_473_600;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum CreateTarget {
@@ -1147,6 +1145,7 @@ mod tests {
 
     use async_trait::async_trait;
     use smolder_core::dfs::{DfsReferral, UncPath};
+    use smolder_proto::smb::filetime::{SECONDS_TO_UNIX_EPOCH, TICKS_PER_SECOND};
     use smolder_proto::smb::netbios::SessionMessage;
     use smolder_proto::smb::smb2::{
         CipherId, CloseResponse, Command, CreateContext, CreateDisposition, CreateOptions,
@@ -1154,7 +1153,7 @@ mod tests {
         DfsReferralRequest, Dialect, DirectoryInformationEntry, DurableHandleFlags,
         DurableHandleResponseV2, FileAttributes, FileBasicInformation, FileId, FileInfoClass,
         FileStandardInformation, FlushRequest, FlushResponse, GlobalCapabilities, Header,
-        IoctlRequest, IoctlResponse, LeaseState, LeaseV2, MessageId, NegotiateRequest,
+        InfoClass, IoctlRequest, IoctlResponse, LeaseState, LeaseV2, MessageId, NegotiateRequest,
         NegotiateResponse, NetworkResiliencyRequest, OplockLevel, QueryDirectoryFlags,
         QueryDirectoryRequest, QueryDirectoryResponse, QueryInfoRequest, QueryInfoResponse,
         ReadRequest, ReadResponse, ReadResponseFlags, RequestedOplockLevel, SessionFlags,
@@ -2598,10 +2597,10 @@ mod tests {
         };
         let basic_info = QueryInfoResponse {
             output_buffer: encode_basic_info(FileBasicInformation {
-                creation_time: super::SEC_TO_UNIX_EPOCH * super::WINDOWS_TICK + 1,
-                last_access_time: super::SEC_TO_UNIX_EPOCH * super::WINDOWS_TICK + 2,
-                last_write_time: super::SEC_TO_UNIX_EPOCH * super::WINDOWS_TICK + 3,
-                change_time: super::SEC_TO_UNIX_EPOCH * super::WINDOWS_TICK + 4,
+                creation_time: SECONDS_TO_UNIX_EPOCH * TICKS_PER_SECOND + 1,
+                last_access_time: SECONDS_TO_UNIX_EPOCH * TICKS_PER_SECOND + 2,
+                last_write_time: SECONDS_TO_UNIX_EPOCH * TICKS_PER_SECOND + 3,
+                change_time: SECONDS_TO_UNIX_EPOCH * TICKS_PER_SECOND + 4,
                 file_attributes: FileAttributes::ARCHIVE,
             }),
         };
@@ -2659,12 +2658,15 @@ mod tests {
 
         let writes = transport_writes(share);
         let first_query = outbound_query_info(&writes[4]);
-        assert_eq!(first_query.file_info_class, FileInfoClass::BasicInformation);
+        assert_eq!(
+            first_query.info_class,
+            InfoClass::File(FileInfoClass::BasicInformation)
+        );
 
         let second_query = outbound_query_info(&writes[5]);
         assert_eq!(
-            second_query.file_info_class,
-            FileInfoClass::StandardInformation
+            second_query.info_class,
+            InfoClass::File(FileInfoClass::StandardInformation)
         );
     }
 
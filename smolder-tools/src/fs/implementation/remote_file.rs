@@ -62,6 +62,7 @@ impl<'a, T> RemoteFile<'a, T>
 where
     T: Transport + Send,
 {
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn new(
         share: &'a mut Share<T>,
         connection: Connection<T, TreeConnected>,
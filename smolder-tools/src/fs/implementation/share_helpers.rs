@@ -1,9 +1,10 @@
 use std::future::Future;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::SystemTime;
 
 use smolder_core::dfs::{referrals_from_response, resolve_unc_path, DfsReferral, UncPath};
 use smolder_core::error::CoreError;
 use smolder_core::transport::Transport;
+use smolder_proto::smb::filetime::filetime_to_system_time;
 use smolder_proto::smb::smb2::{
     Command, DfsReferralRequest, DirectoryInformationEntry, FileAttributes, FileBasicInformation,
     FileStandardInformation, IoctlRequest,
@@ -12,7 +13,7 @@ use smolder_proto::smb::status::NtStatus;
 
 use super::{
     Share, SmbClient, SmbDirectoryEntry, SmbMetadata, DEFAULT_DFS_REFERRAL_MAX_HOPS,
-    DEFAULT_DFS_REFERRAL_MAX_RESPONSE, SEC_TO_UNIX_EPOCH, WINDOWS_TICK,
+    DEFAULT_DFS_REFERRAL_MAX_RESPONSE,
 };
 
 pub(super) fn normalize_share_name(share: &str) -> Result<String, CoreError> {
@@ -99,12 +100,7 @@ pub(super) fn directory_entry_from_query(entry: DirectoryInformationEntry) -> Sm
 }
 
 fn system_time_from_windows_ticks(value: u64) -> Option<SystemTime> {
-    if value == 0 {
-        return None;
-    }
-
-    let unix_ticks = value.checked_sub(SEC_TO_UNIX_EPOCH * WINDOWS_TICK)?;
-    Some(UNIX_EPOCH + Duration::from_nanos(unix_ticks.saturating_mul(100)))
+    (value != 0).then(|| filetime_to_system_time(value))
 }
 
 pub(super) fn parse_unc_share(unc: &str) -> Result<(String, String), CoreError> {
@@ -759,12 +759,10 @@ impl RemoteExecClient {
             let _ = admin.try_remove(&command_paths.debug_relative).await;
             let _ = admin.try_remove(&command_paths.runner_relative).await;
             let _ = admin.try_remove(&command_paths.script_relative).await;
-            if matches!(mode, ExecMode::PsExec) {
-                if self.psexec_service_binary.is_some() {
-                    let _ = admin
-                        .try_remove(&command_paths.service_binary_relative)
-                        .await;
-                }
+            if matches!(mode, ExecMode::PsExec) && self.psexec_service_binary.is_some() {
+                let _ = admin
+                    .try_remove(&command_paths.service_binary_relative)
+                    .await;
             }
         }
 
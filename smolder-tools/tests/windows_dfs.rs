@@ -101,9 +101,7 @@ fn connected_builder() -> Option<(
         );
         return None;
     };
-    let Some(root) = config.dfs_root_path() else {
-        return None;
-    };
+    let root = config.dfs_root_path()?;
 
     let builder = config.builder(&root);
     Some((config, root, builder))